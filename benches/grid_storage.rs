@@ -0,0 +1,59 @@
+// Parcourir un grand niveau presque vide, rangé en dense puis en sparse
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use svnake::game::{Cell, Grid, Rng};
+
+const DIMENSIONS: (isize, isize, isize) = (200, 200, 20);
+const OCCUPANCY: f64 = 0.01;
+const ROUNDS: u32 = 20;
+
+fn main() {
+    let dense = floating_islands();
+    let sparse = dense.clone().into_sparse();
+    println!(
+        "{}x{}x{}, {} cells that aren't void",
+        DIMENSIONS.0,
+        DIMENSIONS.1,
+        DIMENSIONS.2,
+        dense.iter_occupied().count()
+    );
+    for (name, grid) in [("dense", &dense), ("sparse", &sparse)] {
+        report(name, "iter", time(|| grid.iter().count()));
+        report(name, "iter_occupied", time(|| grid.iter_occupied().count()));
+    }
+}
+
+// des cellules posées au hasard dans le vide, toujours les mêmes
+fn floating_islands() -> Grid {
+    let (mx, my, mz) = DIMENSIONS;
+    let volume = (mx * my * mz) as usize;
+    let mut grid = Grid::new(DIMENSIONS, vec![Cell::Void; volume]).unwrap();
+    let mut rng = Rng::new(0);
+    for _ in 0..(volume as f64 * OCCUPANCY) as usize {
+        let coord = (
+            rng.between(0, mx - 1),
+            rng.between(0, my - 1),
+            rng.between(0, mz - 1),
+        );
+        grid[coord] = Cell::Block;
+    }
+    grid
+}
+
+// le temps moyen d'un parcours
+fn time(mut run: impl FnMut() -> usize) -> Duration {
+    black_box(run());
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        black_box(run());
+    }
+    start.elapsed() / ROUNDS
+}
+
+fn report(storage: &str, what: &str, time: Duration) {
+    println!("{storage:>6} {what:<14} {time:?}");
+}
@@ -0,0 +1,47 @@
+// Le coût d'un tick pour un serpent de 10 000 segments, à côté de celui d'un serpent de 10
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use svnake::game::{Cell, Direction, GameConfig, GameState, Grid};
+
+const LENGTHS: [usize; 2] = [10, 10_000];
+const TICKS: usize = 2000;
+
+fn main() {
+    for length in LENGTHS {
+        let time = time_ticks(length);
+        println!("{length:>6} segments {time:?} per tick");
+    }
+}
+
+// le serpent couché sur la deuxième rangée, la tête à l'est, qui zigzague vers l'est,
+// sur la même grille quelle que soit sa longueur
+fn time_ticks(length: usize) -> Duration {
+    let width = (10_000 + TICKS) as isize;
+    let mut cells = vec![Cell::Block; width as usize * 2];
+    cells.extend(vec![Cell::Empty; width as usize * 2]);
+    let mut grid = Grid::new((width, 2, 2), cells).unwrap();
+    // hors d'atteinte, sinon le niveau est déjà fini
+    grid[(width - 1, 0, 1)] = Cell::Food;
+    let config = GameConfig {
+        starting_length: length,
+        starting_facing: Direction::East,
+        ..GameConfig::default()
+    };
+    let mut game = GameState::new((length as isize - 1, 1, 1), grid, &config).unwrap();
+    let zigzag = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::East,
+    ];
+    let start = Instant::now();
+    for dir in zigzag.into_iter().cycle().take(TICKS) {
+        game.queue_input(dir);
+        black_box(game.update()).unwrap();
+    }
+    start.elapsed() / TICKS as u32
+}
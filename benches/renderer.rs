@@ -0,0 +1,82 @@
+// Dessiner la même partie frame après frame, avec et sans le Renderer
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use ruscii::{drawing::Pencil, spatial::Vec2};
+use svnake::game::{
+    Align, Cell, Direction, GameConfig, GameState, Grid, Renderer, StringCanvas, View,
+};
+
+const DIMENSIONS: (isize, isize, isize) = (60, 60, 4);
+const SCREEN: Vec2 = Vec2 { x: 260, y: 140 };
+const FRAMES: u32 = 200;
+
+fn main() {
+    report(
+        "draw_view",
+        frames(|game, pencil, view| {
+            game.draw_view(pencil, view);
+        }),
+    );
+    let mut renderer = Renderer::default();
+    report(
+        "renderer",
+        frames(|game, pencil, view| {
+            renderer.draw(game, pencil, view);
+        }),
+    );
+    let mut renderer = Renderer::default();
+    report(
+        "renderer, redrawn from scratch",
+        frames(|game, pencil, view| {
+            renderer.invalidate();
+            renderer.draw(game, pencil, view);
+        }),
+    );
+}
+
+// un plateau sur deux étages de blocs, et le serpent qui tourne en rond dessus, un tick par frame
+fn frames(mut draw: impl FnMut(&GameState, &mut Pencil, View)) -> Duration {
+    let (mx, my, _) = DIMENSIONS;
+    let mut grid = Grid::empty(DIMENSIONS);
+    for x in 0..mx {
+        for y in 0..my {
+            grid[(x, y, 0)] = Cell::Block;
+            grid[(x, y, 1)] = Cell::Block;
+        }
+    }
+    grid[(mx - 1, my - 1, 2)] = Cell::Food;
+    let mut game = GameState::new((0, 0, 2), grid, &GameConfig::default()).unwrap();
+    let mut canvas = StringCanvas::new(SCREEN);
+    let origin = game
+        .grid()
+        .board_origin(SCREEN, Align::Center, View::default().projection);
+    let circle = [
+        Direction::East,
+        Direction::South,
+        Direction::West,
+        Direction::North,
+    ];
+    let start = Instant::now();
+    for (frame, dir) in circle.into_iter().cycle().take(FRAMES as usize).enumerate() {
+        game.queue_input(dir);
+        game.update().unwrap();
+        canvas.clear();
+        let mut pencil = canvas.pencil();
+        pencil.set_origin(origin);
+        let view = View {
+            frame,
+            ..View::default()
+        };
+        draw(&game, &mut pencil, view);
+        black_box(canvas.char_at(Vec2::zero()));
+    }
+    start.elapsed() / FRAMES
+}
+
+fn report(what: &str, time: Duration) {
+    println!("{what:<31} {time:?} per frame");
+}
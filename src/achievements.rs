@@ -0,0 +1,302 @@
+// Des objectifs qui se débloquent en jouant et qui restent d'une partie à l'autre
+
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use ruscii::{drawing::Pencil, spatial::Vec2, terminal::Color};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::GameEvent;
+use crate::scores::data_dir;
+use crate::stats::Turns;
+
+const LONG_SNAKE: usize = 20;
+const LONG_FALL: u32 = 5; // cells fallen in a row
+const MAX_TURNS: u32 = 10;
+const FEAST_MEALS: usize = 3;
+const FEAST_TICKS: u32 = 15; // for all of them, the first one's tick included
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Achievement {
+    LongSnake,
+    LongFall,
+    FewTurns,
+    Feast,
+}
+
+impl Achievement {
+    /// Every achievement, in the order they're listed.
+    pub const ALL: [Achievement; 4] = [
+        Achievement::LongSnake,
+        Achievement::LongFall,
+        Achievement::FewTurns,
+        Achievement::Feast,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::LongSnake => "World serpent",
+            Achievement::LongFall => "Soft landing",
+            Achievement::FewTurns => "Straight to the point",
+            Achievement::Feast => "Feast",
+        }
+    }
+
+    pub fn description(self) -> String {
+        match self {
+            Achievement::LongSnake => format!("Grow {LONG_SNAKE} segments long"),
+            Achievement::LongFall => format!("Fall {LONG_FALL} cells and live"),
+            Achievement::FewTurns => format!("Finish a level turning {MAX_TURNS} times at most"),
+            Achievement::Feast => format!("Eat {FEAST_MEALS} times in {FEAST_TICKS} ticks"),
+        }
+    }
+}
+
+/// Where a game stands towards the achievements, fed its events in order.
+/// Only makes sense for a game with a single snake, the events don't say whose they are.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    length: usize,
+    fall: u32,            // cells fallen without landing yet
+    fell_this_tick: bool, // the next move is part of the fall
+    turns: Turns,
+    meals: VecDeque<u32>, // ticks of the last few meals
+}
+
+impl Progress {
+    pub fn new(starting_length: usize) -> Self {
+        Self {
+            length: starting_length,
+            fall: 0,
+            fell_this_tick: false,
+            turns: Turns::default(),
+            meals: VecDeque::with_capacity(FEAST_MEALS),
+        }
+    }
+
+    /// What `event`, which happened on `tick`, reaches. Can give the same one again later,
+    /// what was already unlocked is for [`Unlocked`] to know.
+    pub fn observe(&mut self, tick: u32, event: &GameEvent) -> Vec<Achievement> {
+        let mut reached = Vec::new();
+        match *event {
+            GameEvent::Ate { .. } => {
+                self.length += 1;
+                if self.length >= LONG_SNAKE {
+                    reached.push(Achievement::LongSnake);
+                }
+                if self.meals.len() == FEAST_MEALS {
+                    self.meals.pop_front();
+                }
+                self.meals.push_back(tick);
+                if self.meals.len() == FEAST_MEALS
+                    && self
+                        .meals
+                        .back()
+                        .zip(self.meals.front())
+                        .is_some_and(|(last, first)| last - first < FEAST_TICKS)
+                {
+                    reached.push(Achievement::Feast);
+                }
+            }
+            GameEvent::Fell => {
+                self.fell_this_tick = true;
+                self.fall += 1;
+            }
+            GameEvent::Moved { head } => {
+                // un tick sans chute après une chute, c'est qu'on a atterri
+                if !self.fell_this_tick {
+                    if self.fall >= LONG_FALL {
+                        reached.push(Achievement::LongFall);
+                    }
+                    self.fall = 0;
+                }
+                self.fell_this_tick = false;
+                self.turns.moved(head);
+            }
+            GameEvent::Completed if self.turns.count() <= MAX_TURNS => {
+                reached.push(Achievement::FewTurns);
+            }
+            GameEvent::Died(_) => self.fall = 0,
+            _ => (),
+        }
+        reached
+    }
+}
+
+/// The achievements unlocked so far, kept next to the high scores.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Unlocked {
+    unlocked: BTreeSet<Achievement>,
+}
+
+impl Unlocked {
+    pub fn default_path() -> Option<PathBuf> {
+        Some(data_dir()?.join("achievements.ron"))
+    }
+
+    /// A missing file means nothing was unlocked yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AchievementsError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err.into()),
+        };
+        ron::from_str(&text).map_err(|err| AchievementsError::Format(err.to_string()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), AchievementsError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| AchievementsError::Format(err.to_string()))?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Returns whether it's the first time.
+    pub fn unlock(&mut self, achievement: Achievement) -> bool {
+        self.unlocked.insert(achievement)
+    }
+
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    /// The list of all of them, those not unlocked yet greyed out.
+    pub fn draw(&self, pencil: &mut Pencil) {
+        pencil.set_foreground(Color::White).draw_text(
+            &format!(
+                "Achievements {}/{} (A to go back)",
+                self.unlocked.len(),
+                Achievement::ALL.len()
+            ),
+            Vec2::xy(2, 1),
+        );
+        for (row, achievement) in Achievement::ALL.into_iter().enumerate() {
+            let (marker, color) = if self.is_unlocked(achievement) {
+                ('*', Color::Yellow)
+            } else {
+                (' ', Color::DarkGrey)
+            };
+            pencil.set_foreground(color).draw_text(
+                &format!(
+                    "{marker} {:<24} {}",
+                    achievement.name(),
+                    achievement.description()
+                ),
+                Vec2::xy(2, 3 + row as i32),
+            );
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AchievementsError {
+    #[error("Could not access the achievements: {0}")]
+    Io(#[from] io::Error),
+    #[error("The achievements file is corrupted: {0}")]
+    Format(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameError;
+
+    // tout ce que les événements débloquent, dans l'ordre
+    fn reached(progress: &mut Progress, events: &[(u32, GameEvent)]) -> Vec<Achievement> {
+        events
+            .iter()
+            .flat_map(|(tick, event)| progress.observe(*tick, event))
+            .collect()
+    }
+
+    fn meals(ticks: &[u32]) -> Vec<(u32, GameEvent)> {
+        let coord = (0, 0, 1);
+        ticks
+            .iter()
+            .map(|&tick| (tick, GameEvent::Ate { coord }))
+            .collect()
+    }
+
+    fn fall(cells: u32) -> Vec<(u32, GameEvent)> {
+        (0..cells)
+            .flat_map(|z| {
+                let head = (0, 0, 10 - z as isize);
+                [(z, GameEvent::Fell), (z, GameEvent::Moved { head })]
+            })
+            .chain([(
+                cells,
+                GameEvent::Moved {
+                    head: (1, 0, 10 - cells as isize),
+                },
+            )])
+            .collect()
+    }
+
+    #[test]
+    fn a_feast_is_three_meals_close_together() {
+        assert_eq!(
+            reached(&mut Progress::new(1), &meals(&[0, 5, 14])),
+            [Achievement::Feast]
+        );
+        assert!(reached(&mut Progress::new(1), &meals(&[0, 5, 15])).is_empty());
+        // les trois derniers suffisent
+        assert_eq!(
+            reached(&mut Progress::new(1), &meals(&[0, 20, 25, 30])),
+            [Achievement::Feast]
+        );
+    }
+
+    #[test]
+    fn growing_long_enough() {
+        let mut progress = Progress::new(LONG_SNAKE - 2);
+        assert!(reached(&mut progress, &meals(&[0])).is_empty());
+        assert_eq!(
+            reached(&mut progress, &meals(&[100])),
+            [Achievement::LongSnake]
+        );
+    }
+
+    #[test]
+    fn a_long_fall_counts_once_landed() {
+        assert_eq!(
+            reached(&mut Progress::new(1), &fall(LONG_FALL)),
+            [Achievement::LongFall]
+        );
+        assert!(reached(&mut Progress::new(1), &fall(LONG_FALL - 1)).is_empty());
+        // mourir en tombant n'est pas atterrir
+        let mut deadly = fall(LONG_FALL);
+        deadly.pop();
+        deadly.push((LONG_FALL, GameEvent::Died(GameError::TimeUp)));
+        assert!(reached(&mut Progress::new(1), &deadly).is_empty());
+    }
+
+    #[test]
+    fn finishing_with_few_turns() {
+        let zigzag = |turns: isize| -> Vec<(u32, GameEvent)> {
+            (0..=turns + 1)
+                .map(|i| {
+                    (
+                        0,
+                        GameEvent::Moved {
+                            head: (i / 2 + i % 2, i / 2, 1),
+                        },
+                    )
+                })
+                .chain([(0, GameEvent::Completed)])
+                .collect()
+        };
+        assert_eq!(
+            reached(&mut Progress::new(1), &zigzag(MAX_TURNS as isize)),
+            [Achievement::FewTurns]
+        );
+        assert!(reached(&mut Progress::new(1), &zigzag(MAX_TURNS as isize + 1)).is_empty());
+    }
+}
@@ -0,0 +1,82 @@
+// Le bip du terminal pour ce qui compte, sans en faire une sirène quand tout arrive en même temps
+
+use crate::game::GameEvent;
+
+/// How many rings can wait their turn, those on top of that are dropped.
+const MAX_PENDING: usize = 2;
+
+/// How many rings `event` is worth: one for food, two for a death.
+pub fn rings_for(event: &GameEvent) -> usize {
+    match event {
+        GameEvent::Ate { .. } => 1,
+        GameEvent::Died(_) => 2,
+        _ => 0,
+    }
+}
+
+/// The rings asked for by the events, let out one at a time with at least `gap` frames between two.
+#[derive(Debug)]
+pub struct Bell {
+    gap: usize,
+    pending: usize,
+    last_rung: Option<usize>, // the frame of the last ring
+}
+
+impl Bell {
+    pub fn new(gap: usize) -> Self {
+        Self {
+            gap,
+            pending: 0,
+            last_rung: None,
+        }
+    }
+
+    pub fn hear(&mut self, event: &GameEvent) {
+        self.pending = (self.pending + rings_for(event)).min(MAX_PENDING);
+    }
+
+    /// Whether to ring on `frame`, frames coming in order.
+    pub fn ring(&mut self, frame: usize) -> bool {
+        let rested = self.last_rung.is_none_or(|last| frame >= last + self.gap);
+        if self.pending == 0 || !rested {
+            return false;
+        }
+        self.pending -= 1;
+        self.last_rung = Some(frame);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameError;
+
+    // les frames où ça sonne, de 0 à `frames`
+    fn rung(bell: &mut Bell, frames: usize) -> Vec<usize> {
+        (0..frames).filter(|&frame| bell.ring(frame)).collect()
+    }
+
+    #[test]
+    fn rings_are_spaced_out_by_the_gap() {
+        let mut bell = Bell::new(3);
+        assert!(rung(&mut bell, 10).is_empty());
+        bell.hear(&GameEvent::Died(GameError::TimeUp));
+        assert_eq!(rung(&mut bell, 10), [0, 3]);
+        bell.hear(&GameEvent::Moved { head: (0, 0, 1) });
+        assert!(rung(&mut bell, 10).is_empty());
+    }
+
+    #[test]
+    fn a_burst_of_events_is_capped() {
+        let mut bell = Bell::new(2);
+        for _ in 0..5 {
+            bell.hear(&GameEvent::Ate { coord: (1, 0, 1) });
+        }
+        assert_eq!(rung(&mut bell, 20), [0, 2]);
+        // le dernier bip compte encore : pas avant que l'écart soit passé
+        bell.hear(&GameEvent::Ate { coord: (1, 0, 1) });
+        assert!(!bell.ring(3));
+        assert!(bell.ring(4));
+    }
+}
@@ -0,0 +1,399 @@
+// Quelle touche fait quoi, lu depuis un fichier que le joueur peut modifier
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use ruscii::keyboard::{Key, KeyEvent};
+use thiserror::Error;
+
+use crate::game::Direction;
+use crate::scores::data_dir;
+
+/// What a key does during a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Move(Direction),
+    MoveAlt(Direction), // the second set of arrows, steering the second snake in versus mode
+    Jump,
+    Quit,
+    Pause,
+    Autopilot,
+    Undo,
+    Save,
+    Load,
+    Hint,
+    Axes,
+    Debug,
+    Coords,
+    Peek,
+    LayerUp,
+    LayerDown,
+    SeeThrough,
+    TrailFade,
+    Preview,
+    TopDown,
+    Faster,
+    Slower,
+}
+
+// les noms dans le fichier, dans l'ordre où ils y sont écrits
+const ACTION_NAMES: &[(&str, Action)] = &[
+    ("move-north", Action::Move(Direction::North)),
+    ("move-south", Action::Move(Direction::South)),
+    ("move-west", Action::Move(Direction::West)),
+    ("move-east", Action::Move(Direction::East)),
+    ("alt-north", Action::MoveAlt(Direction::North)),
+    ("alt-south", Action::MoveAlt(Direction::South)),
+    ("alt-west", Action::MoveAlt(Direction::West)),
+    ("alt-east", Action::MoveAlt(Direction::East)),
+    ("jump", Action::Jump),
+    ("quit", Action::Quit),
+    ("pause", Action::Pause),
+    ("autopilot", Action::Autopilot),
+    ("undo", Action::Undo),
+    ("save", Action::Save),
+    ("load", Action::Load),
+    ("hint", Action::Hint),
+    ("axes", Action::Axes),
+    ("debug", Action::Debug),
+    ("coords", Action::Coords),
+    ("peek", Action::Peek),
+    ("layer-up", Action::LayerUp),
+    ("layer-down", Action::LayerDown),
+    ("see-through", Action::SeeThrough),
+    ("trail-fade", Action::TrailFade),
+    ("preview", Action::Preview),
+    ("top-down", Action::TopDown),
+    ("faster", Action::Faster),
+    ("slower", Action::Slower),
+];
+
+// ruscii ne sait pas lister ses touches, leur nom est celui de leur Debug
+const KEYS: &[Key] = &[
+    Key::Esc,
+    Key::Space,
+    Key::Enter,
+    Key::Backspace,
+    Key::CapsLock,
+    Key::Tab,
+    Key::Up,
+    Key::Down,
+    Key::Left,
+    Key::Right,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Insert,
+    Key::Delete,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::Grave,
+    Key::Minus,
+    Key::Equal,
+    Key::LeftBracket,
+    Key::RightBracket,
+    Key::BackSlash,
+    Key::Semicolon,
+    Key::Apostrophe,
+    Key::Comma,
+    Key::Dot,
+    Key::Slash,
+];
+
+// arrows, WASD and HJKL all steer out of the box, WASD going to the second snake in versus mode
+const DEFAULTS: &[(Key, Action)] = &[
+    (Key::Up, Action::Move(Direction::North)),
+    (Key::Down, Action::Move(Direction::South)),
+    (Key::Left, Action::Move(Direction::West)),
+    (Key::Right, Action::Move(Direction::East)),
+    (Key::K, Action::Move(Direction::North)),
+    (Key::J, Action::Move(Direction::South)),
+    (Key::H, Action::Move(Direction::West)),
+    (Key::L, Action::Move(Direction::East)),
+    (Key::W, Action::MoveAlt(Direction::North)),
+    (Key::S, Action::MoveAlt(Direction::South)),
+    (Key::A, Action::MoveAlt(Direction::West)),
+    (Key::D, Action::MoveAlt(Direction::East)),
+    (Key::Space, Action::Jump), // TODO : pour tester le saut voir
+    (Key::Esc, Action::Quit),
+    (Key::P, Action::Pause),
+    (Key::B, Action::Autopilot),
+    (Key::U, Action::Undo),
+    (Key::F5, Action::Save),
+    (Key::F9, Action::Load),
+    (Key::I, Action::Hint),
+    (Key::G, Action::Axes),
+    (Key::F3, Action::Debug),
+    (Key::C, Action::Coords),
+    (Key::V, Action::Peek),
+    (Key::PageUp, Action::LayerUp),
+    (Key::PageDown, Action::LayerDown),
+    (Key::T, Action::SeeThrough),
+    (Key::F, Action::TrailFade),
+    (Key::N, Action::Preview), // comme next
+    (Key::O, Action::TopDown),
+    (Key::Equal, Action::Faster), // la touche du +
+    (Key::Minus, Action::Slower),
+];
+
+/// Which key does what, one action per key but as many keys as wanted per action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bindings {
+    actions: HashMap<Key, Action>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            actions: DEFAULTS.iter().copied().collect(),
+        }
+    }
+}
+
+impl Bindings {
+    /// Where the bindings live, under the user's data directory.
+    pub fn default_path() -> Option<PathBuf> {
+        Some(data_dir()?.join("keys.txt"))
+    }
+
+    /// What `key` is bound to, if anything.
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.actions.get(&key).copied()
+    }
+
+    /// What a frame's key presses ask for, in order. Releases and keys bound to nothing are
+    /// dropped here, so they can't get in the way of the moves pressed around them.
+    pub fn actions(&self, events: &[KeyEvent]) -> Vec<Action> {
+        events
+            .iter()
+            .filter_map(|event| self.action(event.pressed()?))
+            .collect()
+    }
+
+    /// The direction of the most recently pressed key still held down among those `steers`
+    /// turns into one, `held` going from the oldest press to the newest like ruscii gives them.
+    /// ruscii only sends a press once however long the key is held, this keeps the snake turning
+    /// with the key held when the one pressed after it is let go.
+    pub fn held_direction(
+        &self,
+        held: &[Key],
+        steers: impl Fn(Action) -> Option<Direction>,
+    ) -> Option<Direction> {
+        held.iter()
+            .rev()
+            .filter_map(|&key| self.action(key))
+            .find_map(steers)
+    }
+
+    /// Reads a bindings file, a line per key like `Space jump`, `#` starting a comment.
+    /// A key bound twice is refused, even to the same action.
+    pub fn parse(text: &str) -> Result<Self, BindingsError> {
+        let mut actions = HashMap::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line_number = idx + 1;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let [key_name, action_name] = line.split_whitespace().collect::<Vec<_>>()[..] else {
+                return Err(BindingsError::Malformed(line_number));
+            };
+            let key = key_named(key_name).ok_or_else(|| BindingsError::UnknownKey {
+                line: line_number,
+                name: key_name.to_string(),
+            })?;
+            let action = action_named(action_name).ok_or_else(|| BindingsError::UnknownAction {
+                line: line_number,
+                name: action_name.to_string(),
+            })?;
+            if let Some(first) = actions.insert(key, action) {
+                return Err(BindingsError::Conflict {
+                    key: key_name.to_string(),
+                    first: action_name_of(first).to_string(),
+                    second: action_name.to_string(),
+                });
+            }
+        }
+        Ok(Self { actions })
+    }
+
+    /// The file [`Bindings::parse`] reads back, grouped by action.
+    pub fn to_text(&self) -> String {
+        let mut text =
+            String::from("# <key> <action>, the keys are named like ruscii names them\n");
+        for &(name, action) in ACTION_NAMES {
+            // dans l'ordre de KEYS pour que le fichier ne change pas d'une sauvegarde à l'autre
+            for key in KEYS.iter().filter(|&&key| self.action(key) == Some(action)) {
+                let _ = writeln!(text, "{key:?} {name}");
+            }
+        }
+        text
+    }
+
+    /// A missing file means the default bindings.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BindingsError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), BindingsError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_text())?;
+        Ok(())
+    }
+}
+
+// UTILS
+
+fn key_named(name: &str) -> Option<Key> {
+    KEYS.iter()
+        .copied()
+        .find(|key| format!("{key:?}").eq_ignore_ascii_case(name))
+}
+
+fn action_named(name: &str) -> Option<Action> {
+    ACTION_NAMES
+        .iter()
+        .find(|&&(action_name, _)| action_name == name)
+        .map(|&(_, action)| action)
+}
+
+fn action_name_of(action: Action) -> &'static str {
+    ACTION_NAMES
+        .iter()
+        .find(|&&(_, named)| named == action)
+        .map_or("?", |&(name, _)| name)
+}
+
+#[derive(Debug, Error)]
+pub enum BindingsError {
+    #[error("Could not access the key bindings: {0}")]
+    Io(#[from] io::Error),
+    #[error("Line {0} of the key bindings should be a key and an action")]
+    Malformed(usize),
+    #[error("Line {line} of the key bindings: unknown key {name:?}")]
+    UnknownKey { line: usize, name: String },
+    #[error("Line {line} of the key bindings: unknown action {name:?}")]
+    UnknownAction { line: usize, name: String },
+    #[error("{key} is bound to both {first} and {second}")]
+    Conflict {
+        key: String,
+        first: String,
+        second: String,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_unknown_key_is_refused_with_its_line() {
+        let err = Bindings::parse("# mes touches\nSpace jump\nBanana pause\n").unwrap_err();
+        assert!(
+            matches!(&err, BindingsError::UnknownKey { line: 3, name } if name == "Banana"),
+            "{err:?}"
+        );
+        let err = Bindings::parse("Space dance").unwrap_err();
+        assert!(matches!(&err, BindingsError::UnknownAction { line: 1, name } if name == "dance"));
+        assert!(matches!(
+            Bindings::parse("Space"),
+            Err(BindingsError::Malformed(1))
+        ));
+    }
+
+    #[test]
+    fn a_key_bound_twice_is_a_conflict() {
+        let err = Bindings::parse("Space jump\nq quit\nspace pause").unwrap_err();
+        assert_eq!(err.to_string(), "space is bound to both jump and pause");
+        // même pour la même action
+        assert!(matches!(
+            Bindings::parse("Space jump\nSpace jump"),
+            Err(BindingsError::Conflict { .. })
+        ));
+        // plusieurs touches pour une action, c'est permis
+        let bindings = Bindings::parse("Space jump\nJ jump").unwrap();
+        assert_eq!(bindings.action(Key::Space), Some(Action::Jump));
+        assert_eq!(bindings.action(Key::J), Some(Action::Jump));
+    }
+
+    #[test]
+    fn the_file_reads_back_the_same_bindings() {
+        let bindings = Bindings::default();
+        assert_eq!(Bindings::parse(&bindings.to_text()).unwrap(), bindings);
+    }
+
+    #[test]
+    fn a_frame_of_key_presses_becomes_actions_in_order() {
+        let bindings = Bindings::default();
+        let events = [KeyEvent::Pressed(Key::Up), KeyEvent::Pressed(Key::P)];
+        assert_eq!(
+            bindings.actions(&events),
+            [Action::Move(Direction::North), Action::Pause]
+        );
+        // les relâchements et les touches sans rien derrière ne gênent pas les autres
+        let events = [
+            KeyEvent::Released(Key::Up),
+            KeyEvent::Pressed(Key::F12),
+            KeyEvent::Pressed(Key::Left),
+        ];
+        assert_eq!(bindings.actions(&events), [Action::Move(Direction::West)]);
+    }
+}
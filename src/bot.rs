@@ -0,0 +1,184 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::game::{self, Direction, GameState, Rng, Vec3};
+
+/// Picks the direction the snake should take this tick.
+///
+/// Goes for the nearest food it can safely reach, and when there is none,
+/// heads wherever leaves it the most room to keep moving.
+pub fn choose_direction(game: &GameState) -> Direction {
+    choose_direction_for(game, 0)
+}
+
+/// Same as [`choose_direction`] for the first or the second snake, both being in the way.
+pub fn choose_direction_for(game: &GameState, player: usize) -> Direction {
+    let head = game.head_of(player);
+    let obstacles = obstacles(game, player);
+    let is_free = |pos: Vec3| !obstacles.contains(&pos);
+
+    let grid = game.grid();
+    if let Some(path) = game::bfs(
+        grid,
+        head,
+        |pos| pos != head && grid.get(pos) == Some(game::Cell::Food),
+        is_free,
+    ) {
+        return direction_towards(game, head, path[1]);
+    }
+
+    // Survie : on va là où il reste le plus de place
+    game::legal_moves(grid, head)
+        .into_iter()
+        .filter(|&pos| is_free(pos))
+        .max_by_key(|&pos| free_space(game, pos, &obstacles))
+        .map(|pos| direction_towards(game, head, pos))
+        .unwrap_or(Direction::None)
+}
+
+/// The computer's snake in rival mode, racing the player for the food.
+#[derive(Debug)]
+pub struct Rival {
+    blunders: u32, // out of a hundred moves, how many are picked at random
+    rng: Rng,
+}
+
+impl Rival {
+    /// The rival is always the second snake.
+    pub const PLAYER: usize = 1;
+
+    pub fn new(blunders: u32, seed: u64) -> Self {
+        Self {
+            blunders,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Plays like the autopilot, but sometimes goes anywhere that doesn't kill it right away.
+    pub fn choose_direction(&mut self, game: &GameState) -> Direction {
+        if self.rng.below(100) < self.blunders as usize {
+            let head = game.head_of(Self::PLAYER);
+            let obstacles = obstacles(game, Self::PLAYER);
+            let safe: Vec<Direction> = game::legal_steps(game.grid(), head)
+                .into_iter()
+                .filter(|&(_, landing)| !obstacles.contains(&landing))
+                .map(|(dir, _)| dir)
+                .collect();
+            if !safe.is_empty() {
+                return safe[self.rng.below(safe.len())];
+            }
+        }
+        choose_direction_for(game, Self::PLAYER)
+    }
+}
+
+// UTILS
+
+fn direction_towards(game: &GameState, from: Vec3, to: Vec3) -> Direction {
+    game::legal_steps(game.grid(), from)
+        .into_iter()
+        .find(|&(_, landing)| landing == to)
+        .map_or(Direction::None, |(dir, _)| dir)
+}
+
+// every snake, except the tail of `player` which moves out of the way on the next tick
+// unless it leaves a wall there
+fn obstacles(game: &GameState, player: usize) -> HashSet<Vec3> {
+    (0..game.player_count())
+        .flat_map(|other| {
+            let body: Vec<Vec3> = game.snake_body_of(other).collect();
+            let len = if other == player && !game.leaves_trail() {
+                body.len() - 1
+            } else {
+                body.len()
+            };
+            body.into_iter().take(len)
+        })
+        .collect()
+}
+
+// how many positions the head could still get to from `start`
+fn free_space(game: &GameState, start: Vec3, obstacles: &HashSet<Vec3>) -> usize {
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(pos) = queue.pop_front() {
+        for next in game::legal_moves(game.grid(), pos) {
+            if !obstacles.contains(&next) && seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    seen.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Difficulty, GameConfig};
+    use crate::level::Level;
+
+    // un niveau où le bot ne s'enferme pas, s'il meurt c'est que les règles ont changé sous lui
+    const SEED: u64 = 0;
+
+    #[test]
+    fn autopilot_survives_and_eats_on_a_generated_level() {
+        let level = Level::generated((20, 12, 3), SEED, Difficulty::Normal);
+        let config = GameConfig {
+            endless: true,
+            seed: SEED,
+            ..GameConfig::default()
+        };
+        let mut game = GameState::new(level.start, level.grid, &config).unwrap();
+        for tick in 0..500 {
+            let input = choose_direction(&game);
+            if let Err(err) = game.step(input) {
+                panic!("died on tick {tick}: {err}");
+            }
+        }
+        assert!(game.foods_eaten() >= 30, "only ate {}", game.foods_eaten());
+    }
+
+    // le joueur tourne en rond pendant que le rival facile chasse la nourriture,
+    // ce qui s'est passé à chaque tick
+    fn race(seed: u64) -> Vec<(Vec<Vec3>, Vec<u32>, bool)> {
+        let level = Level::arena((10, 10, 2));
+        let config = GameConfig {
+            endless: true,
+            rival: true,
+            seed,
+            ..GameConfig::default()
+        };
+        let mut game = GameState::new(level.start, level.grid, &config).unwrap();
+        let mut rival = Rival::new(30, seed);
+        let square = [
+            Direction::East,
+            Direction::South,
+            Direction::West,
+            Direction::North,
+        ];
+        let mut trace = Vec::new();
+        for dir in square.iter().flat_map(|&dir| [dir; 3]).cycle().take(300) {
+            if game.player_count() > 1 {
+                let rival_dir = rival.choose_direction(&game);
+                game.queue_input_for(Rival::PLAYER, rival_dir);
+            }
+            game.queue_input(dir);
+            let alive = game.update().is_ok();
+            let players = 0..game.player_count();
+            let heads = players.clone().map(|p| game.head_of(p)).collect();
+            trace.push((heads, players.map(|p| game.score_of(p)).collect(), alive));
+            if !alive {
+                break;
+            }
+        }
+        trace
+    }
+
+    #[test]
+    fn the_easy_rival_plays_the_same_for_the_same_seed() {
+        let trace = race(SEED);
+        assert_eq!(race(SEED), trace);
+        assert_ne!(race(SEED + 1), trace);
+        // il est allé chercher de quoi manger avant le joueur qui ne fait que tourner
+        assert!(trace.iter().any(|(_, scores, _)| scores.get(1) > Some(&0)));
+    }
+}
@@ -0,0 +1,363 @@
+// La ligne de commande, séparée de main pour pouvoir la lire depuis n'importe quelle liste d'arguments
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::game::Difficulty;
+use crate::game::{Align, Direction, GameConfig, Vec3, Wrap};
+
+pub const USAGE: &str = "\
+Usage: svnake [options]
+  --level <path>     play the level in this file
+  --select           choose the level from a list
+  --levels <dir>     where --select looks for levels (default: levels)
+  --edit <path>      open the level editor on this file
+  --size <XxYxZ>     size of the arena, fitted to the terminal otherwise
+  --fps <n>          frames drawn per second
+  --tps <n>          game ticks per second
+  --speedup <n>      the game goes n% faster with every food eaten
+  --max-speedup <n>  up to n% faster than --tps (default: 100 with --speedup)
+  --coyote <n>       ticks the snake can stay in the air before falling
+  --length <n>       how long the snake is at the start
+  --facing <n|s|w|e> where the snake looks at the start, its body trailing behind
+  --time-attack <s>  the run ends after this many seconds
+  --shrink <s>       the edge of the arena collapses every this many seconds
+  --endless          new food keeps appearing, the level never ends
+  --versus           two players on one keyboard, arrows against WASD
+  --rival <e|n|h>    race an easy, normal or hard computer snake for the food
+  --expanding        start small, every food eaten adds a piece of level
+  --loops            closing a loop with the body scores a point per cell inside
+  --streak <n>       eating again within n ticks makes each food worth a point more
+  --trail            the snake leaves a wall behind wherever its tail has been
+  --wrap <x|y|xy>    leaving across these edges comes back in from the other side
+  --fog <n>          only see this many cells around the head
+  --dark <n>         the same, without remembering anything out of sight
+  --generate         play a level made up from the seed
+  --difficulty <e|n|h> speed, food, poison and how hard --generate makes it (default: n),
+                     the options after it can still change its settings
+  --daily            play today's challenge, the same generated level for everyone
+  --seed <n>         seed for everything random, picked from the clock otherwise
+  --align <c|t>      center the board in the window, or put it at the top
+  --practice         allows undoing moves with U
+  --strict           refuses to play a level with problems
+  --bot              lets the autopilot play
+  --sound            rings the terminal bell on food and death
+  --record <path>    records a replay of the game
+  --replay <path>    plays a replay back, with the settings it was recorded with
+  --save <path>      where F5/F9 save and load (default: savegame.ron)";
+
+/// Everything that can be asked for on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Args {
+    pub config: GameConfig,
+    pub level: Option<PathBuf>,
+    pub select: bool,
+    pub generate: bool,
+    pub daily: bool,
+    pub levels_dir: PathBuf,
+    pub edit: Option<PathBuf>,
+    pub seed: Option<u64>,
+    pub align: Align,
+    pub strict: bool,
+    pub bot: bool,
+    pub sound: bool,
+    pub rival: Option<u32>, // how many moves out of a hundred the rival makes at random
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub save: String,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            config: GameConfig::default(),
+            level: None,
+            select: false,
+            generate: false,
+            daily: false,
+            levels_dir: PathBuf::from("levels"),
+            edit: None,
+            seed: None,
+            align: Align::Center,
+            strict: false,
+            bot: false,
+            sound: false,
+            rival: None,
+            record: None,
+            replay: None,
+            save: "savegame.ron".to_string(),
+        }
+    }
+}
+
+/// Reads the arguments, without the program name in front.
+pub fn parse(args: &[String]) -> Result<Args, ArgsError> {
+    let mut parsed = Args::default();
+    // in seconds, turned into ticks once the tick rate is known
+    let mut time_attack = None;
+    let mut shrink = None;
+    // the difficulty goes first, what's set on its own wins over it whatever the order
+    let mut preset = None;
+    let mut tps = None;
+    let (mut speedup, mut max_speedup) = (None, None);
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .map(String::as_str)
+                .ok_or_else(|| ArgsError::MissingValue(flag.clone()))
+        };
+        match flag.as_str() {
+            "--level" => parsed.level = Some(PathBuf::from(value()?)),
+            "--select" => parsed.select = true,
+            "--levels" => parsed.levels_dir = PathBuf::from(value()?),
+            "--edit" => parsed.edit = Some(PathBuf::from(value()?)),
+            "--size" => parsed.config.dimensions = Some(dimensions(flag, value()?)?),
+            "--fps" => parsed.config.fps = positive(flag, value()?)?,
+            "--tps" => tps = Some(positive(flag, value()?)?),
+            "--speedup" => speedup = Some(number(flag, value()?)?),
+            "--max-speedup" => max_speedup = Some(number(flag, value()?)?),
+            "--coyote" => parsed.config.coyote_ticks = number(flag, value()?)?,
+            "--length" => parsed.config.starting_length = positive(flag, value()?)? as usize,
+            "--facing" => parsed.config.starting_facing = facing(flag, value()?)?,
+            "--time-attack" => time_attack = Some(positive(flag, value()?)?),
+            "--shrink" => shrink = Some(positive(flag, value()?)?),
+            "--endless" => parsed.config.endless = true,
+            "--versus" => parsed.config.two_players = true,
+            "--rival" => parsed.rival = Some(blunders(flag, value()?)?),
+            "--expanding" => parsed.config.expanding = true,
+            "--loops" => parsed.config.loops = true,
+            "--streak" => parsed.config.streak = Some(positive(flag, value()?)?),
+            "--trail" => parsed.config.trail = true,
+            "--wrap" => parsed.config.wrap = wrap(flag, value()?)?,
+            "--fog" => parsed.config.fog = Some(number(flag, value()?)?),
+            "--dark" => {
+                parsed.config.fog = Some(number(flag, value()?)?);
+                parsed.config.dark = true;
+            }
+            "--generate" => parsed.generate = true,
+            "--difficulty" => preset = Some(difficulty(flag, value()?)?),
+            "--daily" => parsed.daily = true,
+            "--seed" => {
+                let value = value()?;
+                parsed.seed = Some(value.parse().map_err(|_| invalid(flag, value))?);
+            }
+            "--align" => parsed.align = align(flag, value()?)?,
+            "--practice" => parsed.config.practice = true,
+            "--strict" => parsed.strict = true,
+            "--bot" => parsed.bot = true,
+            "--sound" => parsed.sound = true,
+            "--record" => parsed.record = Some(value()?.to_string()),
+            "--replay" => parsed.replay = Some(value()?.to_string()),
+            "--save" => parsed.save = value()?.to_string(),
+            _ => return Err(ArgsError::Unknown(flag.clone())),
+        }
+    }
+    if let Some(difficulty) = preset {
+        difficulty.apply_to(&mut parsed.config);
+    }
+    if let Some(tps) = tps {
+        parsed.config.ticks_per_second = tps;
+    }
+    if let Some(percent) = speedup {
+        parsed.config.speedup_percent = percent;
+        parsed.config.max_speedup_percent = parsed.config.max_speedup_percent.max(100);
+    }
+    if let Some(percent) = max_speedup {
+        parsed.config.max_speedup_percent = percent;
+    }
+    parsed.config.time_limit = time_attack.map(|secs| secs * parsed.config.ticks_per_second);
+    parsed.config.shrink_every = shrink.map(|secs| secs * parsed.config.ticks_per_second);
+    Ok(parsed)
+}
+
+// UTILS
+
+fn number(flag: &str, value: &str) -> Result<u32, ArgsError> {
+    value.parse().map_err(|_| invalid(flag, value))
+}
+
+fn positive(flag: &str, value: &str) -> Result<u32, ArgsError> {
+    number(flag, value)
+        .ok()
+        .filter(|&n| n > 0)
+        .ok_or_else(|| invalid(flag, value))
+}
+
+// 20x20x10, il faut au moins deux couches pour avoir un sol et de quoi marcher dessus
+fn dimensions(flag: &str, value: &str) -> Result<Vec3, ArgsError> {
+    let dims: Option<Vec<isize>> = value.split('x').map(|n| n.parse().ok()).collect();
+    match dims.as_deref() {
+        Some(&[x, y, z]) if x > 0 && y > 0 && z > 1 => Ok((x, y, z)),
+        _ => Err(invalid(flag, value)),
+    }
+}
+
+fn facing(flag: &str, value: &str) -> Result<Direction, ArgsError> {
+    match value {
+        "n" => Ok(Direction::North),
+        "s" => Ok(Direction::South),
+        "w" => Ok(Direction::West),
+        "e" => Ok(Direction::East),
+        _ => Err(invalid(flag, value)),
+    }
+}
+
+fn align(flag: &str, value: &str) -> Result<Align, ArgsError> {
+    match value {
+        "c" => Ok(Align::Center),
+        "t" => Ok(Align::Top),
+        _ => Err(invalid(flag, value)),
+    }
+}
+
+fn blunders(flag: &str, value: &str) -> Result<u32, ArgsError> {
+    match value {
+        "e" => Ok(30),
+        "n" => Ok(10),
+        "h" => Ok(0),
+        _ => Err(invalid(flag, value)),
+    }
+}
+
+fn difficulty(flag: &str, value: &str) -> Result<Difficulty, ArgsError> {
+    match value {
+        "e" => Ok(Difficulty::Easy),
+        "n" => Ok(Difficulty::Normal),
+        "h" => Ok(Difficulty::Hard),
+        _ => Err(invalid(flag, value)),
+    }
+}
+
+fn wrap(flag: &str, value: &str) -> Result<Wrap, ArgsError> {
+    match value {
+        "x" => Ok(Wrap { x: true, y: false }),
+        "y" => Ok(Wrap { x: false, y: true }),
+        "xy" => Ok(Wrap { x: true, y: true }),
+        _ => Err(invalid(flag, value)),
+    }
+}
+
+fn invalid(flag: &str, value: &str) -> ArgsError {
+    ArgsError::Invalid {
+        flag: flag.to_string(),
+        value: value.to_string(),
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ArgsError {
+    #[error("Unknown option {0}")]
+    Unknown(String),
+    #[error("{0} needs a value")]
+    MissingValue(String),
+    #[error("Invalid value {value:?} for {flag}")]
+    Invalid { flag: String, value: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(args: &str) -> Result<Args, ArgsError> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        parse(&args)
+    }
+
+    #[test]
+    fn nothing_is_the_defaults() {
+        assert_eq!(parse_str(""), Ok(Args::default()));
+    }
+
+    #[test]
+    fn valid_arguments() {
+        let args = parse_str(
+            "--level foo.ron --size 20x10x3 --tps 10 --time-attack 3 --facing w --wrap xy --seed 42 --bot",
+        )
+        .unwrap();
+        assert_eq!(args.level, Some(PathBuf::from("foo.ron")));
+        assert_eq!(args.config.dimensions, Some((20, 10, 3)));
+        assert_eq!(args.config.ticks_per_second, 10);
+        assert_eq!(args.config.time_limit, Some(30)); // in ticks
+        assert_eq!(args.config.starting_facing, Direction::West);
+        assert_eq!(args.config.wrap, Wrap { x: true, y: true });
+        assert_eq!(args.seed, Some(42));
+        assert!(args.bot);
+
+        let args = parse_str("--dark 3 --rival e").unwrap();
+        assert_eq!((args.config.fog, args.config.dark), (Some(3), true));
+        assert_eq!(args.rival, Some(30));
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let invalid = |flag: &str, value: &str| {
+            Err(ArgsError::Invalid {
+                flag: flag.to_string(),
+                value: value.to_string(),
+            })
+        };
+        assert_eq!(
+            parse_str("--endless --fly"),
+            Err(ArgsError::Unknown("--fly".to_string()))
+        );
+        assert_eq!(
+            parse_str("--tps"),
+            Err(ArgsError::MissingValue("--tps".to_string()))
+        );
+        assert_eq!(parse_str("--tps 0"), invalid("--tps", "0"));
+        assert_eq!(parse_str("--length -2"), invalid("--length", "-2"));
+        assert_eq!(parse_str("--size 20x10x1"), invalid("--size", "20x10x1"));
+        assert_eq!(parse_str("--size 20x10"), invalid("--size", "20x10"));
+        assert_eq!(parse_str("--facing up"), invalid("--facing", "up"));
+        assert_eq!(parse_str("--difficulty x"), invalid("--difficulty", "x"));
+        assert_eq!(parse_str("--seed 0x10"), invalid("--seed", "0x10"));
+    }
+
+    #[test]
+    fn options_win_over_the_difficulty_whatever_the_order() {
+        assert_eq!(
+            parse_str("--tps 30 --difficulty h")
+                .unwrap()
+                .config
+                .ticks_per_second,
+            30
+        );
+        assert_eq!(
+            parse_str("--difficulty h --tps 30")
+                .unwrap()
+                .config
+                .ticks_per_second,
+            30
+        );
+        // le temps en ticks suit le --tps, pas celui de la difficulté
+        assert_eq!(
+            parse_str("--time-attack 10 --tps 30 --difficulty h")
+                .unwrap()
+                .config
+                .time_limit,
+            Some(300)
+        );
+        assert_eq!(
+            parse_str("--difficulty h").unwrap().config.ticks_per_second,
+            28
+        );
+    }
+
+    #[test]
+    fn the_speedup_wins_over_the_difficulty_whatever_the_order() {
+        let speedup = |args: &str| {
+            let config = parse_str(args).unwrap().config;
+            (config.speedup_percent, config.max_speedup_percent)
+        };
+        assert_eq!(speedup("--difficulty h"), (5, 100));
+        assert_eq!(speedup("--speedup 10 --difficulty h"), (10, 100));
+        assert_eq!(speedup("--difficulty h --speedup 10"), (10, 100));
+        assert_eq!(speedup("--max-speedup 50 --difficulty h"), (5, 50));
+        assert_eq!(speedup("--difficulty h --max-speedup 50"), (5, 50));
+        assert_eq!(speedup("--speedup 10"), (10, 100));
+        assert_eq!(speedup("--max-speedup 30 --speedup 10"), (10, 30));
+        assert_eq!(speedup("--difficulty e --speedup 10"), (10, 100));
+    }
+}
@@ -0,0 +1,121 @@
+// Le 3, 2, 1 avant que ça parte, au début de la partie et en sortant de la pause
+
+use ruscii::{drawing::Pencil, spatial::Vec2, terminal::Color};
+
+/// What's counted down from, a second per number.
+const COUNT_FROM: usize = 3;
+
+// 3 by 5 characters, '#' being filled
+const FONT: &[(char, [&str; 5])] = &[
+    ('0', ["###", "# #", "# #", "# #", "###"]),
+    ('1', [" # ", "## ", " # ", " # ", "###"]),
+    ('2', ["###", "  #", "###", "#  ", "###"]),
+    ('3', ["###", "  #", " ##", "  #", "###"]),
+    ('4', ["# #", "# #", "###", "  #", "  #"]),
+    ('5', ["###", "#  ", "###", "  #", "###"]),
+    ('6', ["###", "#  ", "###", "# #", "###"]),
+    ('7', ["###", "  #", "  #", "  #", "  #"]),
+    ('8', ["###", "# #", "###", "# #", "###"]),
+    ('9', ["###", "# #", "###", "  #", "###"]),
+    ('G', ["###", "#  ", "# #", "# #", "###"]),
+    ('O', ["###", "# #", "# #", "# #", "###"]),
+    ('!', [" # ", " # ", " # ", "   ", " # "]),
+];
+
+/// Draws `text` in big letters centered on `center`, what the font doesn't know is left blank.
+pub fn draw_big_text(pencil: &mut Pencil, text: &str, center: Vec2) {
+    let width = text.chars().count() as i32 * 4 - 1; // une colonne entre deux lettres
+    let corner = center - Vec2::xy(width / 2, 2);
+    for (i, c) in text.chars().enumerate() {
+        let Some((_, rows)) = FONT.iter().find(|&&(letter, _)| letter == c) else {
+            continue;
+        };
+        for (y, row) in rows.iter().enumerate() {
+            for (x, _) in row.char_indices().filter(|&(_, pixel)| pixel == '#') {
+                let at = corner + Vec2::xy(i as i32 * 4 + x as i32, y as i32);
+                pencil.draw_char('█', at);
+            }
+        }
+    }
+}
+
+/// Counts the frames of a 3, 2, 1 then GO!, the game waiting until GO! shows up.
+#[derive(Debug)]
+pub struct Countdown {
+    fps: usize,
+    frames: usize, // shown so far
+}
+
+impl Countdown {
+    pub fn new(fps: u32) -> Self {
+        Self {
+            fps: fps.max(1) as usize,
+            frames: 0,
+        }
+    }
+
+    /// Whether the game still has to wait, the inputs pressed meanwhile are kept for when it goes.
+    pub fn is_holding(&self) -> bool {
+        self.frames < COUNT_FROM * self.fps
+    }
+
+    /// Once GO! has been shown for half a second.
+    pub fn is_over(&self) -> bool {
+        self.frames >= COUNT_FROM * self.fps + self.fps / 2
+    }
+
+    pub fn advance(&mut self) {
+        self.frames += 1;
+    }
+
+    fn text(&self) -> String {
+        match COUNT_FROM.checked_sub(self.frames / self.fps) {
+            Some(left) if left > 0 => left.to_string(),
+            _ => "GO!".to_string(),
+        }
+    }
+
+    pub fn draw(&self, pencil: &mut Pencil, center: Vec2) {
+        pencil.set_foreground(Color::White);
+        draw_big_text(pencil, &self.text(), center);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::StringCanvas;
+
+    fn big(text: &str, size: Vec2) -> String {
+        let mut canvas = StringCanvas::new(size);
+        draw_big_text(&mut canvas.pencil(), text, Vec2::xy(size.x / 2, 2));
+        canvas.to_string()
+    }
+
+    #[test]
+    fn big_digits_are_drawn_from_the_font() {
+        assert_eq!(big("1", Vec2::xy(3, 5)), " █\n██\n █\n █\n███");
+        assert_eq!(
+            big("GO!", Vec2::xy(11, 5)),
+            "███ ███  █\n█   █ █  █\n█ █ █ █  █\n█ █ █ █\n███ ███  █"
+        );
+        // ce que la police ne connaît pas laisse un trou
+        assert_eq!(
+            big("1?1", Vec2::xy(11, 5)).lines().next(),
+            Some(" █       █")
+        );
+    }
+
+    #[test]
+    fn counts_down_a_second_per_number() {
+        let mut countdown = Countdown::new(2);
+        let mut shown = Vec::new();
+        while !countdown.is_over() {
+            shown.push((countdown.text(), countdown.is_holding()));
+            countdown.advance();
+        }
+        let texts: Vec<&str> = shown.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(texts, ["3", "3", "2", "2", "1", "1", "GO!"]);
+        assert_eq!(shown.iter().filter(|(_, holding)| *holding).count(), 6);
+    }
+}
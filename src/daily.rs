@@ -0,0 +1,53 @@
+// Le défi du jour : le même niveau pour tout le monde, tiré de la date
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::game::Difficulty;
+use crate::game::Vec3;
+use crate::level::Level;
+use crate::scores;
+
+// fixed so that the level doesn't depend on the terminal it's played in
+const DIMENSIONS: Vec3 = (24, 16, 5);
+const DIFFICULTY: Difficulty = Difficulty::Normal;
+
+/// Today's date in UTC, `YYYY-MM-DD`.
+pub fn today() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    scores::format_date(now)
+}
+
+/// The seed of the challenge of `date`: the FNV-1a hash of its text.
+/// Changing it would change every challenge, past ones included, and their replays with them:
+/// `2026-10-16` has to keep giving `0x37c8_393a_7a9e_fe65`.
+pub fn seed(date: &str) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+    date.bytes().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// The level of the challenge of `date`, the same wherever it's played.
+pub fn level(date: &str) -> Level {
+    let mut level = Level::generated(DIMENSIONS, seed(date), DIFFICULTY);
+    level.name = format!("Daily {date}");
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_seed_of_a_date_never_changes() {
+        assert_eq!(seed("2026-10-16"), 0x37c8_393a_7a9e_fe65);
+        assert_ne!(seed("2026-10-17"), seed("2026-10-16"));
+        let today = level("2026-10-16");
+        assert_eq!(today.name, "Daily 2026-10-16");
+        assert_eq!(today.fingerprint(), level("2026-10-16").fingerprint());
+    }
+}
@@ -0,0 +1,109 @@
+// Quand personne ne touche au menu, le bot joue tout seul pour montrer à quoi ressemble le jeu
+
+use ruscii::{drawing::Pencil, spatial::Vec2, terminal::Color};
+
+use crate::bot;
+use crate::game::Difficulty;
+use crate::game::{Align, GameConfig, GameState, Renderer, View};
+use crate::level::Level;
+use crate::timestep::FixedTimestep;
+
+/// How long the menu waits without a key pressed before the demo starts.
+pub const IDLE_SECONDS: u32 = 15;
+
+// the same level and the same game every time
+const SEED: u64 = 0x5e4a_4e4b_0de3_0000;
+// back to the menu after a minute at 20 ticks per second, even if the bot is still alive
+const MAX_TICKS: u32 = 1200;
+
+/// The built-in level the demo is played on.
+pub fn level() -> Level {
+    let mut level = Level::generated((12, 8, 3), SEED, Difficulty::Easy);
+    level.name = "Demo".to_string();
+    level
+}
+
+/// A game the bot plays on its own, that nothing is saved from.
+#[derive(Debug)]
+pub struct Demo {
+    game: GameState,
+    timestep: FixedTimestep,
+    renderer: Renderer,
+    over: bool,
+}
+
+impl Demo {
+    pub fn new(ticks_per_second: u32) -> Self {
+        let level = level();
+        // la nourriture revient, pour que ça dure un peu
+        let config = GameConfig {
+            endless: true,
+            seed: SEED,
+            ..GameConfig::default()
+        };
+        Self {
+            game: GameState::new(level.start, level.grid, &config)
+                .expect("the demo level has room for the snake"),
+            timestep: FixedTimestep::new(ticks_per_second),
+            renderer: Renderer::default(),
+            over: false,
+        }
+    }
+
+    /// Plays the ticks of this frame, until the bot dies or has played long enough.
+    pub fn advance(&mut self) {
+        for _ in 0..self.timestep.tick() {
+            if self.over {
+                return;
+            }
+            self.play_tick();
+        }
+    }
+
+    fn play_tick(&mut self) {
+        let input = bot::choose_direction(&self.game);
+        self.over = self.game.step(input).is_err() || self.game.tick() >= MAX_TICKS;
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    pub fn draw(&mut self, pencil: &mut Pencil, size: Vec2) {
+        let view = View::default();
+        pencil.set_origin(self.game.grid().board_origin_following(
+            size,
+            self.game.head(),
+            Align::Center,
+            view.projection,
+        ));
+        self.renderer.draw(&self.game, pencil, view);
+        pencil.set_origin(Vec2::zero());
+        pencil
+            .set_foreground(Color::Yellow)
+            .draw_center_text("DEMO - press any key", Vec2::xy(size.x / 2, 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // la partie du bot jusqu'au bout, sans attendre les frames
+    fn played() -> Demo {
+        let mut demo = Demo::new(20);
+        while !demo.is_over() {
+            demo.play_tick();
+        }
+        demo
+    }
+
+    #[test]
+    fn the_demo_always_plays_out_the_same() {
+        let demo = played();
+        // le bot finit par s'enfermer avant la minute, toujours au même endroit
+        let outcome = |demo: &Demo| (demo.game.tick(), demo.game.score(), demo.game.head());
+        assert_eq!(outcome(&demo), (327, 37, (5, 7, 1)));
+        assert_eq!(outcome(&played()), outcome(&demo));
+    }
+}
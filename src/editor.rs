@@ -0,0 +1,293 @@
+// L'éditeur de niveaux, lancé avec --edit <fichier>
+
+use std::path::{Path, PathBuf};
+
+use ruscii::{
+    drawing::{Pencil, RectCharset},
+    keyboard::Key,
+    spatial::Vec2,
+    terminal::Color,
+};
+
+use crate::game::{self, Align, Cell, Direction, Grid, Isometric, Pos3, StampMode, Vec3, prefab};
+use crate::level::{Level, LevelError, LevelIssue};
+
+/// What the number keys paint with, in order. I changes the number of keys and doors,
+/// and which way the stairs and conveyors go.
+const PALETTE: [Cell; 10] = [
+    Cell::Void,
+    Cell::Empty,
+    Cell::Block,
+    Cell::Food,
+    Cell::Spike,
+    Cell::Ice,
+    Cell::Key(0),
+    Cell::Door(0),
+    Cell::Stairs(Direction::Up),
+    Cell::Conveyor(Direction::North),
+];
+const LOCK_IDS: u8 = 10;
+/// What P stamps at the cursor, O goes to the next one and R turns it.
+const PREFABS: [(&str, &str); 3] = [
+    ("staircase", prefab::SPIRAL_STAIRCASE),
+    ("bridge", prefab::BRIDGE),
+    ("tower", prefab::HOLLOW_TOWER),
+];
+
+// une modification, avec de quoi revenir en arrière
+#[derive(Debug, Clone)]
+enum Edit {
+    Paint { coord: Vec3, previous: Cell },
+    Start { previous: Vec3 },
+    Bulk { previous: Box<Grid> }, // a stamp or a fill can touch a lot of cells, the whole grid is kept
+}
+
+#[derive(Debug)]
+pub struct Editor {
+    level: Level,
+    path: PathBuf,
+    cursor: Vec3,
+    brush: Cell,
+    history: Vec<Edit>,
+    issues: Vec<LevelIssue>, // what Level::validate says, kept up to date after every edit
+    status: Option<String>,
+    show_axes: bool,
+    prefab: usize,
+    quarter_turns: u8,
+}
+
+impl Editor {
+    /// Opens the level at `path`, or starts from `blank` if there is no file there yet.
+    pub fn open(path: impl AsRef<Path>, blank: Level) -> Result<Self, LevelError> {
+        let path = path.as_ref();
+        let level = if path.exists() {
+            Level::load(path)?
+        } else {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            Level::new(name, blank.grid, blank.start)
+        };
+        Ok(Self {
+            issues: level.validate(),
+            cursor: level.start,
+            level,
+            path: path.to_path_buf(),
+            brush: Cell::Block,
+            history: Vec::new(),
+            status: None,
+            show_axes: false,
+            prefab: 0,
+            quarter_turns: 0,
+        })
+    }
+
+    // ruscii ne donne pas les modificateurs, pas de Ctrl+S : on sauvegarde avec F2
+    pub fn press(&mut self, key: Key) {
+        self.status = None;
+        match key {
+            Key::Up => self.move_cursor(Direction::North),
+            Key::Down => self.move_cursor(Direction::South),
+            Key::Left => self.move_cursor(Direction::West),
+            Key::Right => self.move_cursor(Direction::East),
+            Key::PageUp => self.move_cursor(Direction::Up),
+            Key::PageDown => self.move_cursor(Direction::Down),
+            Key::Space | Key::Enter => self.paint(),
+            Key::S => self.set_start(),
+            Key::U | Key::Backspace => self.undo(),
+            Key::F2 => self.save(),
+            Key::G => self.show_axes = !self.show_axes,
+            Key::I => self.next_lock_id(),
+            Key::F => self.fill(),
+            Key::P => self.stamp(),
+            Key::O => self.prefab = (self.prefab + 1) % PREFABS.len(),
+            Key::R => self.quarter_turns = (self.quarter_turns + 1) % 4,
+            key => {
+                if let Some(cell) = palette_cell(key) {
+                    self.brush = cell;
+                }
+            }
+        }
+    }
+
+    pub fn draw(&self, pencil: &mut Pencil, size: Vec2) {
+        let pencil = pencil
+            .set_origin(Vec2::zero())
+            .set_foreground(Color::Grey)
+            .draw_rect(&RectCharset::double_lines(), Vec2::zero(), size);
+
+        pencil.set_origin(
+            self.level
+                .grid
+                .board_origin(size, Align::Center, &Isometric),
+        );
+        self.level
+            .grid
+            .draw_layer(pencil, self.cursor.2, &Isometric);
+        if self.show_axes {
+            game::draw_axes(pencil, self.level.grid.dimensions(), &Isometric);
+        }
+        if self.level.grid.get(self.level.start).is_some() {
+            pencil.set_foreground(Color::Green);
+            game::draw_marker(pencil, self.level.start, 'S', &Isometric);
+        }
+        pencil.set_foreground(Color::Magenta);
+        game::draw_marker(pencil, self.cursor, 'X', &Isometric);
+        pencil.set_origin(Vec2::zero());
+
+        let (_, _, mz) = self.level.grid.dimensions();
+        let (x, y, z) = self.cursor;
+        pencil.set_foreground(Color::White).draw_text(
+            &format!(
+                "{}  layer {z}/{}  cursor ({x}, {y})  brush {:?}  prefab {} {}°",
+                self.level.name,
+                mz - 1,
+                self.brush,
+                PREFABS[self.prefab].0,
+                u16::from(self.quarter_turns) * 90
+            ),
+            Vec2::xy(1, 1),
+        );
+        pencil.set_foreground(Color::DarkGrey).draw_text(
+            "0-9 brush  I lock id/direction  Space paint  F fill  P stamp  O prefab  R turn  S start  U undo  PgUp/PgDn layer  G axes  F2 save  Esc quit",
+            Vec2::xy(1, 2),
+        );
+        if let Some(status) = &self.status {
+            pencil
+                .set_foreground(Color::White)
+                .draw_text(status, Vec2::xy(1, 3));
+        }
+
+        if self.issues.is_empty() {
+            pencil
+                .set_foreground(Color::Green)
+                .draw_text("Level OK", Vec2::xy(1, size.y - 1));
+        }
+        pencil.set_foreground(Color::Red);
+        for (row, issue) in self.issues.iter().rev().take(5).enumerate() {
+            pencil.draw_text(&issue.to_string(), Vec2::xy(1, size.y - 1 - row as i32));
+        }
+    }
+
+    // UTILS
+
+    fn move_cursor(&mut self, direction: Direction) {
+        let next = Pos3::from(self.cursor) + direction;
+        if next.is_within(self.level.grid.dimensions().into()) {
+            self.cursor = next.into();
+        }
+    }
+
+    fn paint(&mut self) {
+        let previous = self.level.grid[self.cursor]; // the cursor never leaves the grid
+        if previous == self.brush {
+            return;
+        }
+        match self.level.grid.set(self.cursor, self.brush) {
+            Ok(()) => self.push(Edit::Paint {
+                coord: self.cursor,
+                previous,
+            }),
+            Err(err) => self.status = Some(err.to_string()),
+        }
+    }
+
+    // everything like the cell under the cursor and touching it, on every layer
+    fn fill(&mut self) {
+        let target = self.level.grid[self.cursor];
+        if target == self.brush {
+            return;
+        }
+        let previous = Box::new(self.level.grid.clone());
+        for coord in self
+            .level
+            .grid
+            .flood_fill(self.cursor, |cell| cell == target)
+        {
+            if let Err(err) = self.level.grid.set(coord, self.brush) {
+                self.status = Some(err.to_string());
+            }
+        }
+        self.push(Edit::Bulk { previous });
+    }
+
+    // the prefab's corner goes on the cursor, its void leaves the level alone
+    fn stamp(&mut self) {
+        let (name, text) = PREFABS[self.prefab];
+        let prefab: Grid = text.parse().expect("the built-in prefabs parse");
+        let previous = Box::new(self.level.grid.clone());
+        let mode = StampMode {
+            skip_void: true,
+            quarter_turns: self.quarter_turns,
+            clip: false,
+        };
+        match self.level.grid.stamp(self.cursor, &prefab, mode) {
+            Ok(()) => self.push(Edit::Bulk { previous }),
+            Err(err) => self.status = Some(format!("The {name} doesn't fit: {err}")),
+        }
+    }
+
+    fn next_lock_id(&mut self) {
+        self.brush = match self.brush {
+            Cell::Key(id) => Cell::Key((id + 1) % LOCK_IDS),
+            Cell::Door(id) => Cell::Door((id + 1) % LOCK_IDS),
+            Cell::Stairs(Direction::Up) => Cell::Stairs(Direction::Down),
+            Cell::Stairs(_) => Cell::Stairs(Direction::Up),
+            Cell::Conveyor(dir) => Cell::Conveyor(dir.rotated_z(1)),
+            cell => cell,
+        };
+    }
+
+    fn set_start(&mut self) {
+        let previous = self.level.start;
+        self.level.start = self.cursor;
+        self.push(Edit::Start { previous });
+    }
+
+    fn undo(&mut self) {
+        let Some(edit) = self.history.pop() else {
+            self.status = Some("Nothing to undo".to_string());
+            return;
+        };
+        match edit {
+            Edit::Paint { coord, previous } => {
+                if let Err(err) = self.level.grid.set(coord, previous) {
+                    self.status = Some(err.to_string());
+                }
+            }
+            Edit::Start { previous } => self.level.start = previous,
+            Edit::Bulk { previous } => self.level.grid = *previous,
+        }
+        self.issues = self.level.validate();
+    }
+
+    fn save(&mut self) {
+        self.status = Some(match self.level.save(&self.path) {
+            Ok(()) => match self.level.grid.validate_reachable(self.level.start) {
+                Ok(()) => format!("Saved to {}", self.path.display()),
+                Err(err) => format!("Saved to {}, but: {err}", self.path.display()),
+            },
+            Err(err) => err.to_string(),
+        });
+    }
+
+    fn push(&mut self, edit: Edit) {
+        self.history.push(edit);
+        self.issues = self.level.validate();
+    }
+}
+
+fn palette_cell(key: Key) -> Option<Cell> {
+    let index = match key {
+        Key::Num0 => 0,
+        Key::Num1 => 1,
+        Key::Num2 => 2,
+        Key::Num3 => 3,
+        Key::Num4 => 4,
+        Key::Num5 => 5,
+        Key::Num6 => 6,
+        Key::Num7 => 7,
+        Key::Num8 => 8,
+        Key::Num9 => 9,
+        _ => return None,
+    };
+    PALETTE.get(index).copied()
+}
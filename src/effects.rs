@@ -0,0 +1,126 @@
+// Des petits effets par-dessus le plateau, rien que pour l'œil : le jeu et les replays n'en savent rien
+
+use ruscii::{drawing::Pencil, spatial::Vec2, terminal::Color};
+
+/// How a bit of food bursts when eaten, a look per frame going from bright to dark.
+const BURST: [(char, Color); 4] = [
+    ('*', Color::White),
+    ('*', Color::Yellow),
+    ('+', Color::Xterm(208)),
+    ('+', Color::DarkGrey),
+];
+// one cell away in every direction the isometric view can show, a cell being two columns wide
+const BURST_OFFSETS: [(i32, i32); 6] = [(-2, 0), (2, 0), (-1, -1), (1, -1), (-1, 1), (1, 1)];
+const FLASH_COLORS: [Color; 2] = [Color::Red, Color::White];
+
+// a character at a fixed position on the board, with a look for each frame it stays
+#[derive(Debug)]
+struct Effect {
+    screen: Vec2,
+    looks: Vec<(char, Color)>,
+    age: usize, // frames already shown
+}
+
+/// What's being shown over the board, drawn with the board's origin and forgotten once over.
+#[derive(Debug, Default)]
+pub struct Effects {
+    effects: Vec<Effect>,
+    flashing: usize, // frames left before the death flash is over
+}
+
+impl Effects {
+    /// Sparks flying one cell out of `screen` for a few frames, where something was eaten.
+    pub fn burst(&mut self, screen: Vec2) {
+        self.effects
+            .extend(BURST_OFFSETS.iter().map(|&(x, y)| Effect {
+                screen: screen + Vec2::xy(x, y),
+                looks: BURST.to_vec(),
+                age: 0,
+            }));
+    }
+
+    /// The body at `screens` going red and white for `frames` frames.
+    pub fn flash(&mut self, screens: impl IntoIterator<Item = Vec2>, frames: usize) {
+        let looks: Vec<(char, Color)> = (0..frames)
+            .map(|frame| ('S', FLASH_COLORS[frame % FLASH_COLORS.len()]))
+            .collect();
+        self.effects
+            .extend(screens.into_iter().map(|screen| Effect {
+                screen,
+                looks: looks.clone(),
+                age: 0,
+            }));
+        self.flashing = self.flashing.max(frames);
+    }
+
+    /// Whether a death is still being flashed, what comes after it waits until it's over.
+    pub fn is_flashing(&self) -> bool {
+        self.flashing > 0
+    }
+
+    /// Goes on to the next frame, dropping what's over.
+    pub fn advance(&mut self) {
+        for effect in &mut self.effects {
+            effect.age += 1;
+        }
+        self.effects
+            .retain(|effect| effect.age < effect.looks.len());
+        self.flashing = self.flashing.saturating_sub(1);
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn draw(&self, pencil: &mut Pencil) {
+        for effect in &self.effects {
+            let (c, color) = effect.looks[effect.age];
+            pencil.set_foreground(color).draw_char(c, effect.screen);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::StringCanvas;
+
+    fn shown(effects: &Effects, at: Vec2) -> Option<(char, Color)> {
+        let mut canvas = StringCanvas::new(Vec2::xy(8, 4));
+        effects.draw(&mut canvas.pencil());
+        Some((canvas.char_at(at)?, canvas.color_at(at)?)).filter(|&(c, _)| c != ' ')
+    }
+
+    #[test]
+    fn a_burst_fades_then_goes_away() {
+        let mut effects = Effects::default();
+        effects.burst(Vec2::xy(4, 2));
+        let spark = Vec2::xy(6, 2);
+        for look in BURST {
+            assert_eq!(shown(&effects, spark), Some(look));
+            effects.advance();
+        }
+        assert_eq!(shown(&effects, spark), None);
+        assert!(effects.effects.is_empty());
+        assert!(!effects.is_flashing());
+    }
+
+    #[test]
+    fn the_death_flash_lasts_its_frames() {
+        let mut effects = Effects::default();
+        let body = [Vec2::xy(0, 0), Vec2::xy(2, 0)];
+        effects.flash(body, 3);
+        for color in [Color::Red, Color::White, Color::Red] {
+            assert!(effects.is_flashing());
+            assert_eq!(shown(&effects, body[1]), Some(('S', color)));
+            effects.advance();
+        }
+        assert!(!effects.is_flashing());
+        assert_eq!(shown(&effects, body[1]), None);
+
+        effects.flash(body, 2);
+        effects.clear();
+        assert!(!effects.is_flashing());
+        assert_eq!(shown(&effects, body[0]), None);
+    }
+}
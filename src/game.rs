@@ -1,73 +1,239 @@
 #![allow(dead_code)]
 
 use std::{
-    collections::{HashSet, VecDeque},
-    ops::Add,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque, hash_map::Entry},
+    ops::{Add, Index, IndexMut},
+    time::Duration,
 };
 
-use anyhow::{Result, anyhow};
-use ruscii::{drawing::Pencil, keyboard::Key, spatial::Vec2};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+mod canvas;
+mod difficulty;
+mod draw;
+mod enclosure;
+mod expand;
+mod fog;
+pub mod generate;
+mod mob;
+mod pos;
+pub mod prefab;
+mod projection;
+mod render;
+mod rng;
+mod save;
+pub mod transform;
+
+use fog::Visibility;
+
+pub use canvas::StringCanvas;
+pub use difficulty::{Difficulty, DifficultyError};
+#[allow(unused_imports)] // Draw is there for other frontends, main goes through the Renderer
+pub use draw::{Align, Draw, PLAYER_COLORS, View, draw_axes, draw_marker, occludes};
+pub use mob::{Mob, Patrol};
+pub use pos::Pos3;
+pub use prefab::StampMode;
+pub use projection::{Isometric, Projection, TopDown};
+pub use render::Renderer;
+pub use rng::Rng;
+pub use save::SaveGame;
+pub use transform::Transform;
+
+// TODO : remplacer partout par Pos3
 pub type Vec3 = (isize, isize, isize);
 
 fn contains(coord: Vec3, dimensions: Vec3) -> bool {
-    let (x, y, z) = coord;
-    let (mx, my, mz) = dimensions;
-    (0..mx).contains(&x) && (0..my).contains(&y) && (0..mz).contains(&z)
-}
-fn coord_to_screen(coord: Vec3) -> Vec2 {
-    let (x, y, z) = coord;
-    let screen_x = (x - y) * 2;
-    let screen_y = (x + y) - z;
-    Vec2::xy(screen_x, screen_y)
+    Pos3::from(coord).is_within(dimensions.into())
 }
-
-pub trait Draw {
-    fn draw(&self, pencil: &mut Pencil);
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Cell {
     Void,  // Permettra de faire des niveaux pas forcément rectangulaires
     Empty, // Juste une case vide, là où Void c'est vraiment du rien
     Block, // Un mur ou un sol
     Food,
+    Spike,                 // Tue le serpent au contact, et on ne peut pas se tenir dessus
+    Ice,                   // Le serpent glisse tout droit tant qu'il est dessus
+    Key(u8),               // Se ramasse en passant dessus, ouvre toutes les portes du même numéro
+    Door(u8),              // Un mur tant que la clé n'a pas été ramassée
+    Stairs(Direction),     // Up ou Down : fait monter ou descendre d'un étage sans sauter
+    Conveyor(Direction),   // Pousse le serpent d'une case de plus dans sa direction au tick suivant
+    Portal(u8, Direction), // Ressort par l'autre portail du même numéro, dans sa direction ou tout droit avec None
+    Crate,                 // Se pousse si la case derrière est libre, et tombe si rien ne la tient
+    Crumble,               // Un sol qui s'effondre dès que la tête du serpent l'a quitté
+    Poison,                // Se mange comme la nourriture, mais tue
 }
 
 impl Cell {
-    fn to_char(self) -> char {
-        match self {
-            Cell::Void => 'V',
-            Cell::Empty => ' ',
-            Cell::Block => 'W',
-            Cell::Food => 'F',
-        }
+    /// Whether the snake can't go through it and can stand on it.
+    pub fn is_solid(self) -> bool {
+        matches!(
+            self,
+            Cell::Block | Cell::Door(_) | Cell::Crate | Cell::Crumble
+        )
+    }
+
+    /// Whether the snake can stand on it, stairs hold up whoever just climbed them.
+    pub fn supports(self) -> bool {
+        self.is_solid() || matches!(self, Cell::Stairs(_))
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+// a grid read from a file goes through Grid::new like the others, and is always written out whole
+#[serde(try_from = "GridData", into = "GridData")]
 pub struct Grid {
-    // le vecteur se parcourt de tous les x, puis incrément y, puis incrément z après avoir fait la première couche
-    cells: Vec<Cell>, // Vecteur comme ça on pourrait faire des niveaux dont la taille change en cours de route par ex
+    storage: GridStorage,
+    dimensions: Vec3,
+    meta: HashMap<Vec3, CellMeta>, // only for the few cells that have some
+    wrap: Wrap,
+}
+
+/// Which edges of a grid lead back in from the opposite side, z never does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Wrap {
+    pub x: bool,
+    pub y: bool,
+}
+
+impl Wrap {
+    pub fn is_none(&self) -> bool {
+        !self.x && !self.y
+    }
+
+    /// Wrapping along an axis when either does.
+    pub fn or(self, other: Wrap) -> Wrap {
+        Wrap {
+            x: self.x || other.x,
+            y: self.y || other.y,
+        }
+    }
+}
+
+/// Extra data for a single cell, kept next to the grid so [`Cell`] stays a small tag.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellMeta {
+    pub label: Option<String>, // a name, for the editor or for scripts
+    pub value: Option<i32>,    // whatever number the cell needs (power-up strength, timer...)
+}
+
+// le vecteur se parcourt de tous les x, puis incrément y, puis incrément z après avoir fait la première couche
+#[derive(Debug, Clone)]
+enum GridStorage {
+    Dense(Vec<Cell>), // Vecteur comme ça on pourrait faire des niveaux dont la taille change en cours de route par ex
+    // Pour les grands niveaux presque vides : seulement ce qui n'est pas du Void,
+    // rangé par index pour garder l'ordre de dessin
+    Sparse(BTreeMap<usize, Cell>),
+}
+
+/// Past this many positions a mostly void level is kept sparse when loaded.
+const SPARSE_MIN_VOLUME: usize = 100_000;
+/// ...and mostly void means at most this fraction of its cells aren't void.
+const SPARSE_MAX_OCCUPANCY: f32 = 0.1;
+
+#[derive(Serialize, Deserialize)]
+struct GridData {
+    cells: Vec<Cell>,
     dimensions: Vec3,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")] // levels made before there was any
+    meta: Vec<(Vec3, CellMeta)>,
+    #[serde(default, skip_serializing_if = "Wrap::is_none")]
+    wrap: Wrap,
+}
+
+impl TryFrom<GridData> for Grid {
+    type Error = GridError;
+
+    fn try_from(data: GridData) -> Result<Self, GridError> {
+        let mut grid = Grid::new(data.dimensions, data.cells)?;
+        grid.wrap = data.wrap;
+        for (coord, meta) in data.meta {
+            grid.set_meta(coord, meta)?;
+        }
+        let occupied = grid.iter_occupied().count();
+        if grid.volume() >= SPARSE_MIN_VOLUME
+            && occupied as f32 <= grid.volume() as f32 * SPARSE_MAX_OCCUPANCY
+        {
+            Ok(grid.into_sparse())
+        } else {
+            Ok(grid)
+        }
+    }
+}
+
+impl From<Grid> for GridData {
+    fn from(grid: Grid) -> Self {
+        // sorted so the same grid is always written out the same way
+        let mut meta: Vec<(Vec3, CellMeta)> = grid.meta.clone().into_iter().collect();
+        meta.sort_by_key(|&(coord, _)| coord);
+        GridData {
+            cells: grid.iter().map(|(_, cell)| cell).collect(),
+            dimensions: grid.dimensions,
+            meta,
+            wrap: grid.wrap,
+        }
+    }
 }
 
 impl Grid {
-    pub fn new(dimensions: Vec3, cells: Vec<Cell>) -> Self {
-        Self { cells, dimensions }
+    /// Fails unless there is exactly one cell per position, which also rules out empty or negative dimensions.
+    pub fn new(dimensions: Vec3, cells: Vec<Cell>) -> Result<Self, GridError> {
+        let (mx, my, mz) = dimensions;
+        let expected = (mx.max(0) * my.max(0) * mz.max(0)) as usize;
+        if expected == 0 || cells.len() != expected {
+            return Err(GridError::DimensionMismatch {
+                dimensions,
+                expected,
+                got: cells.len(),
+            });
+        }
+        Ok(Self {
+            storage: GridStorage::Dense(cells),
+            dimensions,
+            meta: HashMap::new(),
+            wrap: Wrap::default(),
+        })
     }
 
     pub fn empty((mx, my, mz): Vec3) -> Self {
         Self {
-            cells: vec![Cell::Empty; (mx * my * mz) as usize],
+            storage: GridStorage::Dense(vec![Cell::Empty; (mx * my * mz) as usize]),
             dimensions: (mx, my, mz),
+            meta: HashMap::new(),
+            wrap: Wrap::default(),
+        }
+    }
+
+    /// A grid of nothing but void that only stores what gets put in it,
+    /// for huge levels that are mostly void (floating islands...).
+    pub fn sparse(dimensions: Vec3) -> Self {
+        Self {
+            storage: GridStorage::Sparse(BTreeMap::new()),
+            dimensions,
+            meta: HashMap::new(),
+            wrap: Wrap::default(),
+        }
+    }
+
+    /// The same grid, stored sparsely.
+    pub fn into_sparse(self) -> Self {
+        let cells = self
+            .iter_occupied()
+            .map(|(coord, cell)| (self.coord_to_index(coord), cell))
+            .collect();
+        Self {
+            storage: GridStorage::Sparse(cells),
+            dimensions: self.dimensions,
+            meta: self.meta,
+            wrap: self.wrap,
         }
     }
 
-    pub fn get(&self, (x, y, z): Vec3) -> Option<Cell> {
-        if contains((x, y, z), self.dimensions)
-            && let cell = self.cells[self.coord_to_index((x, y, z))]
+    /// The fallible way in, for coordinates coming from the game (moves, inputs, files...).
+    /// The void counts as nothing there, use indexing to see it.
+    pub fn get(&self, coord: Vec3) -> Option<Cell> {
+        if contains(coord, self.dimensions)
+            && let cell = self.get_unchecked(coord)
             && cell != Cell::Void
         {
             Some(cell)
@@ -76,45 +242,474 @@ impl Grid {
         }
     }
 
-    pub fn set(&mut self, coord: Vec3, cell: Cell) -> Result<()> {
-        if contains(coord, self.dimensions) {
-            let idx = self.coord_to_index(coord);
-            self.cells[idx] = cell;
+    /// For hot paths (rendering, pathfinding) where `coord` is known to be in the grid,
+    /// only checked in debug builds. Gives back the raw cell, void included.
+    pub fn get_unchecked(&self, coord: Vec3) -> Cell {
+        debug_assert!(
+            contains(coord, self.dimensions),
+            "{coord:?} is outside the grid of dimensions {:?}",
+            self.dimensions
+        );
+        let idx = self.coord_to_index(coord);
+        match &self.storage {
+            GridStorage::Dense(cells) => cells[idx],
+            GridStorage::Sparse(cells) => cells.get(&idx).copied().unwrap_or(Cell::Void),
+        }
+    }
+
+    pub fn dimensions(&self) -> Vec3 {
+        self.dimensions
+    }
+
+    /// How many positions there are in the grid, void or not.
+    pub fn volume(&self) -> usize {
+        let (mx, my, mz) = self.dimensions;
+        (mx * my * mz) as usize
+    }
+
+    /// What `coord` rests on, `None` if it's the void or the edge of the map.
+    pub fn cell_below(&self, coord: Vec3) -> Option<Cell> {
+        self.get(coord + Direction::Down)
+    }
+
+    /// The cells touching `coord` along the six axes, `None` for the void. Positions off the grid are skipped.
+    pub fn neighbors(
+        &self,
+        coord: Vec3,
+    ) -> impl Iterator<Item = (Direction, Vec3, Option<Cell>)> + '_ {
+        Direction::ALL
+            .into_iter()
+            .map(move |dir| (dir, coord + dir))
+            .filter(|&(_, next)| contains(next, self.dimensions))
+            .map(|(dir, next)| {
+                let cell = self.get_unchecked(next);
+                (dir, next, (cell != Cell::Void).then_some(cell))
+            })
+    }
+
+    /// Same as [`Grid::neighbors`] with only the positions that aren't void.
+    pub fn neighbors_6(&self, coord: Vec3) -> impl Iterator<Item = (Vec3, Cell)> + '_ {
+        self.neighbors(coord)
+            .filter_map(|(_, next, cell)| Some((next, cell?)))
+    }
+
+    /// Every cell with its position, layer by layer from the bottom, voids included.
+    /// Goes through the whole volume even for a sparse grid, see [`Grid::iter_occupied`].
+    pub fn iter(&self) -> impl Iterator<Item = (Vec3, Cell)> + '_ {
+        (0..self.volume()).map(|idx| {
+            let coord = self.index_to_coord(idx);
+            (coord, self.get_unchecked(coord))
+        })
+    }
+
+    /// Same as [`Grid::iter`] without the voids, in the same order.
+    /// Only touches what's stored for a sparse grid, so this is the one to draw with.
+    pub fn iter_occupied(&self) -> Box<dyn Iterator<Item = (Vec3, Cell)> + '_> {
+        match &self.storage {
+            GridStorage::Dense(cells) => Box::new(
+                cells
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &cell)| cell != Cell::Void)
+                    .map(|(idx, &cell)| (self.index_to_coord(idx), cell)),
+            ),
+            GridStorage::Sparse(cells) => Box::new(
+                cells
+                    .iter()
+                    .filter(|&(_, &cell)| cell != Cell::Void)
+                    .map(|(&idx, &cell)| (self.index_to_coord(idx), cell)),
+            ),
+        }
+    }
+
+    /// Same as [`Grid::iter`] but only for the cells at height `z`, nothing if there is no such layer.
+    pub fn iter_layer(&self, z: isize) -> impl Iterator<Item = (Vec3, Cell)> + '_ {
+        let (mx, my, mz) = self.dimensions;
+        let layer_len = if (0..mz).contains(&z) { mx * my } else { 0 };
+        (0..layer_len).map(move |idx| {
+            let coord = (idx % mx, idx / mx, z);
+            (coord, self.get_unchecked(coord))
+        })
+    }
+
+    /// For bulk edits, lets every cell be changed in place.
+    /// A sparse grid only has its cells that aren't void to hand out.
+    pub fn iter_mut(&mut self) -> Box<dyn Iterator<Item = (Vec3, &mut Cell)> + '_> {
+        let dimensions = self.dimensions;
+        match &mut self.storage {
+            GridStorage::Dense(cells) => Box::new(
+                cells
+                    .iter_mut()
+                    .enumerate()
+                    .map(move |(idx, cell)| (coord_at(idx, dimensions), cell)),
+            ),
+            GridStorage::Sparse(cells) => Box::new(
+                cells
+                    .iter_mut()
+                    .map(move |(&idx, cell)| (coord_at(idx, dimensions), cell)),
+            ),
+        }
+    }
+
+    /// Maps every cell through `f`, for transforms over the whole level like turning all the food
+    /// into walls. Like [`Grid::iter_mut`] a sparse grid only hands out what isn't void,
+    /// and like [`Grid::set`] the cells turned empty or void lose their data.
+    pub fn apply(&mut self, mut f: impl FnMut(Vec3, Cell) -> Cell) {
+        for (coord, cell) in self.iter_mut() {
+            *cell = f(coord, *cell);
+        }
+        let cleared: Vec<Vec3> = self
+            .meta
+            .keys()
+            .copied()
+            .filter(|&coord| matches!(self.get_unchecked(coord), Cell::Empty | Cell::Void))
+            .collect();
+        for coord in cleared {
+            self.meta.remove(&coord);
+        }
+        if let GridStorage::Sparse(cells) = &mut self.storage {
+            cells.retain(|_, &mut cell| cell != Cell::Void);
+        }
+    }
+
+    /// The cells of this grid that aren't the same in `other`, in the grid's order:
+    /// [`Grid::apply_patch`] turns `other` into this grid with them.
+    /// Only the cells, their data and the wrapping aren't part of it.
+    pub fn diff(&self, other: &Grid) -> Result<Vec<(Vec3, Cell)>, GridError> {
+        if self.dimensions != other.dimensions {
+            return Err(GridError::DifferentDimensions(
+                self.dimensions,
+                other.dimensions,
+            ));
+        }
+        Ok(self
+            .iter()
+            .zip(other.iter())
+            .filter(|&((_, ours), (_, theirs))| ours != theirs)
+            .map(|(changed, _)| changed)
+            .collect())
+    }
+
+    /// Sets every cell of `patch`, as made by [`Grid::diff`].
+    /// Stops at the first one outside the grid, the ones before it being already set.
+    pub fn apply_patch(&mut self, patch: &[(Vec3, Cell)]) -> Result<(), GridError> {
+        for &(coord, cell) in patch {
+            self.set(coord, cell)?;
+        }
+        Ok(())
+    }
+
+    /// Every position holding `cell`, in the grid's order.
+    pub fn find_all(&self, cell: Cell) -> Vec<Vec3> {
+        let matching = |&(_, other): &(Vec3, Cell)| other == cell;
+        let found = if cell == Cell::Void {
+            self.iter().filter(matching).collect::<Vec<_>>()
+        } else {
+            self.iter_occupied().filter(matching).collect()
+        };
+        found.into_iter().map(|(coord, _)| coord).collect()
+    }
+
+    /// How many cells of the grid are `cell`, in a single pass over what's stored.
+    /// The void is what's left over, so counting it doesn't walk a sparse grid whole.
+    pub fn count(&self, cell: Cell) -> usize {
+        if cell == Cell::Void {
+            self.volume() - self.iter_occupied().count()
+        } else {
+            self.iter_occupied()
+                .filter(|&(_, other)| other == cell)
+                .count()
+        }
+    }
+
+    /// The extra data of the cell at `coord`, if it has any.
+    pub fn meta(&self, coord: Vec3) -> Option<&CellMeta> {
+        self.meta.get(&coord)
+    }
+
+    /// Attaches data to the cell at `coord`, until it's replaced by [`Cell::Empty`] or [`Cell::Void`] through [`Grid::set`].
+    pub fn set_meta(&mut self, coord: Vec3, meta: CellMeta) -> Result<(), GridError> {
+        if !contains(coord, self.dimensions) {
+            return Err(GridError::OutOfBounds {
+                coord,
+                dimensions: self.dimensions,
+            });
+        }
+        self.meta.insert(coord, meta);
+        Ok(())
+    }
+
+    /// Takes the data off the cell at `coord` and returns it.
+    pub fn take_meta(&mut self, coord: Vec3) -> Option<CellMeta> {
+        self.meta.remove(&coord)
+    }
+
+    /// The fallible counterpart of `grid[coord] = cell`.
+    pub fn set(&mut self, coord: Vec3, cell: Cell) -> Result<(), GridError> {
+        if !contains(coord, self.dimensions) {
+            return Err(GridError::OutOfBounds {
+                coord,
+                dimensions: self.dimensions,
+            });
+        }
+        if matches!(cell, Cell::Empty | Cell::Void) {
+            self.meta.remove(&coord);
+        }
+        let idx = self.coord_to_index(coord);
+        match &mut self.storage {
+            GridStorage::Dense(cells) => cells[idx] = cell,
+            GridStorage::Sparse(cells) if cell == Cell::Void => {
+                cells.remove(&idx);
+            }
+            GridStorage::Sparse(cells) => {
+                cells.insert(idx, cell);
+            }
+        }
+        Ok(())
+    }
+
+    /// Every position the head can get to from `spawn`, following the game's movement rules.
+    pub fn reachable(&self, spawn: Vec3) -> HashSet<Vec3> {
+        let mut seen = HashSet::new();
+        if self.get(spawn).is_none() {
+            return seen;
+        }
+        let mut queue = VecDeque::from([spawn]);
+        seen.insert(spawn);
+        while let Some(pos) = queue.pop_front() {
+            for next in legal_moves(self, pos) {
+                if seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        seen
+    }
+
+    /// Fails with every food the snake can't get to from `spawn`, for checking a level before saving it.
+    pub fn validate_reachable(&self, spawn: Vec3) -> Result<(), GridError> {
+        let reachable = self.reachable(spawn);
+        let unreachable: Vec<Vec3> = self
+            .find_all(Cell::Food)
+            .into_iter()
+            .filter(|coord| !reachable.contains(coord))
+            .collect();
+        if unreachable.is_empty() {
             Ok(())
         } else {
-            Err(anyhow!("Coordonnées hors de la grille"))
+            Err(GridError::UnreachableFood(unreachable))
+        }
+    }
+
+    /// Every position linked to `start` through its six neighbors by cells matching `predicate`,
+    /// `start` included. Empty if `start` itself doesn't match or is outside the grid.
+    pub fn flood_fill(&self, start: Vec3, predicate: impl Fn(Cell) -> bool) -> HashSet<Vec3> {
+        let mut seen = HashSet::new();
+        if !self.get(start).is_some_and(&predicate) {
+            return seen;
+        }
+        // une pile plutôt que de la récursion, une grande grille ferait déborder celle des appels
+        let mut stack = vec![start];
+        seen.insert(start);
+        while let Some(pos) = stack.pop() {
+            for (next, cell) in self.neighbors_6(pos) {
+                if predicate(cell) && seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        seen
+    }
+
+    /// The cells matching `predicate` split into the groups [`Grid::flood_fill`] would find,
+    /// in the order of their first cell along [`Grid::iter`].
+    pub fn regions(&self, predicate: impl Fn(Cell) -> bool) -> Vec<HashSet<Vec3>> {
+        let mut regions: Vec<HashSet<Vec3>> = Vec::new();
+        let mut done = HashSet::new();
+        for (coord, cell) in self.iter() {
+            if predicate(cell) && !done.contains(&coord) {
+                let region = self.flood_fill(coord, &predicate);
+                done.extend(region.iter().copied());
+                regions.push(region);
+            }
         }
+        regions
+    }
+
+    /// Reads a grid from JSON, going through the same checks as one from a level file.
+    pub fn from_json(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a grid is only cells and numbers")
+    }
+
+    pub fn wrap(&self) -> Wrap {
+        self.wrap
+    }
+
+    pub fn set_wrap(&mut self, wrap: Wrap) {
+        self.wrap = wrap;
+    }
+
+    /// One step from `coord` towards `dir`, coming back in from the other side
+    /// across the edges that wrap. Can still end up outside the grid across the others.
+    pub fn step(&self, coord: Vec3, dir: Direction) -> Vec3 {
+        let (x, y, z) = coord + dir;
+        let (mx, my, _) = self.dimensions;
+        let x = if self.wrap.x { x.rem_euclid(mx) } else { x };
+        let y = if self.wrap.y { y.rem_euclid(my) } else { y };
+        (x, y, z)
+    }
+
+    /// Whether `coord` is a position of the grid, void or not.
+    pub fn contains(&self, coord: Vec3) -> bool {
+        contains(coord, self.dimensions)
+    }
+
+    /// Changes the size of the grid, every cell staying at the same coordinates.
+    /// What doesn't fit anymore is dropped and the new space is filled with `fill`,
+    /// it's up to the caller to check the snake is still inside with [`Grid::contains`].
+    pub fn resize(&mut self, new_dimensions: Vec3, fill: Cell) -> Result<(), GridError> {
+        let mut resized = self.blank(new_dimensions, fill)?;
+        if matches!(resized.storage, GridStorage::Sparse(_)) && fill != Cell::Void {
+            for idx in 0..resized.volume() {
+                let coord = coord_at(idx, new_dimensions);
+                if !self.contains(coord) {
+                    resized.set(coord, fill)?;
+                }
+            }
+        }
+        for (coord, cell) in self.stored() {
+            if resized.contains(coord) {
+                resized.set(coord, cell)?;
+            }
+        }
+        resized.meta = std::mem::take(&mut self.meta);
+        resized
+            .meta
+            .retain(|&coord, _| contains(coord, new_dimensions));
+        *self = resized;
+        Ok(())
+    }
+
+    /// A copy of the box going from `min` (included) to `max` (excluded), `min` becoming the origin.
+    pub fn crop(&self, min: Vec3, max: Vec3) -> Result<Grid, GridError> {
+        let (min, max) = (Pos3::from(min), Pos3::from(max));
+        let last = max - Pos3::new(1, 1, 1);
+        for corner in [min, last] {
+            if !self.contains(corner.into()) {
+                return Err(GridError::OutOfBounds {
+                    coord: corner.into(),
+                    dimensions: self.dimensions,
+                });
+            }
+        }
+        let mut cropped = self.blank((max - min).into(), Cell::Void)?;
+        for (coord, cell) in self.stored() {
+            let inside = Pos3::from(coord) - min;
+            if cropped.contains(inside.into()) {
+                cropped.set(inside.into(), cell)?;
+                if let Some(meta) = self.meta(coord) {
+                    cropped.set_meta(inside.into(), meta.clone())?;
+                }
+            }
+        }
+        Ok(cropped)
     }
 
     // UTILS
 
+    // a grid stored the same way as this one, full of `fill` (void for a sparse one)
+    fn blank(&self, dimensions: Vec3, fill: Cell) -> Result<Grid, GridError> {
+        let (mx, my, mz) = dimensions;
+        if mx <= 0 || my <= 0 || mz <= 0 {
+            return Err(GridError::InvalidDimensions(dimensions));
+        }
+        let storage = match self.storage {
+            GridStorage::Dense(_) => GridStorage::Dense(vec![fill; (mx * my * mz) as usize]),
+            GridStorage::Sparse(_) => GridStorage::Sparse(BTreeMap::new()),
+        };
+        Ok(Grid {
+            storage,
+            dimensions,
+            meta: HashMap::new(),
+            wrap: self.wrap,
+        })
+    }
+
+    // every cell that has to be copied over to get the same grid again
+    fn stored(&self) -> Box<dyn Iterator<Item = (Vec3, Cell)> + '_> {
+        match self.storage {
+            GridStorage::Dense(_) => Box::new(self.iter()),
+            GridStorage::Sparse(_) => self.iter_occupied(),
+        }
+    }
+
     // does not check if the coord is in the grid
-    fn coord_to_index(&self, (x, y, z): Vec3) -> usize {
-        let (mx, my, _) = self.dimensions;
-        (z * my * mx + y * mx + x) as usize
+    fn coord_to_index(&self, coord: Vec3) -> usize {
+        let (pos, dims) = (Pos3::from(coord), Pos3::from(self.dimensions));
+        (pos.z * dims.y * dims.x + pos.y * dims.x + pos.x) as usize
     }
     fn index_to_coord(&self, idx: usize) -> Vec3 {
-        let idx = idx as isize;
-        let (mx, my, _) = self.dimensions;
-        let x = idx % mx;
-        let y = (idx / mx) % my;
-        let z = idx / (mx * my);
-        (x, y, z)
+        coord_at(idx, self.dimensions)
     }
 }
 
-impl Draw for Grid {
-    fn draw(&self, pencil: &mut Pencil) {
-        for (idx, &cell) in self.cells.iter().enumerate() {
-            let coord = self.index_to_coord(idx);
-            let screen_vec = coord_to_screen(coord);
-            let c = cell.to_char();
-            pencil.draw_char(c, screen_vec);
+/// For code building levels, which already knows its coordinates are good:
+/// panics with the coordinate and the dimensions if they aren't.
+impl Index<Vec3> for Grid {
+    type Output = Cell;
+
+    fn index(&self, coord: Vec3) -> &Cell {
+        assert!(
+            contains(coord, self.dimensions),
+            "{coord:?} is outside the grid of dimensions {:?}",
+            self.dimensions
+        );
+        let idx = self.coord_to_index(coord);
+        match &self.storage {
+            GridStorage::Dense(cells) => &cells[idx],
+            GridStorage::Sparse(cells) => cells.get(&idx).unwrap_or(&Cell::Void),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl IndexMut<Vec3> for Grid {
+    fn index_mut(&mut self, coord: Vec3) -> &mut Cell {
+        assert!(
+            contains(coord, self.dimensions),
+            "{coord:?} is outside the grid of dimensions {:?}",
+            self.dimensions
+        );
+        let idx = self.coord_to_index(coord);
+        match &mut self.storage {
+            GridStorage::Dense(cells) => &mut cells[idx],
+            // a void written through here stays stored, iter_occupied skips it anyway
+            GridStorage::Sparse(cells) => cells.entry(idx).or_insert(Cell::Void),
+        }
+    }
+}
+
+// how far `coord` is from the sides of the grid, 0 for the outer ring, whatever the height
+fn ring_of((x, y, _): Vec3, (mx, my, _): Vec3) -> isize {
+    x.min(y).min(mx - 1 - x).min(my - 1 - y)
+}
+
+// index_to_coord without borrowing the grid, for when its cells are already borrowed
+fn coord_at(idx: usize, dimensions: Vec3) -> Vec3 {
+    let idx = idx as isize;
+    let dims = Pos3::from(dimensions);
+    Pos3::new(
+        idx % dims.x,
+        (idx / dims.x) % dims.y,
+        idx / (dims.x * dims.y),
+    )
+    .into()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Direction {
     North,
     South,
@@ -125,34 +720,71 @@ pub enum Direction {
     None,
 }
 
-impl From<Key> for Direction {
-    fn from(key: Key) -> Self {
-        match key {
-            Key::Up => Direction::North,
-            Key::Down => Direction::South,
-            Key::Left => Direction::West,
-            Key::Right => Direction::East,
-            Key::Space => Direction::Up, // TODO : pour tester le saut voir
-            _ => Direction::None,
+impl Direction {
+    /// Every way to go from a cell to one of its six neighbors, the four horizontal ones first.
+    pub const ALL: [Direction; 6] = [
+        Direction::North,
+        Direction::South,
+        Direction::West,
+        Direction::East,
+        Direction::Up,
+        Direction::Down,
+    ];
+
+    /// The directions a snake can actually be steered in (Up is only there to test jumping).
+    pub fn horizontal() -> [Direction; 4] {
+        [
+            Direction::North,
+            Direction::South,
+            Direction::West,
+            Direction::East,
+        ]
+    }
+
+    /// Where to go to come right back.
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+            Direction::East => Direction::West,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::None => Direction::None,
+        }
+    }
+
+    /// How much a position moves when going in this direction.
+    pub fn delta(self) -> Pos3 {
+        match self {
+            Direction::North => Pos3::new(0, -1, 0),
+            Direction::South => Pos3::new(0, 1, 0),
+            Direction::West => Pos3::new(-1, 0, 0),
+            Direction::East => Pos3::new(1, 0, 0),
+            Direction::Up => Pos3::new(0, 0, 1),
+            Direction::Down => Pos3::new(0, 0, -1),
+            Direction::None => Pos3::ZERO,
         }
     }
 }
 
+/// Finds the direction of a one cell step, gives the delta back if it isn't one.
+impl TryFrom<Vec3> for Direction {
+    type Error = Vec3;
+
+    fn try_from(delta: Vec3) -> Result<Self, Vec3> {
+        Direction::ALL
+            .into_iter()
+            .find(|dir| Vec3::from(dir.delta()) == delta)
+            .ok_or(delta)
+    }
+}
+
 impl Add<Direction> for Vec3 {
     type Output = Vec3;
 
     fn add(self, dir: Direction) -> Vec3 {
-        let (x, y, z) = self;
-        let (dx, dy, dz) = match dir {
-            Direction::North => (0, -1, 0),
-            Direction::South => (0, 1, 0),
-            Direction::West => (-1, 0, 0),
-            Direction::East => (1, 0, 0),
-            Direction::Up => (0, 0, 1),
-            Direction::Down => (0, 0, -1),
-            Direction::None => (0, 0, 0),
-        };
-        (x + dx, y + dy, z + dz)
+        (Pos3::from(self) + dir).into()
     }
 }
 
@@ -162,133 +794,2763 @@ impl Add<Direction> for Vec3 {
 struct Snake {
     direction: Direction,
     body: VecDeque<Vec3>,
+    // combien de segments sur chaque case, tenu à jour avec body pour ne pas le reparcourir à chaque tick
+    occupancy: HashMap<Vec3, u32>,
+    pending_growth: usize, // segments still to grow, one per move where the tail stays put
 }
 
 impl Snake {
-    fn new(pos: Vec3) -> Self {
-        let mut body = VecDeque::new();
-        body.push_back(pos);
+    fn new(direction: Direction, body: VecDeque<Vec3>) -> Self {
+        let mut occupancy = HashMap::with_capacity(body.len());
+        for &segment in &body {
+            *occupancy.entry(segment).or_insert(0) += 1;
+        }
         Self {
-            direction: Direction::None,
+            direction,
             body,
+            occupancy,
+            pending_growth: 0,
         }
     }
 
+    // le corps part de la tête vers l'arrière, à l'opposé de là où elle regarde
+    fn with_length(pos: Vec3, facing: Direction, len: usize) -> Self {
+        let body = std::iter::successors(Some(Pos3::from(pos)), |&segment| {
+            Some(segment - facing.delta())
+        })
+        .map(Vec3::from)
+        .take(len.max(1))
+        .collect();
+        Self::new(Direction::None, body)
+    }
+
     fn head(&self) -> &Vec3 {
         self.body
             .front()
             .expect("C'est pas normal un serpent sans queue ni tête (pun intended)")
     }
 
-    fn move_to(&mut self, target: Vec3, growing: bool) {
-        self.body.push_front(target);
-        if !growing {
-            self.body.pop_back();
+    fn is_occupied(&self, coord: Vec3) -> bool {
+        self.occupancy.contains_key(&coord)
+    }
+
+    // returns where the tail was, if it moved
+    fn move_to(&mut self, target: Vec3) -> Option<Vec3> {
+        self.push_front(target);
+        if self.pending_growth > 0 {
+            self.pending_growth -= 1;
+            None
+        } else {
+            self.pop_back()
+        }
+    }
+
+    // the undo of move_to
+    fn move_back(&mut self, tail: Option<Vec3>) {
+        if let Some(head) = self.body.pop_front() {
+            self.vacate(head);
+        }
+        if let Some(tail) = tail {
+            self.body.push_back(tail);
+            *self.occupancy.entry(tail).or_insert(0) += 1;
         }
     }
 
+    /// Only the head can have just run into the rest of the body.
     fn is_superlapping(&self) -> bool {
-        let mut seen = HashSet::new();
-        self.body.iter().any(|&coord| !seen.insert(coord))
+        self.occupancy
+            .get(self.head())
+            .is_some_and(|&count| count > 1)
+    }
+
+    fn push_front(&mut self, coord: Vec3) {
+        self.body.push_front(coord);
+        *self.occupancy.entry(coord).or_insert(0) += 1;
+    }
+
+    fn pop_back(&mut self) -> Option<Vec3> {
+        let tail = self.body.pop_back()?;
+        self.vacate(tail);
+        Some(tail)
     }
-}
 
-impl Draw for Snake {
-    fn draw(&self, pencil: &mut Pencil) {
-        for &coord in &self.body {
-            let screen_vec = coord_to_screen(coord);
-            pencil.draw_char('S', screen_vec);
+    fn vacate(&mut self, coord: Vec3) {
+        if let Entry::Occupied(mut entry) = self.occupancy.entry(coord) {
+            *entry.get_mut() -= 1;
+            if *entry.get() == 0 {
+                entry.remove();
+            }
         }
     }
 }
 
-#[derive(Debug)]
-pub struct GameState {
-    grid: Grid,
-    snake: Snake,
+/// What happens to the snake once its head has landed somewhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Move,
+    Eat,
+    Slide,             // moves, but won't listen to the player next tick
+    Unlock(u8),        // moves onto a key
+    Convey(Direction), // moves, and gets pushed this way next tick whatever the player wants
+    Push(Direction),   // moves where a crate was, the crate going one further this way
 }
 
-impl GameState {
-    pub fn new(starting_pos: Vec3, level: Grid) -> Self {
-        Self {
-            grid: level,
-            snake: Snake::new(starting_pos),
+/// Resolves where the head ends up when moving from `head` towards `dir`, what it lands on
+/// and which way it faces if that changed, without touching the grid.
+/// Stairs send it one level up or down, portals to the other portal of the pair,
+/// and crates are pushed out of the way when there is room behind them.
+fn resolve_move(
+    grid: &Grid,
+    head: Vec3,
+    dir: Direction,
+) -> Result<(Vec3, Step, Option<Direction>), GameError> {
+    let next_head = grid.step(head, dir);
+    match grid.get(next_head) {
+        Some(Cell::Stairs(dir)) => {
+            resolve_stairs(grid, head, next_head, dir).map(|(landing, step)| (landing, step, None))
         }
+        Some(Cell::Portal(id, _)) => resolve_portal(grid, head, next_head, id, dir),
+        Some(Cell::Crate) => {
+            resolve_push(grid, head, next_head, dir).map(|step| (next_head, step, None))
+        }
+        _ => resolve_landing(grid, head, next_head).map(|step| (next_head, step, None)),
     }
+}
 
-    pub fn update(&mut self, dir_held_player: Direction) -> Result<()> {
-        let dir = if dir_held_player == Direction::None {
-            self.snake.direction
-        } else {
-            dir_held_player
-        };
-        self.snake.direction = dir;
+fn resolve_stairs(
+    grid: &Grid,
+    head: Vec3,
+    next_head: Vec3,
+    dir: Direction,
+) -> Result<(Vec3, Step), GameError> {
+    let landing = grid.step(next_head, dir);
+    // the snake has to be able to stand where it arrives, otherwise the stairs are a wall
+    let lands = grid.cell_below(landing).is_some_and(Cell::supports)
+        && !matches!(grid.get(landing), Some(Cell::Stairs(_))); // no chaining stairs
+    match resolve_landing(grid, head, landing) {
+        Ok(step) if lands => Ok((landing, step)),
+        _ => Err(GameError::SnakeCollision {
+            head,
+            attempted_move: next_head,
+        }),
+    }
+}
 
-        let next_head = *self.snake.head() + dir;
-        if let Some(cell) = self.grid.get(next_head)
-            && cell != Cell::Block
-        {
-            // falls if not on a block
-            let cell = match self.grid.get(next_head + Direction::Down) {
-                Some(Cell::Block) => {
-                    cell // if there is a block under the next head, we can move
-                }
-                Some(c) => {
-                    c // if not we fall if there is somewhere to fall
-                }
-                None => {
-                    // otherwise we die falling out of the map
-                    return Err(GameError::SnakeFell {
-                        head: *self.snake.head(),
-                        attempted_move: next_head,
-                    }
-                    .into());
-                }
-            };
+// l'élan est gardé : on ressort dans le sens où on allait, sauf si le portail de sortie impose le sien
+fn resolve_portal(
+    grid: &Grid,
+    head: Vec3,
+    entry: Vec3,
+    id: u8,
+    travel: Direction,
+) -> Result<(Vec3, Step, Option<Direction>), GameError> {
+    let blocked = GameError::SnakeCollision {
+        head,
+        attempted_move: entry,
+    };
+    // a portal without exactly one partner is just a wall
+    let mut partners = grid.iter_occupied().filter(|&(coord, cell)| {
+        coord != entry && matches!(cell, Cell::Portal(other, _) if other == id)
+    });
+    let (Some((exit, Cell::Portal(_, facing))), None) = (partners.next(), partners.next()) else {
+        return Err(blocked);
+    };
+    let dir = match facing {
+        Direction::None => travel,
+        facing => facing,
+    };
+    let landing = grid.step(exit, dir);
+    // no going from a portal straight into another one, that's how a tick never ends
+    if landing == exit || matches!(grid.get(landing), Some(Cell::Portal(..))) {
+        return Err(blocked);
+    }
+    let step = resolve_landing(grid, head, landing)?;
+    let turned = Direction::horizontal().contains(&dir).then_some(dir);
+    Ok((landing, step, turned))
+}
 
-            // Faudrait changer ça si on ajoute des upgrades pour traverser les murs par exemple
-            match cell {
-                Cell::Empty => {
-                    self.snake.move_to(next_head, false);
-                }
-                Cell::Food => {
-                    self.snake.move_to(next_head, true);
-                    self.grid.set(next_head, Cell::Empty)?;
-                }
-                _ => unreachable!(),
-            }
-        } else {
-            return Err(GameError::SnakeCollision {
-                head: *self.snake.head(),
+// Comme au Sokoban : une seule caisse à la fois, et seulement vers une case vide
+fn resolve_push(
+    grid: &Grid,
+    head: Vec3,
+    next_head: Vec3,
+    dir: Direction,
+) -> Result<Step, GameError> {
+    let blocked = GameError::SnakeCollision {
+        head,
+        attempted_move: next_head,
+    };
+    if !Direction::horizontal().contains(&dir)
+        || grid.get(grid.step(next_head, dir)) != Some(Cell::Empty)
+    {
+        return Err(blocked);
+    }
+    // the snake then stands where the crate was, falling from there like from anywhere else
+    match grid.cell_below(next_head) {
+        Some(_) => Ok(Step::Push(dir)),
+        None => Err(GameError::SnakeFell {
+            head,
+            attempted_move: next_head,
+        }),
+    }
+}
+
+// what happens once the head is at `next_head`, wherever it came from
+fn resolve_landing(grid: &Grid, head: Vec3, next_head: Vec3) -> Result<Step, GameError> {
+    let Some(cell) = grid.get(next_head).filter(|&cell| !cell.is_solid()) else {
+        return Err(GameError::SnakeCollision {
+            head,
+            attempted_move: next_head,
+        });
+    };
+
+    // falls if not on a block
+    let cell = match grid.cell_below(next_head) {
+        Some(below) if below.supports() => {
+            cell // if there is a block under the next head, we can move
+        }
+        Some(c) => {
+            c // if not we fall if there is somewhere to fall
+        }
+        None => {
+            // otherwise we die falling out of the map
+            return Err(GameError::SnakeFell {
+                head,
                 attempted_move: next_head,
-            }
-            .into());
+            });
         }
-        if self.snake.is_superlapping() {
-            return Err(GameError::SnakeCannibalism {
-                head: *self.snake.head(),
+    };
+
+    // Pas de _ ici, comme ça un nouveau type de case oblige à décider quoi en faire
+    match cell {
+        Cell::Empty => Ok(Step::Move),
+        Cell::Food => Ok(Step::Eat),
+        Cell::Ice => Ok(Step::Slide),
+        Cell::Conveyor(dir) => Ok(Step::Convey(dir)),
+        Cell::Key(id) => Ok(Step::Unlock(id)),
+        Cell::Poison => Err(GameError::Poisoned {
+            head,
+            attempted_move: next_head,
+        }),
+        Cell::Spike => {
+            // no support on a spike either, landing on one is just as deadly
+            Err(GameError::Impaled {
+                head,
                 attempted_move: next_head,
-            }
-            .into());
+            })
+        }
+        Cell::Void
+        | Cell::Block
+        | Cell::Door(_)
+        | Cell::Stairs(_)
+        | Cell::Portal(..)
+        | Cell::Crate
+        | Cell::Crumble => {
+            // anything we don't know how to stand in is as solid as a wall
+            Err(GameError::SnakeCollision {
+                head,
+                attempted_move: next_head,
+            })
         }
-        Ok(())
     }
 }
 
-impl Draw for GameState {
-    fn draw(&self, pencil: &mut Pencil) {
-        self.grid.draw(pencil);
-        self.snake.draw(pencil);
-    }
+/// Every position the head can legally reach from `pos` in a single tick.
+pub fn legal_moves(grid: &Grid, pos: Vec3) -> Vec<Vec3> {
+    legal_steps(grid, pos)
+        .into_iter()
+        .map(|(_, landing)| landing)
+        .collect()
 }
 
-#[derive(Debug, Error)]
-#[allow(clippy::enum_variant_names)] // passque y'aura maybe d'autres erreurs que juste le serpent
-enum GameError {
-    #[error("Snake collision when attempting to move head from {head:?} to {attempted_move:?}")]
-    SnakeCollision { head: Vec3, attempted_move: Vec3 },
-    #[error("Snake at {head:?} tried to eat itself at {attempted_move:?}")]
+/// Same as [`legal_moves`] along with the direction to take for each,
+/// since with stairs the head doesn't always end up next to `pos`.
+pub fn legal_steps(grid: &Grid, pos: Vec3) -> Vec<(Direction, Vec3)> {
+    Direction::horizontal()
+        .into_iter()
+        .filter_map(|dir| {
+            resolve_move(grid, pos, dir)
+                .ok()
+                .map(|(landing, _, _)| (dir, landing))
+        })
+        .collect()
+}
+
+/// Shortest sequence of legal head positions from `from` to `to`, both included.
+pub fn pathfind(grid: &Grid, from: Vec3, to: Vec3) -> Option<Vec<Vec3>> {
+    bfs(grid, from, |pos| pos == to, |_| true)
+}
+
+/// Breadth first over [`legal_moves`], only through `is_free` positions, until one matching `is_goal`
+/// is found. The path to it comes back like with [`pathfind`].
+pub fn bfs(
+    grid: &Grid,
+    from: Vec3,
+    is_goal: impl Fn(Vec3) -> bool,
+    is_free: impl Fn(Vec3) -> bool,
+) -> Option<Vec<Vec3>> {
+    let mut came_from = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    came_from.insert(from, from);
+    while let Some(pos) = queue.pop_front() {
+        if is_goal(pos) {
+            let mut path = vec![pos];
+            let mut current = pos;
+            while current != from {
+                current = came_from[&current];
+                path.push(current);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for next in legal_moves(grid, pos) {
+            if !is_free(next) {
+                continue;
+            }
+            if let Entry::Vacant(entry) = came_from.entry(next) {
+                entry.insert(pos);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+#[derive(Debug, Default)]
+// Le chemin vers la bouffe la plus proche, recalculé seulement quand la tête ou la grille bougent
+struct Hint {
+    computed_for: Option<(Vec3, u64)>,
+    path: Vec<Vec3>,
+}
+
+/// Gets called at the relevant points of [`GameState::update`], every method does nothing by default.
+pub trait GameObserver: std::fmt::Debug {
+    fn on_move(&mut self, _head: Vec3) {}
+    fn on_eat(&mut self, _food: Vec3) {}
+    fn on_death(&mut self, _reason: &GameError) {}
+    /// Everything the event log gets, whether it's enabled or not.
+    fn on_event(&mut self, _event: &GameEvent) {}
+}
+
+/// How many ticks can be undone in practice mode.
+const UNDO_HISTORY_SIZE: usize = 50;
+
+// Ce qu'un tick a changé, pour pouvoir revenir en arrière sans garder des copies de toute la grille
+#[derive(Debug)]
+struct UndoStep {
+    moved: bool,              // whether a new head was pushed
+    tail: Option<Vec3>,       // popped from the back of the body
+    cells: Vec<(Vec3, Cell)>, // what the modified cells held before
+    direction: Direction,
+    score: u32,
+    eaten: u32,
+    ticks_since_eat: Option<u32>,
+    multiplier: u32,
+    tick: u32,
+    played: Duration,
+    ticks_airborne: u32,
+    jumping: bool,
+    sliding: bool,
+    conveyed: Option<Direction>,
+    pending_growth: usize,
+    collected: Option<u8>, // key picked up on this tick
+    mobs: Vec<Mob>,
+}
+
+/// How many turns can be queued up in advance before new ones get ignored.
+const INPUT_BUFFER_SIZE: usize = 3;
+/// How many points a food is worth at most in the middle of a streak.
+pub const MAX_MULTIPLIER: u32 = 8;
+/// How many ticks the snake can spend with nothing under its head before it starts falling.
+const DEFAULT_COYOTE_TICKS: u32 = 2;
+
+/// Everything that can be tweaked about a game, the defaults being the normal game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameConfig {
+    pub fps: u32,
+    pub ticks_per_second: u32, // how often the game logic runs, whatever the frame rate
+    pub dimensions: Option<Vec3>, // None to fit the level to the terminal
+    pub coyote_ticks: u32,
+    pub practice: bool, // allows undoing moves
+    pub endless: bool,  // food keeps coming back, the level is never complete
+    pub starting_length: usize,
+    pub starting_facing: Direction, // the rest of the body is laid out behind the head
+    pub time_limit: Option<u32>,    // in ticks, for time attack
+    pub shrink_every: Option<u32>,  // in ticks, the arena loses its outer ring this often
+    pub two_players: bool,          // a second snake starts on the other side of the level
+    pub rival: bool, // that second snake is the computer's, and the game goes on without it
+    pub expanding: bool, // eating adds a new piece to the level, with the next food in it
+    pub loops: bool, // closing a loop with the body scores a point per cell inside
+    pub streak: Option<u32>, // eating again within this many ticks makes each food worth one more point
+    pub trail: bool,         // every cell the tail leaves becomes a wall, like a light cycle
+    pub wrap: Wrap, // edges that lead back in from the other side, on top of the level's own
+    pub fog: Option<u32>, // only what's this close to the head is seen, the rest stays as it was last seen
+    pub dark: bool,       // the fog doesn't remember anything, what's out of sight isn't drawn
+    pub seed: u64,
+    // the rest is what Difficulty sets, see Difficulty::apply_to
+    pub difficulty: Difficulty, // only a label, the settings below are what makes it
+    pub foods: usize,           // how many foods endless mode keeps on the board
+    pub poison_percent: u32,    // the chance that a poison spawns along with a food
+    pub reverse_guard: bool,    // turning straight back is ignored instead of biting the neck
+    pub speedup_percent: u32,   // how much faster the game ticks with every food eaten
+    pub max_speedup_percent: u32, // where it stops speeding up, 100 being twice as fast
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            fps: 30,
+            ticks_per_second: 20,
+            dimensions: None,
+            coyote_ticks: DEFAULT_COYOTE_TICKS,
+            practice: false,
+            endless: false,
+            starting_length: 1,
+            starting_facing: Direction::North,
+            time_limit: None,
+            shrink_every: None,
+            two_players: false,
+            rival: false,
+            expanding: false,
+            loops: false,
+            streak: None,
+            trail: false,
+            wrap: Wrap::default(),
+            fog: None,
+            dark: false,
+            seed: 0,
+            difficulty: Difficulty::Normal,
+            foods: 1,
+            poison_percent: 0,
+            reverse_guard: true,
+            speedup_percent: 0,
+            max_speedup_percent: 0,
+        }
+    }
+}
+
+impl GameConfig {
+    /// How much faster than `ticks_per_second` the game goes once `foods` have been eaten,
+    /// in percent: `speedup_percent` more for each, up to `max_speedup_percent`.
+    pub fn speedup_after(&self, foods: u32) -> u32 {
+        self.speedup_percent
+            .saturating_mul(foods)
+            .min(self.max_speedup_percent)
+    }
+}
+
+// Ce qui est à chaque serpent, le reste de la partie est partagé
+#[derive(Debug)]
+struct Player {
+    snake: Snake,
+    inputs: VecDeque<Direction>, // one is consumed per tick, so quick successive turns all count
+    ticks_airborne: u32,
+    jumping: bool, // only one jump until the snake is back on the ground
+    sliding: bool,
+    conveyed: Option<Direction>, // where the conveyor under the head pushes it on the next tick
+    score: u32,
+    eaten: u32,                   // not the score, closing loops scores without eating
+    ticks_since_eat: Option<u32>, // None until the first food
+    multiplier: u32,              // what the next food is worth if it comes in time
+}
+
+impl Player {
+    fn new(snake: Snake) -> Self {
+        Self {
+            snake,
+            inputs: VecDeque::with_capacity(INPUT_BUFFER_SIZE),
+            ticks_airborne: 0,
+            jumping: false,
+            sliding: false,
+            conveyed: None,
+            score: 0,
+            eaten: 0,
+            ticks_since_eat: None,
+            multiplier: 1,
+        }
+    }
+
+    fn queue_input(&mut self, dir: Direction, reverse_guard: bool) {
+        let last = self.inputs.back().copied().unwrap_or(self.snake.direction);
+        // tout droit en arrière, la tête irait dans le cou. Avant le premier pas le serpent
+        // n'a pas de direction, c'est là où est son cou qui dit où est l'arrière
+        let back = match (last, self.snake.body.get(1)) {
+            (Direction::None, Some(&neck)) => Direction::horizontal()
+                .into_iter()
+                .find(|&dir| *self.snake.head() + dir == neck)
+                .unwrap_or(Direction::None),
+            _ => last.opposite(),
+        };
+        let reversing = reverse_guard && self.snake.body.len() > 1 && dir == back;
+        if dir == Direction::None
+            || dir == last
+            || reversing
+            || self.inputs.len() >= INPUT_BUFFER_SIZE
+        {
+            return;
+        }
+        self.inputs.push_back(dir);
+    }
+
+    fn take_input(&mut self) -> Direction {
+        // on ice or a conveyor the inputs wait until we're back on something with grip
+        if self.sliding || self.conveyed.is_some() {
+            Direction::None
+        } else {
+            self.inputs.pop_front().unwrap_or(Direction::None)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GameState {
+    grid: Grid,
+    grid_revision: u64,   // incremented every time the grid is modified
+    players: Vec<Player>, // the one at the keyboard first, then the second one in two player mode
+    losers: Vec<usize>,   // who died on the last tick
+    rival: bool,          // the second snake is left out when it dies, see GameConfig::rival
+    coyote_ticks: u32,
+    endless: bool,
+    time_limit: Option<u32>,
+    shrink_every: Option<u32>,
+    expanding: bool,
+    loops: bool,
+    streak: Option<u32>,
+    trail: bool,
+    difficulty: Difficulty,
+    foods: usize,
+    poison_percent: u32,
+    reverse_guard: bool,
+    starting_length: usize,
+    speedup_percent: u32,
+    max_speedup_percent: u32,
+    keys: BTreeSet<u8>, // ids of the keys picked up
+    rng: Rng,
+    tick: u32,
+    ticks_per_second: u32,
+    played: Duration, // the ticks added up at the pace each was played at
+    events: Option<Vec<(u32, GameEvent)>>, // only kept when asked for, see enable_event_log
+    hint: Option<Hint>,
+    mobs: Vec<Mob>,
+    observer: Option<Box<dyn GameObserver>>,
+    visibility: Option<Visibility>,      // only in fog of war mode
+    history: Option<VecDeque<UndoStep>>, // only in practice mode, see enable_undo
+}
+
+impl GameState {
+    /// Fails if the snake doesn't fit where it should start. In two player or rival mode the
+    /// second one starts on the opposite side of the level, looking the other way, and has to fit too.
+    pub fn new(
+        starting_pos: Vec3,
+        mut level: Grid,
+        config: &GameConfig,
+    ) -> Result<Self, SpawnError> {
+        level.set_wrap(level.wrap().or(config.wrap));
+        let mut snakes = vec![Snake::with_length(
+            starting_pos,
+            config.starting_facing,
+            config.starting_length,
+        )];
+        if config.two_players || config.rival {
+            let (mx, my, _) = level.dimensions;
+            let (x, y, z) = starting_pos;
+            snakes.push(Snake::with_length(
+                (mx - 1 - x, my - 1 - y, z),
+                config.starting_facing.opposite(),
+                config.starting_length,
+            ));
+        }
+        let mut taken = HashSet::new();
+        for &segment in snakes.iter().flat_map(|snake| &snake.body) {
+            if !contains(segment, level.dimensions) {
+                return Err(SpawnError::OutOfBounds(segment));
+            }
+            if level
+                .get(segment)
+                .is_none_or(|cell| cell.is_solid() || cell == Cell::Spike)
+                || !taken.insert(segment)
+            {
+                return Err(SpawnError::Blocked(segment));
+            }
+        }
+        let mut game = Self {
+            grid: level,
+            grid_revision: 0,
+            players: snakes.into_iter().map(Player::new).collect(),
+            losers: Vec::new(),
+            rival: config.rival,
+            coyote_ticks: config.coyote_ticks,
+            endless: config.endless,
+            time_limit: config.time_limit,
+            shrink_every: config.shrink_every,
+            expanding: config.expanding,
+            loops: config.loops,
+            streak: config.streak,
+            trail: config.trail,
+            difficulty: config.difficulty,
+            foods: config.foods,
+            poison_percent: config.poison_percent,
+            reverse_guard: config.reverse_guard,
+            starting_length: config.starting_length,
+            speedup_percent: config.speedup_percent,
+            max_speedup_percent: config.max_speedup_percent,
+            keys: BTreeSet::new(),
+            rng: Rng::new(config.seed),
+            tick: 0,
+            ticks_per_second: config.ticks_per_second,
+            played: Duration::ZERO,
+            events: None,
+            hint: None,
+            mobs: Vec::new(),
+            observer: None,
+            visibility: config.fog.map(if config.dark {
+                Visibility::dark
+            } else {
+                Visibility::new
+            }),
+            history: None,
+        };
+        if config.practice {
+            game.enable_undo();
+        }
+        game.look_around();
+        Ok(game)
+    }
+
+    /// Starts keeping every [`GameEvent`] along with the tick it happened on.
+    pub fn enable_event_log(&mut self) {
+        self.events.get_or_insert_with(Vec::new);
+    }
+
+    /// Everything that happened since the event log was enabled, empty if it wasn't.
+    pub fn events(&self) -> &[(u32, GameEvent)] {
+        self.events.as_deref().unwrap_or_default()
+    }
+
+    /// Empties the event log and gives back what was in it, the log staying enabled.
+    /// For those that only care about what's new since they last looked.
+    pub fn take_events(&mut self) -> Vec<(u32, GameEvent)> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Plugs something in to be told about what happens in the game (sounds, logs, stats...).
+    pub fn set_observer(&mut self, observer: impl GameObserver + 'static) {
+        self.observer = Some(Box::new(observer));
+    }
+
+    pub fn score(&self) -> u32 {
+        self.players[0].score
+    }
+
+    /// How many foods the first player ate, what the game speeds up with.
+    pub fn foods_eaten(&self) -> u32 {
+        self.players[0].eaten
+    }
+
+    /// How much faster than its tick rate the game goes by now, in percent,
+    /// like [`GameConfig::speedup_after`] with the foods the first player ate.
+    pub fn speedup(&self) -> u32 {
+        self.speedup_percent
+            .saturating_mul(self.foods_eaten())
+            .min(self.max_speedup_percent)
+    }
+
+    /// Ticks since the first player last ate, `None` before the first food.
+    pub fn ticks_since_eat(&self) -> Option<u32> {
+        self.players[0].ticks_since_eat
+    }
+
+    /// What the first player's next food is worth if it's eaten before the streak runs out,
+    /// always 1 without streaks.
+    pub fn multiplier(&self) -> u32 {
+        self.players[0].multiplier
+    }
+
+    /// The ticks left for the first player to keep the streak going, `None` when there is none.
+    pub fn streak_left(&self) -> Option<u32> {
+        let player = &self.players[0];
+        let window = self.streak.filter(|_| player.multiplier > 1)?;
+        Some(window.saturating_sub(player.ticks_since_eat?))
+    }
+
+    /// One, or two in two player mode and while the rival is alive.
+    pub fn player_count(&self) -> usize {
+        self.players.len()
+    }
+
+    /// The score of the first or the second player.
+    pub fn score_of(&self, player: usize) -> u32 {
+        self.players[player].score
+    }
+
+    /// Who won a two player game once it's over: the last one alive, or the best score
+    /// once all the food is eaten. `None` while it's going on and for a draw.
+    pub fn winner(&self) -> Option<usize> {
+        if self.players.len() < 2 {
+            return None;
+        }
+        match self.losers.as_slice() {
+            &[loser] => Some(1 - loser),
+            [] if self.is_complete() => {
+                let (first, second) = (self.players[0].score, self.players[1].score);
+                (first != second).then_some(if first > second { 0 } else { 1 })
+            }
+            _ => None,
+        }
+    }
+
+    /// What's right under `coord`, the gravity checks are all about this.
+    pub fn cell_below(&self, coord: Vec3) -> Option<Cell> {
+        self.grid.cell_below(coord)
+    }
+
+    /// How many ticks have been played, the game's own clock.
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// How long the game has been played, each tick counted at the pace it came at,
+    /// so it's real time even once the game has sped up.
+    pub fn time_played(&self) -> Duration {
+        self.played
+    }
+
+    /// How long a tick lasts at the pace the game has sped up to.
+    pub fn tick_length(&self) -> Duration {
+        Duration::from_secs(1) * 100 / (self.ticks_per_second * (100 + self.speedup()))
+    }
+
+    /// Ticks left before the time is up, `None` when not playing against the clock.
+    pub fn remaining_ticks(&self) -> Option<u32> {
+        self.time_limit.map(|limit| limit.saturating_sub(self.tick))
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    /// Switches to the settings of `difficulty` that the game itself holds, the speed
+    /// being the frontend's. Only before the first tick, a run is played at one difficulty.
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) -> Result<(), DifficultyError> {
+        if self.tick > 0 {
+            return Err(DifficultyError::AlreadyStarted(self.tick));
+        }
+        let config = difficulty.to_config();
+        self.difficulty = difficulty;
+        self.foods = config.foods;
+        self.poison_percent = config.poison_percent;
+        self.reverse_guard = config.reverse_guard;
+        self.speedup_percent = config.speedup_percent;
+        self.max_speedup_percent = config.max_speedup_percent;
+        Ok(())
+    }
+
+    /// Whether the tails leave walls behind, so that a snake can't follow its own tail.
+    pub fn leaves_trail(&self) -> bool {
+        self.trail
+    }
+
+    /// Ticks left before the outer ring of the arena collapses, `None` when it isn't shrinking (anymore).
+    pub fn ticks_until_shrink(&self) -> Option<u32> {
+        self.next_collapse().map(|(tick, _)| tick - self.tick)
+    }
+
+    /// Whether `coord` is part of the ring that collapses next.
+    pub fn is_doomed(&self, coord: Vec3) -> bool {
+        self.next_collapse()
+            .is_some_and(|(_, ring)| ring_of(coord, self.grid.dimensions) == ring)
+    }
+
+    // when the next ring goes and which one it is, counting from the edge
+    fn next_collapse(&self) -> Option<(u32, isize)> {
+        let every = self.shrink_every?;
+        let collapses = self.tick.max(1).div_ceil(every);
+        let ring = collapses as isize - 1;
+        // on garde toujours le centre
+        let (mx, my, _) = self.grid.dimensions;
+        (ring < (mx.min(my) - 1) / 2).then_some((collapses * every, ring))
+    }
+
+    /// The level is done once there is nothing left to eat, never in endless or expanding mode.
+    pub fn is_complete(&self) -> bool {
+        !self.endless && !self.expanding && self.grid.count(Cell::Food) == 0
+    }
+
+    /// Plays the game without a terminal, feeding one input per tick
+    /// (`Direction::None` to keep going), until the inputs run out,
+    /// the snake dies or the level is complete.
+    pub fn run_script(&mut self, inputs: impl IntoIterator<Item = Direction>) -> SimulationReport {
+        let mut ticks = 0;
+        let mut death = None;
+        for dir in inputs {
+            if self.is_complete() {
+                break;
+            }
+            self.queue_input(dir);
+            if let Err(err) = self.update() {
+                death = Some(err);
+                break;
+            }
+            ticks += 1;
+        }
+        SimulationReport {
+            length: self.players[0].snake.body.len(),
+            score: self.score(),
+            ticks,
+            death,
+        }
+    }
+
+    /// The level as it is now, what's been eaten, pushed or unlocked included.
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// Where the head of the first snake is.
+    pub fn head(&self) -> Vec3 {
+        self.head_of(0)
+    }
+
+    /// Same as [`GameState::head`] for the first or the second snake.
+    pub fn head_of(&self, player: usize) -> Vec3 {
+        *self.players[player].snake.head()
+    }
+
+    /// Where the head of `player` should be after the next tick if nothing more is pressed,
+    /// going by the first queued turn and what's under it like [`GameState::step`] does.
+    /// A move that would kill gives back the cell it bumps into instead.
+    /// The mobs and the other snake's own move aren't accounted for.
+    pub fn predicted_head_of(&self, player: usize) -> Result<Vec3, Vec3> {
+        let p = &self.players[player];
+        let head = *p.snake.head();
+        let input = match p.inputs.front() {
+            Some(&dir) if !p.sliding && p.conveyed.is_none() => dir,
+            _ => Direction::None,
+        };
+        let supported = self.grid.cell_below(head).is_some_and(Cell::supports);
+        let in_coyote_time = supported || p.ticks_airborne < self.coyote_ticks;
+        let dir = if let Some(push) = p.conveyed.filter(|_| supported) {
+            push
+        } else if input == Direction::Up && in_coyote_time && (supported || !p.jumping) {
+            Direction::Up
+        } else if !in_coyote_time {
+            Direction::Down
+        } else if input != Direction::None && input != Direction::Up {
+            input
+        } else {
+            p.snake.direction
+        };
+
+        let attempted = self.grid.step(head, dir);
+        let Ok((next_head, step, _)) = resolve_move(&self.grid, head, dir) else {
+            return Err(attempted);
+        };
+        // la queue s'en va en même temps, sauf si le serpent grandit ou laisse un mur derrière lui
+        let tail_leaves = p.snake.pending_growth == 0 && step != Step::Eat && !self.trail;
+        let into_tail = tail_leaves && p.snake.body.back() == Some(&next_head);
+        let pushed_into_snake =
+            matches!(step, Step::Push(dir) if self.is_occupied(self.grid.step(next_head, dir)));
+        if (self.is_occupied(next_head) && !into_tail) || pushed_into_snake {
+            return Err(attempted);
+        }
+        Ok(next_head)
+    }
+
+    /// Whether some part of a snake is at `coord`.
+    pub fn is_occupied(&self, coord: Vec3) -> bool {
+        self.players
+            .iter()
+            .any(|player| player.snake.is_occupied(coord))
+    }
+
+    /// Where the first snake is, from the head to the tail.
+    pub fn snake_body(&self) -> impl Iterator<Item = Vec3> + '_ {
+        self.snake_body_of(0)
+    }
+
+    /// Same as [`GameState::snake_body`] for the first or the second snake.
+    pub fn snake_body_of(&self, player: usize) -> impl Iterator<Item = Vec3> + '_ {
+        self.players[player].snake.body.iter().copied()
+    }
+
+    /// Buffers a direction to be applied on one of the next ticks.
+    pub fn queue_input(&mut self, dir: Direction) {
+        self.queue_input_for(0, dir);
+    }
+
+    /// Same as [`GameState::queue_input`] for the first or the second player.
+    pub fn queue_input_for(&mut self, player: usize, dir: Direction) {
+        if let Some(player) = self.players.get_mut(player) {
+            player.queue_input(dir, self.reverse_guard);
+        }
+    }
+
+    /// Whether a player has no direction waiting to be applied.
+    pub fn inputs_empty(&self, player: usize) -> bool {
+        self.players
+            .get(player)
+            .is_none_or(|player| player.inputs.is_empty())
+    }
+
+    pub fn toggle_hint(&mut self) {
+        self.hint = match self.hint {
+            Some(_) => None,
+            None => Some(Hint::default()),
+        };
+        self.refresh_hint();
+    }
+
+    fn refresh_hint(&mut self) {
+        let key = (self.head(), self.grid_revision);
+        if let Some(hint) = &mut self.hint
+            && hint.computed_for != Some(key)
+        {
+            hint.path = bfs(
+                &self.grid,
+                key.0,
+                |pos| self.grid.get(pos) == Some(Cell::Food),
+                |_| true,
+            )
+            .unwrap_or_default();
+            hint.computed_for = Some(key);
+        }
+    }
+
+    pub fn update(&mut self) -> Result<(), GameError> {
+        let input = self.take_input();
+        self.step(input)
+    }
+
+    /// The input the next tick will act on, `Direction::None` if there isn't one.
+    pub fn take_input(&mut self) -> Direction {
+        self.players[0].take_input()
+    }
+
+    /// Advances the game by one tick with the given input, bypassing the input buffer.
+    /// A second player still plays from its own buffer.
+    pub fn step(&mut self, input: Direction) -> Result<(), GameError> {
+        let mut undo = self.undo_step();
+        // au rythme d'avant le tick, c'est celui qui l'a amené
+        self.played += self.tick_length();
+        let result = self.advance(input, &mut undo);
+        if let Some(history) = &mut self.history {
+            if history.len() == UNDO_HISTORY_SIZE {
+                history.pop_front();
+            }
+            history.push_back(undo);
+        }
+        if let Err(reason) = &result {
+            self.log(GameEvent::Died(reason.clone()));
+            self.notify(|observer| observer.on_death(reason));
+        }
+        self.tick += 1;
+        self.look_around();
+        result
+    }
+
+    fn advance(&mut self, input: Direction, undo: &mut UndoStep) -> Result<(), GameError> {
+        self.losers.clear();
+        if self.remaining_ticks() == Some(0) {
+            return Err(GameError::TimeUp);
+        }
+        if let Some((tick, ring)) = self.next_collapse()
+            && tick == self.tick
+        {
+            self.collapse(ring, undo)?;
+        }
+
+        let mut deaths = Vec::new();
+        let heads_before: Vec<Vec3> = self.players.iter().map(|p| *p.snake.head()).collect();
+        for player in 0..self.players.len() {
+            let input = match player {
+                0 => input,
+                _ => self.players[player].take_input(),
+            };
+            if let Err(err) = self.move_player(player, input, undo) {
+                deaths.push((player, err));
+            }
+        }
+        // une fois que tout le monde a bougé, ce qui attrape aussi les face à face,
+        // même entre deux têtes toutes seules qui échangent leurs places
+        if let [first, second] = self.players.as_slice() {
+            for (player, (mover, other)) in
+                [(first, second), (second, first)].into_iter().enumerate()
+            {
+                let head = *mover.snake.head();
+                let swapped =
+                    head == heads_before[1 - player] && *other.snake.head() == heads_before[player];
+                if (other.snake.is_occupied(head) || swapped)
+                    && deaths.iter().all(|&(dead, _)| dead != player)
+                {
+                    deaths.push((player, GameError::HitOtherSnake { head }));
+                }
+            }
+        }
+        // les mobs bougent après les serpents : une tête peut leur rentrer dedans, ou se faire rattraper
+        if !self.mobs.is_empty() {
+            self.check_eaten(&mut deaths);
+            self.move_mobs();
+            self.check_eaten(&mut deaths);
+        }
+        // une caisse tombée sur un serpent l'écrase
+        for (player, p) in self.players.iter().enumerate() {
+            if let Some(&at) = p
+                .snake
+                .body
+                .iter()
+                .find(|&&segment| self.grid.get(segment) == Some(Cell::Crate))
+                && deaths.iter().all(|&(dead, _)| dead != player)
+            {
+                deaths.push((player, GameError::Crushed { at }));
+            }
+        }
+        // l'ordinateur qui meurt laisse juste la place, la partie continue sans lui
+        if self.rival && deaths.iter().any(|&(player, _)| player == 1) {
+            deaths.retain(|&(player, _)| player != 1);
+            self.players.truncate(1);
+        }
+        self.losers = deaths.iter().map(|&(player, _)| player).collect();
+        match deaths.into_iter().next() {
+            Some((_, err)) => Err(err),
+            None => {
+                self.refresh_hint();
+                Ok(())
+            }
+        }
+    }
+
+    fn move_player(
+        &mut self,
+        player: usize,
+        input: Direction,
+        undo: &mut UndoStep,
+    ) -> Result<(), GameError> {
+        self.digest(player);
+        let coyote_ticks = self.coyote_ticks;
+        let p = &mut self.players[player];
+        // Sauter c'est une impulsion, on garde la direction d'avant pour après
+        if input != Direction::None && input != Direction::Up {
+            p.snake.direction = input;
+        }
+
+        let head = *p.snake.head();
+        let supported = self.grid.cell_below(head).is_some_and(Cell::supports);
+        if supported {
+            p.ticks_airborne = 0;
+            p.jumping = false;
+        } else {
+            p.ticks_airborne += 1;
+        }
+        let in_coyote_time = p.ticks_airborne <= coyote_ticks;
+
+        let conveyed = p.conveyed.take();
+        let dir = if let Some(push) = conveyed.filter(|_| supported) {
+            push
+        } else if input == Direction::Up && in_coyote_time && !p.jumping {
+            p.jumping = true;
+            self.log(GameEvent::Jumped);
+            Direction::Up
+        } else if !in_coyote_time {
+            self.log(GameEvent::Fell);
+            Direction::Down // gravity commits
+        } else {
+            p.snake.direction
+        };
+
+        // Faudrait changer ça si on ajoute des upgrades pour traverser les murs par exemple
+        let (next_head, step, turned) = resolve_move(&self.grid, head, dir)?;
+        // the grid doesn't know about the snakes, a crate can't be pushed into one
+        if let Step::Push(dir) = step
+            && self.is_occupied(self.grid.step(next_head, dir))
+        {
+            return Err(GameError::SnakeCollision {
+                head,
+                attempted_move: next_head,
+            });
+        }
+        let p = &mut self.players[player];
+        if let Some(facing) = turned {
+            p.snake.direction = facing;
+        }
+        if step == Step::Eat {
+            p.snake.pending_growth += 1;
+        }
+        let tail = p.snake.move_to(next_head);
+        if next_head != head {
+            self.crumble(head + Direction::Down, undo);
+        }
+        if self.trail
+            && let Some(tail) = tail.filter(|&tail| tail != next_head)
+        {
+            self.lay_trail(tail, undo);
+        }
+        let p = &mut self.players[player];
+        p.sliding = step == Step::Slide;
+        p.conveyed = match step {
+            Step::Convey(dir) => Some(dir),
+            _ => None,
+        };
+        match step {
+            Step::Move | Step::Slide | Step::Convey(_) => (),
+            Step::Push(dir) => self.push_crate(next_head, dir, undo),
+            Step::Eat => {
+                undo.cells.push((next_head, Cell::Food));
+                self.grid.set(next_head, Cell::Empty)?;
+                self.grid_revision += 1;
+                self.eat(player);
+                self.log(GameEvent::Ate { coord: next_head });
+                self.notify(|observer| observer.on_eat(next_head));
+                if self.is_complete() {
+                    self.log(GameEvent::Completed);
+                }
+                // the level can't grow without somewhere to bridge from, then it's endless mode
+                let expanded = self.expanding && self.expand()?;
+                if (self.endless || self.expanding) && !expanded {
+                    for _ in self.grid.count(Cell::Food)..self.foods {
+                        self.spawn_food(undo);
+                    }
+                }
+            }
+            Step::Unlock(id) => self.unlock(id, next_head, undo),
+        }
+        if self.loops {
+            self.close_loops(player);
+        }
+        // only the first player can be undone, there is no undo with two
+        if player == 0 {
+            undo.tail = tail;
+            undo.moved = true;
+        }
+        self.log(GameEvent::Moved { head: next_head });
+        self.notify(|observer| observer.on_move(next_head));
+        // la queue ne laisse plus sa place, elle devient un mur au moment où la tête arrive
+        if self.trail && tail == Some(next_head) {
+            return Err(GameError::SnakeCollision {
+                head,
+                attempted_move: next_head,
+            });
+        }
+        if self.players[player].snake.is_superlapping() {
+            return Err(GameError::SnakeCannibalism {
+                head: next_head,
+                attempted_move: next_head,
+            });
+        }
+        Ok(())
+    }
+
+    // Manger à temps fait monter le multiplicateur, et la nourriture vaut ce qu'il valait avant
+    fn eat(&mut self, player: usize) {
+        let p = &mut self.players[player];
+        p.score += p.multiplier;
+        p.eaten += 1;
+        p.ticks_since_eat = Some(0);
+        if self.streak.is_some() {
+            p.multiplier = (p.multiplier + 1).min(MAX_MULTIPLIER);
+        }
+    }
+
+    // le temps passe depuis le dernier repas, et la série s'arrête si c'est trop long
+    fn digest(&mut self, player: usize) {
+        let p = &mut self.players[player];
+        p.ticks_since_eat = p.ticks_since_eat.map(|ticks| ticks + 1);
+        if let Some(window) = self.streak
+            && p.ticks_since_eat.is_some_and(|ticks| ticks > window)
+        {
+            p.multiplier = 1;
+        }
+    }
+
+    // Le bord de l'arène part dans le vide, et le serpent avec s'il était dessus
+    fn collapse(&mut self, ring: isize, undo: &mut UndoStep) -> Result<(), GameError> {
+        let dimensions = self.grid.dimensions;
+        let doomed: Vec<(Vec3, Cell)> = self
+            .grid
+            .iter_occupied()
+            .filter(|&(coord, _)| ring_of(coord, dimensions) == ring)
+            .collect();
+        for (coord, cell) in doomed {
+            undo.cells.push((coord, cell));
+            self.grid.set(coord, Cell::Void)?;
+        }
+        self.grid_revision += 1;
+        self.log(GameEvent::Shrunk { ring });
+        let mut rival_fell = false;
+        for (player, p) in self.players.iter().enumerate() {
+            let head = *p.snake.head();
+            if let Some(&segment) = p
+                .snake
+                .body
+                .iter()
+                .find(|&&segment| ring_of(segment, dimensions) <= ring)
+            {
+                if self.rival && player == 1 {
+                    rival_fell = true;
+                    continue;
+                }
+                self.losers.push(player);
+                return Err(GameError::SnakeFell {
+                    head,
+                    attempted_move: segment,
+                });
+            }
+        }
+        if rival_fell {
+            self.players.truncate(1);
+        }
+        Ok(())
+    }
+
+    // Pas d'aléatoire pour que les replays restent justes : l'endroit dépend juste du tick
+    fn spawn_food(&mut self, undo: &mut UndoStep) {
+        let spots: Vec<Vec3> = self
+            .grid
+            .find_all(Cell::Empty)
+            .into_iter()
+            .filter(|&coord| {
+                self.grid.cell_below(coord).is_some_and(Cell::is_solid)
+                    && !self.is_occupied(coord)
+                    && !self.is_doomed(coord)
+            })
+            .collect();
+        if spots.is_empty() {
+            return;
+        }
+        let coord = spots[self.tick as usize * 7919 % spots.len()];
+        undo.cells.push((coord, Cell::Empty));
+        // the spot comes from the grid itself
+        self.grid.set(coord, Cell::Food).unwrap();
+        // le poison aussi ne dépend que du tick, et va sur une autre des cases libres
+        if spots.len() > 1 && self.tick.wrapping_mul(104_729) % 100 < self.poison_percent {
+            let poison = spots[(self.tick as usize * 7919 + spots.len() / 2) % spots.len()];
+            undo.cells.push((poison, Cell::Empty));
+            self.grid.set(poison, Cell::Poison).unwrap();
+        }
+    }
+
+    // La caisse avance puis tombe, et celles empilées dessus tombent à sa place
+    fn push_crate(&mut self, from: Vec3, dir: Direction, undo: &mut UndoStep) {
+        undo.cells.push((from, Cell::Crate));
+        self.grid[from] = Cell::Empty;
+        let to = self.grid.step(from, dir);
+        undo.cells.push((to, Cell::Empty));
+        self.grid[to] = Cell::Crate;
+        self.settle_crate(to, undo);
+        self.settle_above(from, undo);
+        self.grid_revision += 1;
+    }
+
+    // the crates stacked over a cell that just emptied
+    fn settle_above(&mut self, coord: Vec3, undo: &mut UndoStep) {
+        let mut above = coord + Direction::Up;
+        while self.grid.get(above) == Some(Cell::Crate) {
+            self.settle_crate(above, undo);
+            above = above + Direction::Up;
+        }
+    }
+
+    // Une caisse tombe jusqu'à ce que quelque chose la tienne. Si c'est un serpent il est écrasé,
+    // elle reste dans sa case et advance s'en rend compte
+    fn settle_crate(&mut self, at: Vec3, undo: &mut UndoStep) {
+        let mut to = at;
+        while !self.is_occupied(to) && self.grid.cell_below(to) == Some(Cell::Empty) {
+            to = to + Direction::Down;
+        }
+        // lost in the void or out of the level
+        let lost = !self.is_occupied(to)
+            && self
+                .grid
+                .cell_below(to)
+                .is_none_or(|cell| cell == Cell::Void);
+        if to == at && !lost {
+            return;
+        }
+        undo.cells.push((at, Cell::Crate));
+        self.grid[at] = Cell::Empty;
+        if !lost {
+            undo.cells.push((to, Cell::Empty));
+            self.grid[to] = Cell::Crate;
+        }
+    }
+
+    // the crumbling floor the head just left, a crumbling floor it stays on holds as long as it wants
+    fn crumble(&mut self, under: Vec3, undo: &mut UndoStep) {
+        if self.grid.get(under) == Some(Cell::Crumble) {
+            undo.cells.push((under, Cell::Crumble));
+            self.grid[under] = Cell::Empty;
+            self.grid_revision += 1;
+        }
+    }
+
+    // La case que la queue vient de quitter devient un mur pour de bon
+    fn lay_trail(&mut self, tail: Vec3, undo: &mut UndoStep) {
+        undo.cells.push((tail, self.grid[tail]));
+        self.grid[tail] = Cell::Block;
+        self.grid_revision += 1;
+    }
+
+    // Ramasse la clé et ouvre toutes ses portes d'un coup
+    fn unlock(&mut self, id: u8, key: Vec3, undo: &mut UndoStep) {
+        let doors = self.grid.find_all(Cell::Door(id));
+        for &coord in std::iter::once(&key).chain(&doors) {
+            undo.cells.push((coord, self.grid[coord]));
+            self.grid[coord] = Cell::Empty;
+        }
+        for &door in &doors {
+            self.settle_above(door, undo);
+        }
+        self.grid_revision += 1;
+        if self.keys.insert(id) {
+            undo.collected = Some(id);
+        }
+        self.log(GameEvent::Unlocked { id });
+    }
+
+    /// Whether the key with this number has been picked up.
+    pub fn has_key(&self, id: u8) -> bool {
+        self.keys.contains(&id)
+    }
+
+    /// Keeps the last few ticks around so they can be undone (practice mode).
+    /// Does nothing with two players, whose moves can't be undone.
+    pub fn enable_undo(&mut self) {
+        if self.players.len() == 1 {
+            self.history
+                .get_or_insert_with(|| VecDeque::with_capacity(UNDO_HISTORY_SIZE));
+        }
+    }
+
+    /// Steps back one tick, returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(undo) = self.history.as_mut().and_then(VecDeque::pop_back) else {
+            return false;
+        };
+        let player = &mut self.players[0];
+        if undo.moved {
+            player.snake.move_back(undo.tail);
+        }
+        player.snake.direction = undo.direction;
+        player.score = undo.score;
+        player.eaten = undo.eaten;
+        player.ticks_since_eat = undo.ticks_since_eat;
+        player.multiplier = undo.multiplier;
+        player.ticks_airborne = undo.ticks_airborne;
+        player.jumping = undo.jumping;
+        player.sliding = undo.sliding;
+        player.conveyed = undo.conveyed;
+        player.snake.pending_growth = undo.pending_growth;
+        // backwards, a cell changed twice in a tick gets what it held first
+        for (coord, cell) in undo.cells.into_iter().rev() {
+            // the cells were in the grid when they were changed
+            self.grid.set(coord, cell).unwrap();
+        }
+        self.grid_revision += 1;
+        self.tick = undo.tick;
+        self.played = undo.played;
+        self.losers.clear();
+        if let Some(id) = undo.collected {
+            self.keys.remove(&id);
+        }
+        self.mobs = undo.mobs;
+        self.refresh_hint();
+        self.look_around();
+        true
+    }
+
+    // what's needed to undo the upcoming tick, the rest is filled in as it happens
+    fn undo_step(&self) -> UndoStep {
+        let player = &self.players[0];
+        UndoStep {
+            moved: false,
+            tail: None,
+            cells: Vec::new(),
+            direction: player.snake.direction,
+            score: player.score,
+            eaten: player.eaten,
+            ticks_since_eat: player.ticks_since_eat,
+            multiplier: player.multiplier,
+            tick: self.tick,
+            played: self.played,
+            ticks_airborne: player.ticks_airborne,
+            jumping: player.jumping,
+            sliding: player.sliding,
+            conveyed: player.conveyed,
+            pending_growth: player.snake.pending_growth,
+            collected: None,
+            mobs: self.mobs.clone(),
+        }
+    }
+
+    // changes whenever the fog moves, 0 without fog
+    pub(super) fn fog_revision(&self) -> u64 {
+        self.visibility.as_ref().map_or(0, Visibility::revision)
+    }
+
+    fn look_around(&mut self) {
+        let (head, dimensions) = (self.head(), self.grid.dimensions);
+        if let Some(visibility) = &mut self.visibility {
+            visibility.update(head, dimensions);
+        }
+    }
+
+    fn log(&mut self, event: GameEvent) {
+        self.notify(|observer| observer.on_event(&event));
+        if let Some(events) = &mut self.events {
+            events.push((self.tick, event));
+        }
+    }
+
+    fn notify(&mut self, event: impl FnOnce(&mut dyn GameObserver)) {
+        if let Some(observer) = &mut self.observer {
+            event(observer.as_mut());
+        }
+    }
+}
+
+/// What can happen during a tick, for tooling that wants to reconstruct a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    Moved { head: Vec3 },
+    Ate { coord: Vec3 },
+    Jumped,
+    Fell, // gravity took over
+    Unlocked { id: u8 },
+    Shrunk { ring: isize }, // the ring that collapsed, 0 being the edge of the grid
+    Expanded { dimensions: Vec3 },
+    Enclosed { cells: usize }, // a loop was closed around that many cells
+    Completed,                 // the last food was eaten
+    Died(GameError),
+}
+
+/// How a headless game went, see [`GameState::run_script`].
+#[derive(Debug)]
+pub struct SimulationReport {
+    pub length: usize,
+    pub score: u32,
+    pub ticks: u32, // ticks survived
+    pub death: Option<GameError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum GridError {
+    #[error("A grid of dimensions {dimensions:?} needs {expected} cells but got {got}")]
+    DimensionMismatch {
+        dimensions: Vec3,
+        expected: usize,
+        got: usize,
+    },
+    #[error("Coordinates {coord:?} are outside the grid of dimensions {dimensions:?}")]
+    OutOfBounds { coord: Vec3, dimensions: Vec3 },
+    #[error("A grid can't have the dimensions {0:?}")]
+    InvalidDimensions(Vec3),
+    #[error("Grids of dimensions {0:?} and {1:?} can't be compared")]
+    DifferentDimensions(Vec3, Vec3),
+    #[error("Can't read a grid: {0}")]
+    Parse(String),
+    #[error("The snake can't reach the food at {0:?}")]
+    UnreachableFood(Vec<Vec3>),
+}
+
+/// Why a snake couldn't be put on the grid at the start of a game.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SpawnError {
+    #[error("The snake would start outside the level at {0:?}")]
+    OutOfBounds(Vec3),
+    #[error("The snake would start inside something at {0:?}")]
+    Blocked(Vec3),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[allow(clippy::enum_variant_names)] // passque y'aura maybe d'autres erreurs que juste le serpent
+pub enum GameError {
+    #[error("Snake collision when attempting to move head from {head:?} to {attempted_move:?}")]
+    SnakeCollision { head: Vec3, attempted_move: Vec3 },
+    #[error("Snake at {head:?} tried to eat itself at {attempted_move:?}")]
     SnakeCannibalism { head: Vec3, attempted_move: Vec3 },
     #[error("Snake fell at {attempted_move:?} from {head:?}")]
     SnakeFell { head: Vec3, attempted_move: Vec3 },
+    #[error("Snake at {head:?} ran into the other snake")]
+    HitOtherSnake { head: Vec3 },
+    #[error("Snake impaled itself on a spike at {attempted_move:?} from {head:?}")]
+    Impaled { head: Vec3, attempted_move: Vec3 },
+    #[error("Snake ate poison at {attempted_move:?} from {head:?}")]
+    Poisoned { head: Vec3, attempted_move: Vec3 },
+    #[error("Snake at {head:?} got eaten")]
+    SnakeEaten { head: Vec3 },
+    #[error("Snake got crushed by a crate at {at:?}")]
+    Crushed { at: Vec3 },
+    #[error("Time's up")]
+    TimeUp,
+    #[error(transparent)]
+    Grid(#[from] GridError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // layers from the bottom up, see the FromStr of Grid, with some food somewhere
+    // since a level without any is already complete
+    fn game(level: &str, start: Vec3) -> GameState {
+        game_with(level, start, &GameConfig::default())
+    }
+
+    fn game_with(level: &str, start: Vec3, config: &GameConfig) -> GameState {
+        GameState::new(start, level.parse().unwrap(), config).unwrap()
+    }
+
+    #[test]
+    fn walking_into_a_spike_kills() {
+        let mut game = game("WWWW\n\n.^.F", (0, 0, 1));
+        let report = game.run_script([Direction::East]);
+        assert_eq!(
+            report.death,
+            Some(GameError::Impaled {
+                head: (0, 0, 1),
+                attempted_move: (1, 0, 1),
+            })
+        );
+    }
+
+    #[test]
+    fn a_spike_is_nothing_to_stand_on() {
+        assert!(!Cell::Spike.supports());
+        let grid: Grid = "WW^\n\n...".parse().unwrap();
+        assert_eq!(legal_moves(&grid, (1, 0, 1)), vec![(0, 0, 1)]);
+
+        let mut game = game("WW^W\n\n...F", (0, 0, 1));
+        let report = game.run_script([Direction::East, Direction::East]);
+        assert_eq!(report.ticks, 1);
+        assert_eq!(
+            report.death,
+            Some(GameError::Impaled {
+                head: (1, 0, 1),
+                attempted_move: (2, 0, 1),
+            })
+        );
+    }
+
+    #[test]
+    fn pathfinding_goes_around_an_l_shaped_wall() {
+        let floor = "WWWWW\nWWWWW\nWWWWW\nWWWWW";
+        let grid: Grid = format!("{floor}\n\nF....\nWWW..\n..W..\n.....")
+            .parse()
+            .unwrap();
+        let path = pathfind(&grid, (0, 2, 1), (0, 0, 1)).unwrap();
+        assert_eq!(path.len(), 11);
+        assert_eq!((path[0], path[10]), ((0, 2, 1), (0, 0, 1)));
+        for pair in path.windows(2) {
+            assert!(legal_moves(&grid, pair[0]).contains(&pair[1]));
+        }
+    }
+
+    #[test]
+    fn no_path_to_walled_off_food() {
+        let floor = "WWWWW\nWWWWW\nWWWWW";
+        let grid: Grid = format!("{floor}\n\nF....\nWWWWW\n.....").parse().unwrap();
+        assert_eq!(pathfind(&grid, (0, 2, 1), (0, 0, 1)), None);
+    }
+
+    #[test]
+    fn newer_cells_are_handled_when_landing() {
+        let grid: Grid = "WWWWW\n\n.P.%F".parse().unwrap();
+        assert_eq!(
+            resolve_landing(&grid, (0, 0, 1), (1, 0, 1)),
+            Err(GameError::Poisoned {
+                head: (0, 0, 1),
+                attempted_move: (1, 0, 1),
+            })
+        );
+        assert_eq!(
+            resolve_landing(&grid, (2, 0, 1), (3, 0, 1)),
+            Err(GameError::SnakeCollision {
+                head: (2, 0, 1),
+                attempted_move: (3, 0, 1),
+            })
+        );
+    }
+
+    // a 5x5 floor with a food in a corner
+    const ARENA: &str = "WWWWW\nWWWWW\nWWWWW\nWWWWW\nWWWWW\n\n....F\n.....\n.....\n.....\n.....";
+
+    #[test]
+    fn two_turns_in_one_tick_are_both_applied() {
+        let mut game = game(ARENA, (2, 2, 1));
+        game.queue_input(Direction::East);
+        game.queue_input(Direction::South);
+        game.update().unwrap();
+        assert_eq!(game.head(), (3, 2, 1));
+        game.update().unwrap();
+        assert_eq!(game.head(), (3, 3, 1));
+        game.update().unwrap();
+        assert_eq!(game.head(), (3, 4, 1));
+    }
+
+    // a ledge two cells long, then a drop of one level
+    const LEDGE: &str = "WWWWWW\n\nWW...F\n\n......\n\n......";
+
+    #[test]
+    fn jumping_in_coyote_time() {
+        let mut game = game(LEDGE, (0, 0, 2));
+        let [east, none, up] = [Direction::East, Direction::None, Direction::Up];
+        // le dernier tick de grâce, deux ticks après avoir quitté le bord
+        let report = game.run_script([east, none, none, up]);
+        assert_eq!(report.death, None);
+        assert_eq!(game.head(), (3, 0, 3));
+    }
+
+    #[test]
+    fn jumping_after_coyote_time_falls() {
+        let mut game = game(LEDGE, (0, 0, 2));
+        let [east, none, up] = [Direction::East, Direction::None, Direction::Up];
+        let report = game.run_script([east, none, none, none, up]);
+        assert_eq!(report.death, None);
+        assert_eq!(game.head(), (4, 0, 1));
+    }
+
+    #[test]
+    fn sliding_across_ice_ignores_turns_until_off_it() {
+        let level = "WWWWWWW\nWWWWWWW\n\n.......\n.~~~..F";
+        let mut game = game(level, (0, 1, 1));
+        let [east, north, none] = [Direction::East, Direction::North, Direction::None];
+        let report = game.run_script([east, north, none, none]);
+        assert_eq!(report.death, None);
+        assert_eq!(game.head(), (4, 1, 1));
+        // the turn was kept for when there is grip again
+        game.update().unwrap();
+        assert_eq!(game.head(), (4, 0, 1));
+    }
+
+    #[test]
+    fn sliding_into_a_wall() {
+        let level = "WWWWW\nWWWWW\n\n.~~WF\n.....";
+        let mut game = game(level, (0, 0, 1));
+        let [east, south] = [Direction::East, Direction::South];
+        let report = game.run_script([east, south, south]);
+        assert_eq!(
+            report.death,
+            Some(GameError::SnakeCollision {
+                head: (2, 0, 1),
+                attempted_move: (3, 0, 1),
+            })
+        );
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum Call {
+        Move(Vec3),
+        Eat(Vec3),
+        Death(GameError),
+    }
+
+    // garde les appels à part, le jeu gardant l'observateur pour lui
+    #[derive(Debug, Default)]
+    struct Recorder(std::rc::Rc<std::cell::RefCell<Vec<Call>>>);
+
+    impl GameObserver for Recorder {
+        fn on_move(&mut self, head: Vec3) {
+            self.0.borrow_mut().push(Call::Move(head));
+        }
+        fn on_eat(&mut self, food: Vec3) {
+            self.0.borrow_mut().push(Call::Eat(food));
+        }
+        fn on_death(&mut self, reason: &GameError) {
+            self.0.borrow_mut().push(Call::Death(reason.clone()));
+        }
+    }
+
+    #[test]
+    fn the_observer_hears_about_a_short_run() {
+        let mut game = game("WWWWW\n\n..FWF", (0, 0, 1));
+        let recorder = Recorder::default();
+        let calls = recorder.0.clone();
+        game.set_observer(recorder);
+        game.run_script([Direction::East; 4]);
+        assert_eq!(
+            *calls.borrow(),
+            [
+                Call::Move((1, 0, 1)),
+                Call::Eat((2, 0, 1)),
+                Call::Move((2, 0, 1)),
+                Call::Death(GameError::SnakeCollision {
+                    head: (2, 0, 1),
+                    attempted_move: (3, 0, 1),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_event_log_of_an_eat_and_a_death() {
+        let mut game = game("WWWWW\n\n..FWF", (0, 0, 1));
+        game.enable_event_log();
+        game.run_script([Direction::East; 4]);
+        let death = GameError::SnakeCollision {
+            head: (2, 0, 1),
+            attempted_move: (3, 0, 1),
+        };
+        assert_eq!(
+            game.events(),
+            [
+                (0, GameEvent::Moved { head: (1, 0, 1) }),
+                (1, GameEvent::Ate { coord: (2, 0, 1) }),
+                (1, GameEvent::Moved { head: (2, 0, 1) }),
+                (2, GameEvent::Died(death)),
+            ]
+        );
+    }
+
+    #[test]
+    fn undoing_a_meal_puts_the_food_back() {
+        let config = GameConfig {
+            practice: true,
+            ..GameConfig::default()
+        };
+        let mut game = game_with("WWWWW\n\n..F.F", (0, 0, 1), &config);
+        game.run_script([Direction::East; 2]);
+        assert_eq!(
+            game.snake_body().collect::<Vec<_>>(),
+            [(2, 0, 1), (1, 0, 1)]
+        );
+        assert_eq!(game.grid().get((2, 0, 1)), Some(Cell::Empty));
+
+        assert!(game.undo());
+        assert_eq!(game.snake_body().collect::<Vec<_>>(), [(1, 0, 1)]);
+        assert_eq!(game.grid().get((2, 0, 1)), Some(Cell::Food));
+        assert_eq!((game.score(), game.foods_eaten(), game.tick()), (0, 0, 1));
+
+        // et on remange pareil
+        game.update().unwrap();
+        assert_eq!(game.snake_body().count(), 2);
+        assert_eq!(game.score(), 1);
+    }
+
+    #[test]
+    fn the_clock_goes_by_the_pace_the_ticks_came_at() {
+        let config = GameConfig {
+            speedup_percent: 100,
+            max_speedup_percent: 100,
+            practice: true,
+            ..GameConfig::default()
+        };
+        let mut game = game_with("WWWWW\n\n.F..F", (0, 0, 1), &config);
+        game.run_script([Direction::East]);
+        assert_eq!(game.time_played(), Duration::from_millis(50));
+        // deux fois plus vite une fois la nourriture mangée
+        assert_eq!(game.tick_length(), Duration::from_millis(25));
+        game.update().unwrap();
+        assert_eq!(game.time_played(), Duration::from_millis(75));
+
+        assert!(game.undo());
+        assert_eq!(game.time_played(), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn what_is_below() {
+        let game = game("WV\n\n.F\n\n..", (0, 0, 1));
+        assert_eq!(game.cell_below((0, 0, 1)), Some(Cell::Block));
+        assert_eq!(game.cell_below((0, 0, 2)), Some(Cell::Empty));
+        assert_eq!(game.cell_below((1, 0, 2)), Some(Cell::Food));
+        assert_eq!(game.cell_below((1, 0, 1)), None); // void
+        assert_eq!(game.cell_below((0, 0, 0)), None); // bottom of the map
+    }
+
+    #[test]
+    fn a_custom_config_sets_up_the_game() {
+        let config = GameConfig {
+            starting_length: 3,
+            starting_facing: Direction::East,
+            time_limit: Some(50),
+            two_players: true,
+            ..GameConfig::default()
+        };
+        let game = game_with(ARENA, (2, 1, 1), &config);
+        assert_eq!(game.player_count(), 2);
+        assert_eq!(
+            game.snake_body().collect::<Vec<_>>(),
+            [(2, 1, 1), (1, 1, 1), (0, 1, 1)]
+        );
+        // l'autre en face, qui regarde de l'autre côté
+        assert_eq!(
+            game.snake_body_of(1).collect::<Vec<_>>(),
+            [(2, 3, 1), (3, 3, 1), (4, 3, 1)]
+        );
+        assert_eq!(game.remaining_ticks(), Some(50));
+        assert_eq!(game.ticks_until_shrink(), None);
+        assert_eq!((game.tick(), game.score()), (0, 0));
+    }
+
+    #[test]
+    fn a_longer_snake_needs_room_behind_its_head() {
+        let grid: Grid = "WWWWW\n\nW...F".parse().unwrap();
+        let config = |length| GameConfig {
+            starting_length: length,
+            starting_facing: Direction::East,
+            ..GameConfig::default()
+        };
+        let game = GameState::new((3, 0, 1), grid.clone(), &config(3)).unwrap();
+        assert_eq!(
+            game.snake_body().collect::<Vec<_>>(),
+            [(3, 0, 1), (2, 0, 1), (1, 0, 1)]
+        );
+        assert_eq!(
+            GameState::new((2, 0, 1), grid.clone(), &config(3)).unwrap_err(),
+            SpawnError::Blocked((0, 0, 1))
+        );
+        assert_eq!(
+            GameState::new((3, 0, 1), grid, &config(5)).unwrap_err(),
+            SpawnError::Blocked((0, 0, 1))
+        );
+    }
+
+    #[test]
+    fn neighbors_at_the_corners_skip_what_is_off_the_grid() {
+        let grid: Grid = "WWW\nWWW\nWWV\n\nF..\n...\n..F".parse().unwrap();
+        let around = |coord| {
+            let mut found: Vec<_> = grid.neighbors(coord).collect();
+            found.sort_by_key(|&(_, next, _)| next);
+            found
+        };
+        assert_eq!(
+            around((0, 0, 0)),
+            [
+                (Direction::Up, (0, 0, 1), Some(Cell::Food)),
+                (Direction::South, (0, 1, 0), Some(Cell::Block)),
+                (Direction::East, (1, 0, 0), Some(Cell::Block)),
+            ]
+        );
+        assert_eq!(
+            around((2, 2, 1)),
+            [
+                (Direction::West, (1, 2, 1), Some(Cell::Empty)),
+                (Direction::North, (2, 1, 1), Some(Cell::Empty)),
+                (Direction::Down, (2, 2, 0), None), // the void
+            ]
+        );
+        assert_eq!(grid.find_all(Cell::Food), [(0, 0, 1), (2, 2, 1)]);
+        assert_eq!(grid.find_all(Cell::Void), [(2, 2, 0)]);
+        assert_eq!(grid.find_all(Cell::Ice), []);
+    }
+
+    #[test]
+    fn iter_visits_every_position_once() {
+        for dimensions in [(1, 1, 1), (3, 1, 2), (2, 5, 3), (7, 4, 6)] {
+            let (mx, my, mz) = dimensions;
+            for grid in [Grid::empty(dimensions), Grid::sparse(dimensions)] {
+                let coords: Vec<Vec3> = grid.iter().map(|(coord, _)| coord).collect();
+                assert_eq!(coords.len(), (mx * my * mz) as usize);
+                assert_eq!(coords.iter().collect::<HashSet<_>>().len(), coords.len());
+                for (idx, &coord) in coords.iter().enumerate() {
+                    assert!(grid.contains(coord));
+                    assert_eq!(grid.coord_to_index(coord), idx);
+                    assert_eq!(grid.index_to_coord(grid.coord_to_index(coord)), coord);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "(3, 0, 0) is outside the grid of dimensions (3, 2, 1)")]
+    fn indexing_outside_says_where() {
+        let grid = Grid::empty((3, 2, 1));
+        let _ = grid[(3, 0, 0)];
+    }
+
+    #[test]
+    #[should_panic(expected = "(0, -1, 0) is outside the grid of dimensions (3, 2, 1)")]
+    fn writing_outside_says_where() {
+        let mut grid = Grid::empty((3, 2, 1));
+        grid[(0, -1, 0)] = Cell::Block;
+    }
+
+    #[test]
+    fn unchecked_gets_the_raw_cell() {
+        let mut grid: Grid = "W.V".parse().unwrap();
+        grid[(1, 0, 0)] = Cell::Food;
+        assert_eq!(grid.get_unchecked((0, 0, 0)), Cell::Block);
+        assert_eq!(grid.get_unchecked((1, 0, 0)), Cell::Food);
+        // the void is there, where get says there's nothing
+        assert_eq!(grid.get_unchecked((2, 0, 0)), Cell::Void);
+        assert_eq!(grid.get((2, 0, 0)), None);
+        let sparse = grid.into_sparse();
+        assert_eq!(sparse.get_unchecked((1, 0, 0)), Cell::Food);
+        assert_eq!(sparse.get_unchecked((2, 0, 0)), Cell::Void);
+    }
+
+    #[test]
+    fn a_grid_needs_one_cell_per_position() {
+        let cells = |n| vec![Cell::Empty; n];
+        assert!(Grid::new((2, 3, 4), cells(24)).is_ok());
+        for got in [23, 25] {
+            assert_eq!(
+                Grid::new((2, 3, 4), cells(got)).unwrap_err(),
+                GridError::DimensionMismatch {
+                    dimensions: (2, 3, 4),
+                    expected: 24,
+                    got,
+                }
+            );
+        }
+        // (-2)*(-3)*4 is 24 too, but a negative size is no size
+        assert_eq!(
+            Grid::new((-2, -3, 4), cells(24)).unwrap_err(),
+            GridError::DimensionMismatch {
+                dimensions: (-2, -3, 4),
+                expected: 0,
+                got: 24,
+            }
+        );
+        assert!(matches!(
+            Grid::new((0, 3, 4), cells(0)),
+            Err(GridError::DimensionMismatch { expected: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn a_key_opens_its_doors_only() {
+        let mut grid: Grid = "WWWWW\nWWWWW\n\n.k#.F\n..#..".parse().unwrap();
+        grid[(2, 1, 1)] = Cell::Door(1);
+        let mut game = GameState::new((0, 0, 1), grid, &GameConfig::default()).unwrap();
+        game.enable_event_log();
+        game.run_script([Direction::East]);
+        assert!(game.has_key(0) && !game.has_key(1));
+        assert_eq!(game.grid().get((1, 0, 1)), Some(Cell::Empty));
+        assert_eq!(game.grid().get((2, 0, 1)), Some(Cell::Empty));
+        assert_eq!(game.grid().get((2, 1, 1)), Some(Cell::Door(1)));
+        assert!(game.events().contains(&(0, GameEvent::Unlocked { id: 0 })));
+        // through the open door
+        let report = game.run_script([Direction::East; 2]);
+        assert_eq!(report.death, None);
+        assert_eq!(game.head(), (3, 0, 1));
+    }
+
+    #[test]
+    fn keys_and_doors_share_a_color() {
+        assert_eq!(Cell::Key(2).to_char(), 'k');
+        assert_eq!(Cell::Door(2).to_char(), '#');
+        assert_eq!(Cell::Key(2).color(), Cell::Door(2).color());
+        assert_ne!(Cell::Door(1).color(), Cell::Door(2).color());
+        assert!(Cell::Door(0).is_solid() && !Cell::Key(0).is_solid());
+    }
+
+    #[test]
+    fn failures_can_be_told_apart() {
+        let mut grid = Grid::empty((2, 2, 2));
+        assert_eq!(
+            grid.set((2, 0, 0), Cell::Block),
+            Err(GridError::OutOfBounds {
+                coord: (2, 0, 0),
+                dimensions: (2, 2, 2),
+            })
+        );
+        let error: GameError = grid.crop((0, 0, 0), (0, 0, 0)).unwrap_err().into();
+        assert!(matches!(
+            error,
+            GameError::Grid(GridError::OutOfBounds { .. })
+        ));
+
+        let mut game = game("WV\n\n.F", (0, 0, 1));
+        assert!(matches!(
+            game.run_script([Direction::East]).death,
+            Some(GameError::SnakeFell {
+                attempted_move: (1, 0, 1),
+                ..
+            })
+        ));
+
+        let config = GameConfig {
+            starting_length: 5,
+            starting_facing: Direction::East,
+            ..GameConfig::default()
+        };
+        let mut game = game_with(ARENA, (4, 2, 1), &config);
+        let [north, west, south] = [Direction::North, Direction::West, Direction::South];
+        assert_eq!(
+            game.run_script([north, west, south]).death,
+            Some(GameError::SnakeCannibalism {
+                head: (3, 2, 1),
+                attempted_move: (3, 2, 1),
+            })
+        );
+    }
+
+    #[test]
+    fn time_runs_out() {
+        let config = GameConfig {
+            time_limit: Some(3),
+            ..GameConfig::default()
+        };
+        let mut game = game_with(ARENA, (0, 0, 1), &config);
+        for left in (1..=3).rev() {
+            assert_eq!(game.remaining_ticks(), Some(left));
+            game.update().unwrap();
+        }
+        assert_eq!(game.remaining_ticks(), Some(0));
+        assert_eq!(game.update(), Err(GameError::TimeUp));
+        assert_eq!(game.remaining_ticks(), Some(0));
+    }
+
+    #[test]
+    fn sparse_grids_behave_like_dense_ones() {
+        let dense: Grid = "VVVV\nVWWV\nVVVV\n\nV.FV\nVVV^\n~VVV".parse().unwrap();
+        let mut sparse = dense.clone().into_sparse();
+        let mut dense = dense;
+        let same = |dense: &Grid, sparse: &Grid| {
+            assert_eq!(dense.dimensions(), sparse.dimensions());
+            assert!(dense.iter().eq(sparse.iter()));
+            assert!(dense.iter_occupied().eq(sparse.iter_occupied()));
+            assert_eq!(dense.diff(sparse).unwrap(), []);
+            for (coord, _) in dense.iter() {
+                assert_eq!(dense.get(coord), sparse.get(coord));
+                assert!(dense.neighbors(coord).eq(sparse.neighbors(coord)));
+            }
+        };
+        same(&dense, &sparse);
+
+        for grid in [&mut dense, &mut sparse] {
+            grid.set((0, 0, 0), Cell::Food).unwrap();
+            grid.set((1, 1, 0), Cell::Void).unwrap();
+            grid[(3, 2, 1)] = Cell::Block;
+            grid.apply(|_, cell| {
+                if cell == Cell::Spike {
+                    Cell::Void
+                } else {
+                    cell
+                }
+            });
+        }
+        same(&dense, &sparse);
+
+        for grid in [&mut dense, &mut sparse] {
+            grid.resize((5, 2, 3), Cell::Void).unwrap();
+        }
+        same(&dense, &sparse);
+        same(
+            &dense.crop((1, 0, 1), (4, 2, 2)).unwrap(),
+            &sparse.crop((1, 0, 1), (4, 2, 2)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn stairs_go_up_and_down() {
+        let mut up = game("WWWW\n\n.<.F\n\n....", (0, 0, 1));
+        up.queue_input(Direction::East);
+        up.update().unwrap();
+        assert_eq!(up.head(), (1, 0, 2));
+
+        let mut down = game("WWWW\n\nW.WW\n\n.>.F", (0, 0, 2));
+        down.queue_input(Direction::East);
+        down.update().unwrap();
+        assert_eq!(down.head(), (1, 0, 1));
+    }
+
+    #[test]
+    fn blocked_stairs_are_a_wall() {
+        let mut game = game("WWWW\n\n.<.F\n\n.W..", (0, 0, 1));
+        game.queue_input(Direction::East);
+        assert_eq!(
+            game.update(),
+            Err(GameError::SnakeCollision {
+                head: (0, 0, 1),
+                attempted_move: (1, 0, 1),
+            })
+        );
+    }
+
+    #[test]
+    fn the_occupancy_follows_the_body() {
+        let config = GameConfig {
+            endless: true,
+            ..GameConfig::default()
+        };
+        let mut game = game_with(ARENA, (0, 0, 1), &config);
+        game.enable_undo();
+        let mut rng = Rng::new(7);
+        let moves = [
+            Direction::North,
+            Direction::South,
+            Direction::West,
+            Direction::East,
+        ];
+        for tick in 0..2000 {
+            game.queue_input(moves[rng.below(moves.len())]);
+            // une mort ou de temps en temps, on revient en arrière pour continuer
+            if game.update().is_err() || tick % 7 == 0 {
+                assert!(game.undo());
+            }
+            let snake = &game.players[0].snake;
+            let mut counted = HashMap::new();
+            for &segment in &snake.body {
+                *counted.entry(segment).or_insert(0) += 1;
+            }
+            assert_eq!(snake.occupancy, counted, "at tick {tick}");
+        }
+        assert!(game.snake_body().count() > 3);
+    }
+
+    #[test]
+    fn neighbors_6_in_the_middle_and_in_a_corner() {
+        let mut grid = Grid::empty((3, 3, 3));
+        assert_eq!(grid.neighbors_6((1, 1, 1)).count(), 6);
+        assert_eq!(grid.neighbors_6((0, 0, 0)).count(), 3);
+        assert_eq!(grid.neighbors_6((2, 2, 2)).count(), 3);
+        assert_eq!(grid.neighbors_6((1, 0, 0)).count(), 4);
+        // le vide n'est pas un voisin
+        grid.set((1, 1, 2), Cell::Void).unwrap();
+        assert_eq!(grid.neighbors_6((1, 1, 1)).count(), 5);
+    }
+
+    #[test]
+    fn resizing_keeps_the_cells_where_they_were() {
+        let mut grid = Grid::empty((4, 5, 2));
+        grid.set((2, 3, 1), Cell::Food).unwrap();
+        grid.set_meta(
+            (2, 3, 1),
+            CellMeta {
+                value: Some(3),
+                ..CellMeta::default()
+            },
+        )
+        .unwrap();
+        grid.resize((6, 7, 3), Cell::Block).unwrap();
+        assert_eq!(grid.dimensions(), (6, 7, 3));
+        assert_eq!(grid.get((2, 3, 1)), Some(Cell::Food));
+        assert_eq!(grid.meta((2, 3, 1)).and_then(|meta| meta.value), Some(3));
+        assert_eq!(grid.get((5, 6, 2)), Some(Cell::Block));
+        assert_eq!(grid.get((3, 4, 1)), Some(Cell::Empty));
+
+        // la tête d'un serpent en (3, 4, 1) ne tient plus dans la grille, à l'appelant de le voir
+        grid.resize((3, 4, 2), Cell::Block).unwrap();
+        assert!(grid.contains((2, 3, 1)));
+        assert!(!grid.contains((3, 4, 1)));
+        assert_eq!(
+            grid.resize((0, 4, 2), Cell::Block),
+            Err(GridError::InvalidDimensions((0, 4, 2)))
+        );
+    }
+
+    #[test]
+    fn food_around_a_wall_is_reachable_until_the_way_is_closed() {
+        let floor = "WWWWW\nWWWWW\nWWWWW";
+        let open: Grid = format!("{floor}\n\n..W.F\n..W..\n....F").parse().unwrap();
+        assert_eq!(open.validate_reachable((0, 0, 1)), Ok(()));
+        assert_eq!(open.reachable((0, 0, 1)).len(), 13);
+
+        let closed: Grid = format!("{floor}\n\n..W.F\n..W..\n..W.F").parse().unwrap();
+        assert_eq!(
+            closed.validate_reachable((0, 0, 1)),
+            Err(GridError::UnreachableFood(vec![(4, 0, 1), (4, 2, 1)]))
+        );
+        // sans escalier pour y monter, la nourriture à l'étage ne se mange pas
+        let up: Grid = format!("{floor}\n\n.....\n.....\n.....\n\n....F\nVVVVV\nVVVVV")
+            .parse()
+            .unwrap();
+        assert!(up.validate_reachable((0, 1, 1)).is_err());
+    }
+
+    #[test]
+    fn the_arena_shrinks_under_a_snake_that_stays_put() {
+        let config = GameConfig {
+            shrink_every: Some(10),
+            ..GameConfig::default()
+        };
+        for (start, ring) in [((0, 0, 1), 0), ((1, 2, 1), 1)] {
+            let mut game = game_with(ARENA, start, &config);
+            let last = (ring + 1) * 10;
+            // le serpent ne bouge pas tant qu'on ne lui dit rien
+            for _ in 0..last {
+                game.update().unwrap();
+            }
+            // l'anneau tombe au début du tick suivant
+            assert_eq!(game.ticks_until_shrink(), Some(0));
+            assert!(game.is_doomed(start));
+            assert_eq!(
+                game.update(),
+                Err(GameError::SnakeFell {
+                    head: start,
+                    attempted_move: start,
+                })
+            );
+            // le tick de la chute compte
+            assert_eq!(game.tick(), last + 1);
+        }
+    }
+
+    // deux serpents de trois, la tête du premier en (2, 1, 1) et celle du second en face en (2, 3, 1)
+    fn duel() -> GameState {
+        let config = GameConfig {
+            starting_length: 3,
+            starting_facing: Direction::East,
+            two_players: true,
+            ..GameConfig::default()
+        };
+        game_with(ARENA, (2, 1, 1), &config)
+    }
+
+    #[test]
+    fn running_into_the_other_snake_loses() {
+        let mut game = duel();
+        game.queue_input_for(0, Direction::East);
+        game.queue_input_for(1, Direction::North);
+        game.update().unwrap();
+        // en plein dans la queue du premier, qui vient de tourner
+        game.queue_input_for(0, Direction::South);
+        game.queue_input_for(1, Direction::North);
+        assert_eq!(
+            game.update(),
+            Err(GameError::HitOtherSnake { head: (2, 1, 1) })
+        );
+        assert_eq!(game.winner(), Some(0));
+    }
+
+    #[test]
+    fn two_snakes_can_brush_past_each_other() {
+        let mut game = duel();
+        let moves = [
+            (Direction::East, Direction::North),
+            (Direction::South, Direction::West),
+            // chacun là où la queue de l'autre était au tick d'avant
+            (Direction::South, Direction::North),
+        ];
+        for (first, second) in moves {
+            game.queue_input_for(0, first);
+            game.queue_input_for(1, second);
+            game.update().unwrap();
+        }
+        assert_eq!((game.head_of(0), game.head_of(1)), ((3, 3, 1), (1, 1, 1)));
+        assert_eq!(game.winner(), None);
+    }
+
+    #[test]
+    fn cell_data_goes_with_the_cell() {
+        let mut grid = Grid::empty((3, 1, 1));
+        let meta = CellMeta {
+            label: Some("bonus".to_string()),
+            value: Some(5),
+        };
+        grid.set((1, 0, 0), Cell::Food).unwrap();
+        grid.set_meta((1, 0, 0), meta.clone()).unwrap();
+        // une autre cellule pleine la garde
+        grid.set((1, 0, 0), Cell::Ice).unwrap();
+        grid[(2, 0, 0)] = Cell::Block;
+        assert_eq!(grid.meta((1, 0, 0)), Some(&meta));
+        // vidée, elle la perd
+        grid.set((1, 0, 0), Cell::Empty).unwrap();
+        assert_eq!(grid.meta((1, 0, 0)), None);
+
+        grid.set_meta((2, 0, 0), meta.clone()).unwrap();
+        grid.set((2, 0, 0), Cell::Void).unwrap();
+        assert_eq!(grid.meta((2, 0, 0)), None);
+        assert_eq!(
+            grid.set_meta((3, 0, 0), meta),
+            Err(GridError::OutOfBounds {
+                coord: (3, 0, 0),
+                dimensions: (3, 1, 1),
+            })
+        );
+    }
+
+    #[test]
+    fn a_one_cell_gap_joins_two_chambers() {
+        let mut grid: Grid = "...W..\n...W..\n...W..".parse().unwrap();
+        let empty = |cell| cell == Cell::Empty;
+        let regions = grid.regions(empty);
+        assert_eq!(regions.len(), 2);
+        assert_eq!((regions[0].len(), regions[1].len()), (9, 6));
+        assert!(regions[0].contains(&(0, 0, 0)) && regions[1].contains(&(5, 2, 0)));
+        assert!(!grid.flood_fill((0, 0, 0), empty).contains(&(4, 0, 0)));
+        assert!(grid.flood_fill((3, 0, 0), empty).is_empty());
+
+        grid.set((3, 1, 0), Cell::Empty).unwrap();
+        let regions = grid.regions(empty);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].len(), 16);
+        assert_eq!(grid.flood_fill((0, 0, 0), empty), regions[0]);
+    }
+
+    // un tapis de `len` cases vers l'est à partir de x = 1, sur un sol de `width` de long
+    fn conveyor_run(width: usize, len: isize, ahead: char) -> GameState {
+        let floor = "W".repeat(width);
+        let row = format!(
+            ".{}{ahead}{}F",
+            ".".repeat(len as usize),
+            ".".repeat(width - 3 - len as usize)
+        );
+        let mut game = game(&format!("{floor}\n\n{row}"), (0, 0, 1));
+        for x in 1..=len {
+            game.grid[(x, 0, 1)] = Cell::Conveyor(Direction::East);
+        }
+        game
+    }
+
+    #[test]
+    fn a_conveyor_carries_the_snake_whatever_it_wants() {
+        let mut game = conveyor_run(8, 3, '.');
+        game.queue_input(Direction::East);
+        game.update().unwrap();
+        // demander autre chose ne sert à rien tant qu'il est sur le tapis
+        game.queue_input(Direction::North);
+        for x in 2..=4 {
+            game.update().unwrap();
+            assert_eq!(game.head(), (x, 0, 1));
+        }
+    }
+
+    #[test]
+    fn a_conveyor_can_push_into_a_wall() {
+        let mut game = conveyor_run(5, 1, 'W');
+        game.queue_input(Direction::East);
+        game.update().unwrap();
+        assert_eq!(
+            game.update(),
+            Err(GameError::SnakeCollision {
+                head: (1, 0, 1),
+                attempted_move: (2, 0, 1),
+            })
+        );
+    }
+
+    #[test]
+    fn directions_come_back_the_way_they_went() {
+        for dir in Direction::ALL {
+            assert_eq!(dir.opposite().opposite(), dir);
+            assert_ne!(dir.opposite(), dir);
+            assert_eq!(dir.delta() + dir.opposite().delta(), Pos3::ZERO);
+            assert_eq!(Direction::try_from(Vec3::from(dir.delta())), Ok(dir));
+        }
+        assert_eq!(Direction::None.opposite(), Direction::None);
+        // pas un pas d'une case
+        assert_eq!(Direction::try_from((1, 1, 0)), Err((1, 1, 0)));
+        assert_eq!(Direction::try_from((0, 0, 0)), Err((0, 0, 0)));
+    }
+
+    fn through_portal(exit_facing: Direction) -> GameState {
+        let mut game = game(ARENA, (0, 0, 1));
+        game.grid[(1, 0, 1)] = Cell::Portal(0, Direction::None);
+        game.grid[(3, 2, 1)] = Cell::Portal(0, exit_facing);
+        game.queue_input(Direction::East);
+        game.update().unwrap();
+        game
+    }
+
+    #[test]
+    fn a_portal_keeps_the_way_the_snake_was_going() {
+        let mut game = through_portal(Direction::None);
+        assert_eq!(game.head(), (4, 2, 1));
+        game.update().unwrap_err(); // toujours vers l'est, dans le bord
+    }
+
+    #[test]
+    fn a_portal_facing_somewhere_turns_the_snake() {
+        let mut game = through_portal(Direction::North);
+        assert_eq!(game.head(), (3, 1, 1));
+        game.update().unwrap();
+        assert_eq!(game.head(), (3, 0, 1));
+    }
+
+    #[test]
+    fn two_foods_in_a_row_grow_the_snake_by_two() {
+        let mut game = game("WWWWWWW\n\n.FF...F", (0, 0, 1));
+        game.queue_input(Direction::East);
+        for _ in 0..4 {
+            game.update().unwrap();
+        }
+        assert_eq!(game.foods_eaten(), 2);
+        assert_eq!(
+            game.snake_body().collect::<Vec<_>>(),
+            [(4, 0, 1), (3, 0, 1), (2, 0, 1)]
+        );
+        // plus rien à manger, il ne grandit plus
+        game.update().unwrap();
+        assert_eq!(game.snake_body().count(), 3);
+    }
+
+    #[test]
+    fn the_head_is_where_the_moves_took_it() {
+        let mut game = game(ARENA, (0, 0, 1));
+        assert_eq!(game.head(), (0, 0, 1));
+        game.run_script([Direction::East, Direction::East, Direction::South]);
+        assert_eq!(game.head(), (2, 1, 1));
+        // ce que l'overlay des coordonnées affiche à côté
+        assert_eq!(game.grid().get(game.head()), Some(Cell::Empty));
+        assert_eq!(game.grid().cell_below(game.head()), Some(Cell::Block));
+    }
+
+    #[test]
+    fn each_head_follows_its_own_snake() {
+        let mut game = duel();
+        let moves = [
+            (Direction::East, Direction::West),
+            (Direction::South, Direction::North),
+            (Direction::East, Direction::West),
+        ];
+        let mut heads = Vec::new();
+        for (first, second) in moves {
+            game.queue_input_for(0, first);
+            game.queue_input_for(1, second);
+            game.update().unwrap();
+            assert_eq!(game.head(), game.head_of(0));
+            assert_eq!(Some(game.head_of(1)), game.snake_body_of(1).next());
+            heads.push((game.head_of(0), game.head_of(1)));
+        }
+        assert_eq!(
+            heads,
+            [
+                ((3, 1, 1), (1, 3, 1)),
+                ((3, 2, 1), (1, 2, 1)),
+                ((4, 2, 1), (0, 2, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_body_goes_from_the_head_to_the_tail() {
+        let config = GameConfig {
+            starting_length: 2,
+            starting_facing: Direction::East,
+            ..GameConfig::default()
+        };
+        let mut game = game_with("WWW\nWWW\nWWW\n\n...\n.F.\n..F", (1, 0, 1), &config);
+        assert_eq!(
+            game.snake_body().collect::<Vec<_>>(),
+            [(1, 0, 1), (0, 0, 1)]
+        );
+        game.run_script([Direction::South, Direction::East, Direction::South]);
+        assert_eq!(game.foods_eaten(), 2);
+        let body: Vec<Vec3> = game.snake_body().collect();
+        assert_eq!(body, [(2, 2, 1), (2, 1, 1), (1, 1, 1), (1, 0, 1)]);
+        assert_eq!(game.snake_body().count(), 2 + game.foods_eaten() as usize);
+    }
+
+    // le serpent en (0, 0, top) pousse vers l'est la caisse posée juste devant lui
+    fn crate_ahead(level: &str, top: isize) -> GameState {
+        let mut game = game(level, (0, 0, top));
+        game.grid[(1, 0, top)] = Cell::Crate;
+        game.queue_input(Direction::East);
+        game
+    }
+
+    #[test]
+    fn a_crate_against_a_wall_does_not_move() {
+        let mut game = crate_ahead("WWWWW\n\n..W.F", 1);
+        assert_eq!(
+            game.update(),
+            Err(GameError::SnakeCollision {
+                head: (0, 0, 1),
+                attempted_move: (1, 0, 1),
+            })
+        );
+        assert_eq!(game.grid.get((1, 0, 1)), Some(Cell::Crate));
+    }
+
+    #[test]
+    fn a_crate_pushed_off_a_ledge_falls() {
+        let mut game = crate_ahead("WWWWW\n\nWW..F\n\n.....", 2);
+        game.update().unwrap();
+        assert_eq!(game.head(), (1, 0, 2));
+        assert_eq!(game.grid.get((2, 0, 2)), Some(Cell::Empty));
+        assert_eq!(game.grid.get((2, 0, 1)), Some(Cell::Crate));
+        // dans le vide, on ne la revoit plus
+        let mut game = crate_ahead("WWVWW\n\n....F", 1);
+        game.update().unwrap();
+        assert_eq!(game.grid.find_all(Cell::Crate), []);
+    }
+
+    #[test]
+    fn wrapping_on_both_axes_comes_back_in_from_the_other_corner() {
+        let config = GameConfig {
+            wrap: Wrap { x: true, y: true },
+            ..GameConfig::default()
+        };
+        let mut game = game_with("WWW\nWWW\nWWW\n\n...\n...\n..F", (0, 0, 1), &config);
+        game.step(Direction::West).unwrap();
+        assert_eq!(game.head(), (2, 0, 1));
+        game.step(Direction::North).unwrap();
+        assert_eq!(game.head(), (2, 2, 1));
+        assert_eq!(game.foods_eaten(), 1);
+    }
+
+    #[test]
+    fn wrapping_into_a_block_is_a_collision() {
+        let config = GameConfig {
+            wrap: Wrap { x: true, y: false },
+            ..GameConfig::default()
+        };
+        let mut game = game_with("WWW\nWWW\n\n..W\n..F", (0, 0, 1), &config);
+        assert_eq!(
+            game.step(Direction::West).err(),
+            Some(GameError::SnakeCollision {
+                head: (0, 0, 1),
+                attempted_move: (2, 0, 1),
+            })
+        );
+        // sans wrap sur y, le bord du haut reste un bord
+        let mut game = game_with("WWW\nWWW\n\n..W\n..F", (0, 0, 1), &config);
+        assert!(game.step(Direction::North).is_err());
+    }
+
+    #[test]
+    fn count_finds_the_food_and_the_blocks_dense_or_sparse() {
+        let grid: Grid = "WWW\nWWW\n\nF.W\n.FV".parse().unwrap();
+        assert_eq!(grid.count(Cell::Food), 2);
+        assert_eq!(grid.count(Cell::Block), 7);
+        assert_eq!(grid.count(Cell::Empty), 2);
+        assert_eq!(grid.count(Cell::Void), 1);
+        let sparse = grid.into_sparse();
+        assert_eq!(sparse.count(Cell::Food), 2);
+        assert_eq!(sparse.count(Cell::Block), 7);
+        assert_eq!(sparse.count(Cell::Void), 1);
+    }
+
+    #[test]
+    fn a_grid_survives_a_json_round_trip() {
+        let mut grid: Grid = "WW^\nWWW\n\nF.k\n~#V".parse().unwrap();
+        grid.set_wrap(Wrap { x: true, y: false });
+        let meta = CellMeta {
+            label: Some("porte".to_string()),
+            value: Some(3),
+        };
+        grid.set_meta((1, 1, 1), meta.clone()).unwrap();
+        let json = grid.to_json();
+        let read = Grid::from_json(&json).unwrap();
+        assert_eq!(read.dimensions, grid.dimensions);
+        assert!(read.iter().eq(grid.iter()));
+        assert_eq!(read.meta((1, 1, 1)), Some(&meta));
+        assert_eq!(read.wrap(), grid.wrap());
+        assert_eq!(read.to_json(), json);
+        // une case de trop ne passe pas les vérifications de Grid::new
+        let broken = json.replacen("\"cells\":[", "\"cells\":[\"Empty\",", 1);
+        assert!(Grid::from_json(&broken).is_err());
+    }
+
+    #[test]
+    fn the_predicted_head_is_where_the_next_tick_goes() {
+        let mut game = game("WWW\nWWW\nWWW\n\n...\n...\n..F", (0, 0, 1));
+        game.queue_input(Direction::South);
+        assert_eq!(game.predicted_head_of(0), Ok((0, 1, 1)));
+        game.update().unwrap();
+        assert_eq!(game.head(), (0, 1, 1));
+        // sans rien dans la file, il continue tout droit
+        assert_eq!(game.predicted_head_of(0), Ok((0, 2, 1)));
+    }
+
+    #[test]
+    fn a_blocked_move_predicts_what_it_bumps_into() {
+        let mut game = game("WWW\n\n.WF", (0, 0, 1));
+        game.queue_input(Direction::East);
+        assert_eq!(game.predicted_head_of(0), Err((1, 0, 1)));
+        assert!(game.update().is_err());
+    }
+
+    #[test]
+    fn crumbling_floor_holds_once_then_drops_the_snake_coming_back() {
+        // sans temps de grâce, sinon il repasse le trou sur sa lancée
+        let config = GameConfig {
+            coyote_ticks: 0,
+            ..GameConfig::default()
+        };
+        let mut game = game_with("W%WWW\n\n....F", (0, 0, 1), &config);
+        game.step(Direction::East).unwrap();
+        assert_eq!(game.head(), (1, 0, 1));
+        assert_eq!(game.grid[(1, 0, 0)], Cell::Crumble);
+        game.step(Direction::East).unwrap();
+        assert_eq!(game.grid[(1, 0, 0)], Cell::Empty);
+        game.step(Direction::West).unwrap();
+        let fell = (0..5).find_map(|_| game.step(Direction::None).err());
+        assert!(
+            matches!(fell, Some(GameError::SnakeFell { .. })),
+            "{fell:?}"
+        );
+    }
+
+    #[test]
+    fn wrapping_along_one_axis_leaves_the_other_alone() {
+        let mut grid = Grid::empty((4, 3, 2));
+        grid.set_wrap(Wrap { x: true, y: false });
+        assert_eq!(grid.step((0, 1, 1), Direction::West), (3, 1, 1));
+        assert_eq!(grid.step((3, 1, 1), Direction::East), (0, 1, 1));
+        assert_eq!(grid.step((1, 0, 1), Direction::North), (1, -1, 1));
+        assert_eq!(grid.step((1, 2, 1), Direction::South), (1, 3, 1));
+
+        grid.set_wrap(Wrap { x: false, y: true });
+        assert_eq!(grid.step((0, 1, 1), Direction::West), (-1, 1, 1));
+        assert_eq!(grid.step((1, 0, 1), Direction::North), (1, 2, 1));
+        assert_eq!(grid.step((1, 2, 1), Direction::South), (1, 0, 1));
+        // z ne boucle jamais
+        grid.set_wrap(Wrap { x: true, y: true });
+        assert_eq!(grid.step((1, 1, 1), Direction::Up), (1, 1, 2));
+        assert_eq!(grid.step((1, 1, 0), Direction::Down), (1, 1, -1));
+    }
+
+    #[test]
+    fn a_y_only_wrap_lets_the_snake_through_the_top_but_not_the_side() {
+        let config = GameConfig {
+            wrap: Wrap { x: false, y: true },
+            ..GameConfig::default()
+        };
+        let level = "WWW\nWWW\nWWW\n\n...\n...\nF..";
+        let mut game = game_with(level, (0, 0, 1), &config);
+        game.step(Direction::North).unwrap();
+        assert_eq!((game.head(), game.foods_eaten()), ((0, 2, 1), 1));
+        let mut game = game_with(level, (0, 0, 1), &config);
+        assert_eq!(
+            game.step(Direction::West).err(),
+            Some(GameError::SnakeCollision {
+                head: (0, 0, 1),
+                attempted_move: (-1, 0, 1),
+            })
+        );
+    }
+
+    #[test]
+    fn eating_then_dying_logs_exactly_that() {
+        let mut game = game("WWW\nWWW\n\n.F.\n..F", (0, 0, 1));
+        game.enable_event_log();
+        game.run_script([Direction::East, Direction::North]);
+        let death = GameError::SnakeCollision {
+            head: (1, 0, 1),
+            attempted_move: (1, -1, 1),
+        };
+        assert_eq!(
+            game.take_events(),
+            [
+                (0, GameEvent::Ate { coord: (1, 0, 1) }),
+                (0, GameEvent::Moved { head: (1, 0, 1) }),
+                (1, GameEvent::Died(death)),
+            ]
+        );
+        assert!(game.take_events().is_empty());
+    }
+
+    #[test]
+    fn apply_changes_only_the_selected_cells() {
+        let mut grid: Grid = "WWW\n\nF.F\n\nV.F".parse().unwrap();
+        let before: Vec<(Vec3, Cell)> = grid.iter().collect();
+        grid.set_meta((2, 0, 1), CellMeta::default()).unwrap();
+        // la nourriture du bas devient un mur, celle du haut disparaît
+        grid.apply(|(_, _, z), cell| match cell {
+            Cell::Food if z == 1 => Cell::Block,
+            Cell::Food => Cell::Empty,
+            other => other,
+        });
+        assert_eq!(grid.count(Cell::Food), 0);
+        for (coord, cell) in before {
+            let expected = match (coord, cell) {
+                ((0 | 2, 0, 1), _) => Cell::Block,
+                ((2, 0, 2), _) => Cell::Empty,
+                _ => cell,
+            };
+            assert_eq!(grid[coord], expected, "{coord:?}");
+        }
+        assert!(grid.meta((2, 0, 1)).is_some());
+
+        grid.apply(|_, cell| {
+            if cell == Cell::Block {
+                Cell::Void
+            } else {
+                cell
+            }
+        });
+        assert_eq!(grid.meta((2, 0, 1)), None);
+        assert_eq!(grid.count(Cell::Block), 0);
+    }
+
+    #[test]
+    fn the_trail_blocks_the_way_back() {
+        let config = GameConfig {
+            trail: true,
+            ..GameConfig::default()
+        };
+        let mut game = game_with("WWW\nWWW\n\n...\n..F", (0, 0, 1), &config);
+        game.run_script([Direction::East, Direction::South, Direction::West]);
+        assert_eq!(game.grid[(0, 0, 1)], Cell::Block);
+        assert_eq!(game.grid[(1, 0, 1)], Cell::Block);
+        assert_eq!(
+            game.step(Direction::North).err(),
+            Some(GameError::SnakeCollision {
+                head: (0, 1, 1),
+                attempted_move: (0, 0, 1),
+            })
+        );
+    }
+
+    #[test]
+    fn a_diff_patches_one_grid_into_the_other() {
+        let before: Grid = "WWW\nWWW\n\nF..\n.k.".parse().unwrap();
+        let after: Grid = "WW.\nWWW\n\n.W.\n.kF".parse().unwrap();
+        let patch = after.diff(&before).unwrap();
+        assert_eq!(
+            patch,
+            [
+                ((2, 0, 0), Cell::Empty),
+                ((0, 0, 1), Cell::Empty),
+                ((1, 0, 1), Cell::Block),
+                ((2, 1, 1), Cell::Food),
+            ]
+        );
+        let mut patched = before.clone();
+        patched.apply_patch(&patch).unwrap();
+        assert!(patched.iter().eq(after.iter()));
+        assert_eq!(after.diff(&patched).unwrap(), []);
+
+        // d'autres dimensions, ou une case hors de la grille
+        assert!(after.diff(&Grid::empty((3, 2, 3))).is_err());
+        let mut grid = before.clone();
+        assert!(
+            grid.apply_patch(&[((1, 1, 1), Cell::Food), ((3, 0, 0), Cell::Food)])
+                .is_err()
+        );
+        assert_eq!(grid[(1, 1, 1)], Cell::Food);
+    }
+
+    // une rangée de `width` cases où la nourriture est aux `foods`, mangée en allant vers l'est
+    fn streak_run(width: usize, foods: &[usize], window: u32) -> GameState {
+        let row: String = (0..width)
+            .map(|x| if foods.contains(&x) { 'F' } else { '.' })
+            .collect();
+        let config = GameConfig {
+            streak: Some(window),
+            ..GameConfig::default()
+        };
+        let floor = "W".repeat(width);
+        let mut game = game_with(&format!("{floor}\n\n{row}"), (0, 0, 1), &config);
+        game.run_script(vec![Direction::East; width - 1]);
+        game
+    }
+
+    #[test]
+    fn eating_in_a_row_builds_up_the_combo_to_its_cap() {
+        let game = streak_run(5, &[1, 2, 3, 4], 2);
+        assert_eq!((game.score(), game.multiplier()), (1 + 2 + 3 + 4, 5));
+        let foods: Vec<usize> = (1..11).collect();
+        let game = streak_run(11, &foods, 2);
+        assert_eq!(game.multiplier(), MAX_MULTIPLIER);
+        assert_eq!(game.score(), (1..=8).sum::<u32>() + 2 * MAX_MULTIPLIER);
+    }
+
+    #[test]
+    fn the_combo_resets_once_the_window_is_over() {
+        // manger `window` ticks après le repas d'avant compte encore, un de plus c'est trop tard
+        let game = streak_run(8, &[1, 3], 2);
+        assert_eq!((game.score(), game.multiplier()), (1 + 2, 3));
+        let game = streak_run(8, &[1, 4], 2);
+        assert_eq!((game.score(), game.multiplier()), (1 + 1, 2));
+
+        // la série en cours, tick après tick
+        let config = GameConfig {
+            streak: Some(2),
+            ..GameConfig::default()
+        };
+        let mut game = game_with("WWWWWWWW\n\n.F.....F", (0, 0, 1), &config);
+        let mut left = Vec::new();
+        for _ in 0..4 {
+            game.step(Direction::East).unwrap();
+            left.push((game.multiplier(), game.streak_left()));
+        }
+        assert_eq!(left, [(2, Some(2)), (2, Some(1)), (2, Some(0)), (1, None)]);
+        // et sans série, rien ne monte
+        let mut plain = game_with("WWWW\n\n.FF.", (0, 0, 1), &GameConfig::default());
+        plain.run_script([Direction::East; 3]);
+        assert_eq!((plain.score(), plain.multiplier()), (2, 1));
+    }
 }
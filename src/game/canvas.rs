@@ -0,0 +1,72 @@
+// Un canvas ruscii sans terminal derrière, qu'on peut relire comme du texte
+
+use std::fmt;
+
+use ruscii::{
+    drawing::Pencil,
+    spatial::Vec2,
+    terminal::{Canvas, Color, VisualElement},
+};
+
+/// Somewhere to draw without a terminal, read back as text, for tests and tools.
+pub struct StringCanvas {
+    canvas: Canvas,
+}
+
+impl StringCanvas {
+    pub fn new(size: Vec2) -> Self {
+        Self {
+            canvas: Canvas::new(size, &VisualElement::default()),
+        }
+    }
+
+    /// A pencil drawing on the canvas, its origin in the top left corner.
+    pub fn pencil(&mut self) -> Pencil<'_> {
+        Pencil::new(&mut self.canvas)
+    }
+
+    /// Blanks everything, like ruscii does between two frames.
+    pub fn clear(&mut self) {
+        self.canvas.clear();
+    }
+
+    /// What's drawn at `pos`, `None` outside the canvas.
+    pub fn char_at(&self, pos: Vec2) -> Option<char> {
+        self.canvas.elem(pos).map(|elem| elem.value)
+    }
+
+    /// The color of what's drawn at `pos`, `None` outside the canvas.
+    pub fn color_at(&self, pos: Vec2) -> Option<Color> {
+        self.canvas.elem(pos).map(|elem| elem.foreground)
+    }
+
+    /// One string per row, without the spaces at the end.
+    pub fn lines(&self) -> Vec<String> {
+        let width = self.canvas.dimension().x.max(1) as usize;
+        self.canvas
+            .data()
+            .chunks(width)
+            .map(|row| {
+                let line: String = row.iter().map(|elem| elem.value).collect();
+                line.trim_end().to_string()
+            })
+            .collect()
+    }
+}
+
+/// The rows one under the other, the empty ones at the bottom left out.
+impl fmt::Display for StringCanvas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = self.lines();
+        while lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl fmt::Debug for StringCanvas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StringCanvas(\n{self}\n)")
+    }
+}
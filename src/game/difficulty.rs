@@ -0,0 +1,131 @@
+// Facile, normal ou difficile : tous les réglages de la partie que ça change sont ici,
+// ceux des niveaux générés sont avec le générateur
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::GameConfig;
+
+/// How hard a game is: how fast it goes, what spawns, what forgives a mistake, and how
+/// hard the generator makes its levels. [`Difficulty::Normal`] is the game's default.
+///
+/// | | easy | normal | hard |
+/// |---|---|---|---|
+/// | ticks per second | 12 | 20 | 28 |
+/// | foods at once (endless) | 2 | 1 | 1 |
+/// | poison with a food | 0% | 0% | 15% |
+/// | turning back ignored | yes | yes | no |
+/// | faster per food eaten | 0% | 0% | 5% |
+/// | at most | - | - | twice as fast |
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    /// The one after, back to easy after hard, for a menu to cycle through them.
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    /// The default config with this difficulty's settings.
+    pub fn to_config(self) -> GameConfig {
+        let mut config = GameConfig::default();
+        self.apply_to(&mut config);
+        config
+    }
+
+    /// Sets the settings that make up this difficulty, leaving the others as they are.
+    pub fn apply_to(self, config: &mut GameConfig) {
+        config.difficulty = self;
+        config.ticks_per_second = match self {
+            Difficulty::Easy => 12,
+            Difficulty::Normal => 20,
+            Difficulty::Hard => 28,
+        };
+        config.foods = match self {
+            Difficulty::Easy => 2,
+            Difficulty::Normal | Difficulty::Hard => 1,
+        };
+        config.poison_percent = match self {
+            Difficulty::Easy | Difficulty::Normal => 0,
+            Difficulty::Hard => 15,
+        };
+        config.reverse_guard = self != Difficulty::Hard;
+        (config.speedup_percent, config.max_speedup_percent) = match self {
+            Difficulty::Easy | Difficulty::Normal => (0, 0),
+            Difficulty::Hard => (5, 100),
+        };
+    }
+}
+
+/// Why the difficulty of a game couldn't be changed.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DifficultyError {
+    #[error("The difficulty can't change once the game has started ({0} ticks in)")]
+    AlreadyStarted(u32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Direction, GameState};
+
+    // le tableau de la doc, une ligne par réglage
+    fn knobs(difficulty: Difficulty) -> (u32, usize, u32, bool, u32, u32) {
+        let config = difficulty.to_config();
+        (
+            config.ticks_per_second,
+            config.foods,
+            config.poison_percent,
+            config.reverse_guard,
+            config.speedup_percent,
+            config.max_speedup_percent,
+        )
+    }
+
+    #[test]
+    fn each_preset_sets_its_knobs() {
+        assert_eq!(knobs(Difficulty::Easy), (12, 2, 0, true, 0, 0));
+        assert_eq!(knobs(Difficulty::Normal), (20, 1, 0, true, 0, 0));
+        assert_eq!(knobs(Difficulty::Hard), (28, 1, 15, false, 5, 100));
+        assert_eq!(Difficulty::Hard.to_config().difficulty, Difficulty::Hard);
+        assert_eq!(Difficulty::default(), Difficulty::Normal);
+        // le reste de la config n'y passe pas
+        let mut config = GameConfig {
+            endless: true,
+            ..GameConfig::default()
+        };
+        Difficulty::Easy.apply_to(&mut config);
+        assert!(config.endless);
+    }
+
+    #[test]
+    fn the_difficulty_only_changes_before_the_first_tick() {
+        let grid = "WWW\n\n..F".parse().unwrap();
+        let mut game = GameState::new((0, 0, 1), grid, &GameConfig::default()).unwrap();
+        assert_eq!(game.set_difficulty(Difficulty::Hard), Ok(()));
+        assert_eq!(game.difficulty(), Difficulty::Hard);
+        game.step(Direction::East).unwrap();
+        assert_eq!(
+            game.set_difficulty(Difficulty::Easy),
+            Err(DifficultyError::AlreadyStarted(1))
+        );
+        assert_eq!(game.difficulty(), Difficulty::Hard);
+    }
+}
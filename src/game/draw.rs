@@ -0,0 +1,688 @@
+// Tout ce qui dépend de ruscii pour l'affichage, le reste du jeu peut tourner sans terminal
+
+use ruscii::{drawing::Pencil, spatial::Vec2, terminal::Color};
+
+use super::{
+    Cell, Direction, GameState, Grid, Hint, Mob, Pos3, Snake, Vec3, contains,
+    fog::Sight,
+    projection::{Isometric, Projection},
+};
+
+/// Whether `a` hides `b` seen through `projection`: both are drawn at the same screen position
+/// and `a` is closer to the camera.
+pub fn occludes(a: Vec3, b: Vec3, projection: &dyn Projection) -> bool {
+    let (gap, step) = (Pos3::from(a) - Pos3::from(b), projection.toward_camera());
+    projection.project(a) == projection.project(b)
+        && gap.x * step.x + gap.y * step.y + gap.z * step.z > 0
+}
+
+/// How the game gets drawn, the defaults showing everything.
+#[derive(Debug, Clone, Copy)]
+pub struct View<'a> {
+    pub projection: &'a dyn Projection,
+    pub layer: Option<isize>, // peek mode, only this layer is drawn
+    pub see_through: bool,    // the head stays visible even behind walls
+    pub trail_fade: bool,     // the body darkens towards the tail
+    pub preview: bool,        // marks where the heads go next tick
+    pub frame: usize,         // the frame being drawn, for what blinks
+}
+
+impl Default for View<'_> {
+    fn default() -> Self {
+        Self {
+            projection: &Isometric,
+            layer: None,
+            see_through: true,
+            trail_fade: true,
+            preview: false,
+            frame: 0,
+        }
+    }
+}
+
+/// How long before collapsing the edge of a shrinking arena starts flashing.
+const SHRINK_WARNING_TICKS: u32 = 20;
+/// How many frames each of the two looks of a blinking cell lasts.
+const BLINK_FRAMES: usize = 5;
+
+/// What each snake is drawn in, the one at the keyboard first.
+pub const PLAYER_COLORS: [Color; 2] = [Color::Yellow, Color::Cyan];
+// the same colors in xterm codes, from the head to the tail
+const TRAIL_RAMPS: [[u8; 5]; 2] = [[226, 220, 178, 136, 94], [51, 44, 37, 30, 23]];
+const KEY_COLORS: [Color; 4] = [Color::Green, Color::Magenta, Color::Blue, Color::White];
+const MOB_COLOR: Color = Color::Red;
+
+/// Where the board goes vertically when it fits in the window, it's always centered horizontally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Align {
+    #[default]
+    Center,
+    Top,
+}
+
+/// Rows kept free above a top aligned board, for the frame and the lines of text at the top.
+const TOP_MARGIN: i32 = 4;
+
+/// Where to put the origin so a board covering `bounds` on screen sits in a `screen` sized area
+/// as `align` says. On an axis where it doesn't fit it starts at the edge, never at negative positions.
+pub fn board_origin_in((min, max): (Vec2, Vec2), screen: Vec2, align: Align) -> Vec2 {
+    let board = max - min + Vec2::xy(1, 1);
+    let margin = Vec2::xy((screen.x - board.x).max(0), (screen.y - board.y).max(0));
+    let top = match align {
+        Align::Center => margin.y / 2,
+        Align::Top => margin.y.min(TOP_MARGIN),
+    };
+    Vec2::xy(margin.x / 2, top) - min
+}
+
+pub trait Draw {
+    fn draw(&self, pencil: &mut Pencil, projection: &dyn Projection);
+}
+
+impl Cell {
+    pub(super) fn to_char(self) -> char {
+        match self {
+            Cell::Void => 'V',
+            Cell::Empty => ' ',
+            Cell::Block => 'W',
+            Cell::Food => 'F',
+            Cell::Spike => '^',
+            Cell::Ice => '~',
+            Cell::Key(_) => 'k',
+            Cell::Door(_) => '#',
+            // comme dans les roguelikes
+            Cell::Stairs(Direction::Down) => '>',
+            Cell::Stairs(_) => '<',
+            // the arrows follow the projection, east goes down and right on screen
+            Cell::Conveyor(Direction::North) => '↗',
+            Cell::Conveyor(Direction::South) => '↙',
+            Cell::Conveyor(Direction::West) => '↖',
+            Cell::Conveyor(_) => '↘',
+            Cell::Portal(..) => '@',
+            Cell::Crate => 'C',
+            Cell::Crumble => '%',
+            Cell::Poison => 'P',
+        }
+    }
+
+    pub(super) fn color(self) -> Color {
+        match self {
+            Cell::Spike => Color::Red,
+            Cell::Ice => Color::Cyan,
+            Cell::Stairs(_) => Color::White,
+            Cell::Conveyor(_) => Color::Green,
+            Cell::Crate => Color::Xterm(130), // du marron
+            Cell::Crumble => Color::Xterm(180),
+            Cell::Poison => Color::Xterm(91), // du violet
+            // une clé a la même couleur que ses portes
+            Cell::Key(id) | Cell::Door(id) | Cell::Portal(id, _) => {
+                KEY_COLORS[id as usize % KEY_COLORS.len()]
+            }
+            _ => Color::Yellow,
+        }
+    }
+
+    // how a blinking cell looks every other BLINK_FRAMES frames, None for those that don't blink
+    fn blinked(self) -> Option<(char, Color)> {
+        match self {
+            Cell::Food => Some(('f', Color::Xterm(208))), // de l'orange
+            _ => None,
+        }
+    }
+}
+
+impl Draw for Grid {
+    fn draw(&self, pencil: &mut Pencil, projection: &dyn Projection) {
+        self.draw_filtered(pencil, None, projection);
+    }
+}
+
+// everything when there is no filter, only that layer otherwise
+fn in_layer(coord: Vec3, z_filter: Option<isize>) -> bool {
+    z_filter.is_none_or(|z| coord.2 == z)
+}
+
+// Les fonctions plot_* donnent ce qu'il faut dessiner et où, pour le pencil ou pour le Renderer
+fn with_pencil(pencil: &mut Pencil) -> impl FnMut(Vec2, char, Color) {
+    |screen, c, color| {
+        pencil.set_foreground(color).draw_char(c, screen);
+    }
+}
+
+impl Grid {
+    /// Draws the grid, or only the cells at height `z_filter` to see what the layers above hide.
+    pub fn draw_filtered(
+        &self,
+        pencil: &mut Pencil,
+        z_filter: Option<isize>,
+        projection: &dyn Projection,
+    ) {
+        self.plot_filtered(z_filter, projection, &mut with_pencil(pencil));
+    }
+
+    pub(super) fn plot_filtered(
+        &self,
+        z_filter: Option<isize>,
+        projection: &dyn Projection,
+        plot: &mut impl FnMut(Vec2, char, Color),
+    ) {
+        self.plot_projected(
+            z_filter,
+            projection,
+            |coord| projection.project(coord),
+            |_, color| Some(color),
+            plot,
+        );
+    }
+
+    // plot_filtered with the screen positions coming from somewhere else, a cache for example,
+    // and the colors going through `shade` which can also hide a cell by giving None
+    pub(super) fn plot_projected(
+        &self,
+        z_filter: Option<isize>,
+        projection: &dyn Projection,
+        screen: impl Fn(Vec3) -> Vec2,
+        shade: impl Fn(Vec3, Color) -> Option<Color>,
+        plot: &mut impl FnMut(Vec2, char, Color),
+    ) {
+        // le Void c'est vraiment rien, il n'y a rien à dessiner
+        for (coord, cell) in self
+            .iter_occupied()
+            .filter(|&(coord, _)| in_layer(coord, z_filter))
+        {
+            if let Some((c, color)) = projection.glyph(coord, cell)
+                && let Some(color) = shade(coord, color)
+            {
+                plot(screen(coord), c, color);
+            }
+        }
+    }
+
+    /// The smallest and biggest screen positions the non-void cells are drawn at, both included.
+    /// High cells end up above the origin so the top can be negative.
+    pub fn screen_bounds(&self, projection: &dyn Projection) -> (Vec2, Vec2) {
+        self.iter_occupied()
+            .map(|(coord, _)| projection.project(coord))
+            .fold(None, |bounds, screen| {
+                let Some((min, max)): Option<(Vec2, Vec2)> = bounds else {
+                    return Some((screen, screen));
+                };
+                Some((
+                    Vec2::xy(min.x.min(screen.x), min.y.min(screen.y)),
+                    Vec2::xy(max.x.max(screen.x), max.y.max(screen.y)),
+                ))
+            })
+            .unwrap_or((Vec2::zero(), Vec2::zero()))
+    }
+
+    /// Where to put the pencil's origin so the whole board is drawn inside a `screen` sized area,
+    /// see [`board_origin_in`].
+    pub fn board_origin(&self, screen: Vec2, align: Align, projection: &dyn Projection) -> Vec2 {
+        board_origin_in(self.screen_bounds(projection), screen, align)
+    }
+
+    /// Same as [`Grid::board_origin`], except that on the axes where the board doesn't fit
+    /// the screen it's moved to keep `focus` in the middle.
+    pub fn board_origin_following(
+        &self,
+        screen: Vec2,
+        focus: Vec3,
+        align: Align,
+        projection: &dyn Projection,
+    ) -> Vec2 {
+        let (min, max) = self.screen_bounds(projection);
+        let board = max - min + Vec2::xy(1, 1);
+        let fitted = self.board_origin(screen, align, projection);
+        let centered = screen / 2 - projection.project(focus);
+        Vec2::xy(
+            if board.x > screen.x {
+                centered.x
+            } else {
+                fitted.x
+            },
+            if board.y > screen.y {
+                centered.y
+            } else {
+                fitted.y
+            },
+        )
+    }
+
+    /// Draws only `layer` in its colors with its empty cells shown, the other layers dimmed behind it.
+    pub fn draw_layer(&self, pencil: &mut Pencil, layer: isize, projection: &dyn Projection) {
+        pencil.set_foreground(Color::DarkGrey);
+        for (coord, cell) in self.iter_occupied().filter(|&((_, _, z), _)| z != layer) {
+            pencil.draw_char(cell.to_char(), projection.project(coord));
+        }
+        for (coord, cell) in self.iter_layer(layer) {
+            let (c, color) = match cell {
+                Cell::Void => continue,
+                Cell::Empty => ('.', Color::Grey),
+                _ => (cell.to_char(), cell.color()),
+            };
+            pencil.set_foreground(color);
+            pencil.draw_char(c, projection.project(coord));
+        }
+    }
+}
+
+/// Draws a single character over `coord`, for things that aren't part of the game like the editor cursor.
+pub fn draw_marker(pencil: &mut Pencil, coord: Vec3, c: char, projection: &dyn Projection) {
+    pencil.draw_char(c, projection.project(coord));
+}
+
+/// Debug overlay showing where the origin is and which way each axis goes, up to `dimensions`.
+pub fn draw_axes(pencil: &mut Pencil, dimensions: Vec3, projection: &dyn Projection) {
+    let (mx, my, mz) = dimensions;
+    let axes = [
+        (Direction::East, mx, 'x'),
+        (Direction::South, my, 'y'),
+        (Direction::Up, mz, 'z'),
+    ];
+    let project = |coord: Pos3| projection.project(coord.into());
+    pencil.set_foreground(Color::Blue);
+    for (dir, len, label) in axes {
+        // le trait suit la direction que prend l'axe à l'écran, rien s'il pointe vers la caméra
+        let line = match project(Pos3::ZERO + dir) - project(Pos3::ZERO) {
+            Vec2 { x: 0, y: 0 } => continue,
+            Vec2 { x: 0, .. } => '|',
+            Vec2 { y: 0, .. } => '-',
+            Vec2 { x, y } if (x > 0) == (y > 0) => '\\',
+            _ => '/',
+        };
+        let mut coord = Pos3::ZERO;
+        for _ in 1..len {
+            coord = coord + dir;
+            pencil.draw_char(line, project(coord));
+        }
+        pencil.draw_char(label, project(coord + dir));
+    }
+    pencil.draw_char('+', project(Pos3::ZERO));
+}
+
+impl Grid {
+    /// The cells drawn over `coord` seen through `projection`, from the closest to `coord` outwards.
+    /// The void and empty cells let what's behind them show.
+    pub fn occluders(
+        &self,
+        coord: Vec3,
+        projection: &dyn Projection,
+    ) -> impl Iterator<Item = Vec3> + '_ {
+        let step = projection.toward_camera();
+        std::iter::successors(Some(Pos3::from(coord) + step), move |&front| {
+            Some(front + step)
+        })
+        .map(Vec3::from)
+        .take_while(|&front| contains(front, self.dimensions))
+        .filter(|&front| !matches!(self.get_unchecked(front), Cell::Void | Cell::Empty))
+    }
+}
+
+impl Snake {
+    // le snake est dessiné après la grille, il faut donc cacher à la main ce qui est derrière un mur
+    fn plot_view(
+        &self,
+        grid: &Grid,
+        view: View,
+        player: usize,
+        plot: &mut impl FnMut(Vec2, char, Color),
+    ) {
+        let len = self.body.len();
+        for (i, &coord) in self.body.iter().enumerate() {
+            let hidden = grid
+                .occluders(coord, view.projection)
+                .any(|front| in_layer(front, view.layer));
+            let shown = in_layer(coord, view.layer) && (!hidden || (view.see_through && i == 0));
+            if shown {
+                let color = if view.trail_fade {
+                    trail_color(&TRAIL_RAMPS[player], i, len)
+                } else {
+                    PLAYER_COLORS[player]
+                };
+                plot(view.projection.project(coord), 'S', color);
+            }
+        }
+    }
+}
+
+impl Mob {
+    // caché derrière les murs comme le serpent
+    fn plot_view(&self, grid: &Grid, view: View, plot: &mut impl FnMut(Vec2, char, Color)) {
+        let hidden = grid
+            .occluders(self.pos, view.projection)
+            .any(|front| in_layer(front, view.layer));
+        if in_layer(self.pos, view.layer) && !hidden {
+            plot(view.projection.project(self.pos), 'M', MOB_COLOR);
+        }
+    }
+}
+
+/// The color of the segment `index` of a body `len` long, the head getting the start of `ramp`
+/// and the tail its end.
+fn trail_color(ramp: &[u8], index: usize, len: usize) -> Color {
+    let last = ramp.len() - 1;
+    let step = match len {
+        0 | 1 => 0,
+        _ => index.min(len - 1) * last / (len - 1),
+    };
+    Color::Xterm(ramp[step])
+}
+
+impl Hint {
+    fn plot_view(&self, view: View, plot: &mut impl FnMut(Vec2, char, Color)) {
+        // the head and the food are already drawn, only the steps in between
+        for &coord in self.path.iter().skip(1).rev().skip(1) {
+            if in_layer(coord, view.layer) {
+                plot(view.projection.project(coord), '·', Color::DarkGrey);
+            }
+        }
+    }
+}
+
+impl Draw for GameState {
+    fn draw(&self, pencil: &mut Pencil, projection: &dyn Projection) {
+        let view = View {
+            projection,
+            ..View::default()
+        };
+        self.draw_view(pencil, view);
+    }
+}
+
+impl GameState {
+    pub fn draw_view(&self, pencil: &mut Pencil, view: View) {
+        let plot = &mut with_pencil(pencil);
+        self.plot_grid(view, |coord| view.projection.project(coord), plot);
+        self.plot_animated(view, &self.animated_cells(), plot);
+        self.plot_overlays(view, plot);
+    }
+
+    // the cells that don't look the same from one frame to the next, the Renderer keeps them
+    // next to its board so it doesn't have to walk the grid every frame to find them
+    pub(super) fn animated_cells(&self) -> Vec<Vec3> {
+        self.grid.find_all(Cell::Food)
+    }
+
+    // Les cases qui clignotent, redessinées à chaque frame par-dessus la grille qui elle ne
+    // change pas. Cachées derrière les murs et dans le brouillard comme les mobs.
+    pub(super) fn plot_animated(
+        &self,
+        view: View,
+        cells: &[Vec3],
+        plot: &mut impl FnMut(Vec2, char, Color),
+    ) {
+        if (view.frame / BLINK_FRAMES).is_multiple_of(2) {
+            return;
+        }
+        for &coord in cells.iter().filter(|&&coord| in_layer(coord, view.layer)) {
+            let cell = self.grid.get_unchecked(coord);
+            let hidden = self
+                .grid
+                .occluders(coord, view.projection)
+                .any(|front| in_layer(front, view.layer));
+            let visible = self
+                .visibility
+                .as_ref()
+                .is_none_or(|visibility| visibility.sight(coord) == Sight::Visible);
+            if let Some((c, color)) = cell.blinked()
+                && view.projection.glyph(coord, cell).is_some()
+                && visible
+                && !hidden
+            {
+                plot(view.projection.project(coord), c, color);
+            }
+        }
+    }
+
+    // the grid as seen through the fog, if there is some
+    pub(super) fn plot_grid(
+        &self,
+        view: View,
+        screen: impl Fn(Vec3) -> Vec2,
+        plot: &mut impl FnMut(Vec2, char, Color),
+    ) {
+        self.grid.plot_projected(
+            view.layer,
+            view.projection,
+            screen,
+            |coord, color| self.through_fog(coord, color),
+            plot,
+        );
+    }
+
+    // ce qu'on a déjà vu reste en gris, ce qu'on n'a jamais vu n'est pas dessiné
+    fn through_fog(&self, coord: Vec3, color: Color) -> Option<Color> {
+        match self
+            .visibility
+            .as_ref()
+            .map(|visibility| visibility.sight(coord))
+        {
+            None | Some(Sight::Visible) => Some(color),
+            Some(Sight::Explored) => Some(Color::DarkGrey),
+            Some(Sight::Unseen) => None,
+        }
+    }
+
+    // what goes over the grid
+    pub(super) fn plot_overlays(&self, view: View, plot: &mut impl FnMut(Vec2, char, Color)) {
+        if let Some(hint) = &self.hint {
+            hint.plot_view(view, plot);
+        }
+        // ce qui va tomber clignote juste avant
+        if self
+            .ticks_until_shrink()
+            .is_some_and(|left| left <= SHRINK_WARNING_TICKS && left % 4 < 2)
+        {
+            for (coord, cell) in self
+                .grid
+                .iter_occupied()
+                .filter(|&(coord, _)| in_layer(coord, view.layer) && self.is_doomed(coord))
+            {
+                plot(view.projection.project(coord), cell.to_char(), Color::Red);
+            }
+        }
+        // les mobs ne se voient que de près, on ne sait pas où ils sont passés depuis
+        for mob in self.mobs.iter().filter(|mob| {
+            self.visibility
+                .as_ref()
+                .is_none_or(|visibility| visibility.sight(mob.pos) == Sight::Visible)
+        }) {
+            mob.plot_view(&self.grid, view, plot);
+        }
+        if view.preview {
+            self.plot_preview(view, plot);
+        }
+        for (i, player) in self.players.iter().enumerate() {
+            player.snake.plot_view(&self.grid, view, i, plot);
+        }
+    }
+
+    // un repère discret là où la tête va arriver, une croix rouge si elle s'y cogne
+    fn plot_preview(&self, view: View, plot: &mut impl FnMut(Vec2, char, Color)) {
+        for player in 0..self.players.len() {
+            let (coord, c, color) = match self.predicted_head_of(player) {
+                Ok(coord) => (coord, '+', Color::DarkGrey),
+                Err(coord) => (coord, 'x', Color::Red),
+            };
+            let hidden = self
+                .grid
+                .occluders(coord, view.projection)
+                .any(|front| in_layer(front, view.layer));
+            if in_layer(coord, view.layer) && !hidden {
+                plot(view.projection.project(coord), c, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Direction, GameConfig, StringCanvas, TopDown};
+
+    #[test]
+    fn each_axis_ends_with_its_label() {
+        let mut canvas = StringCanvas::new(Vec2::xy(14, 7));
+        let origin = Vec2::xy(6, 3);
+        draw_axes(canvas.pencil().set_origin(origin), (3, 2, 2), &Isometric);
+        let at = |x, y| canvas.char_at(origin + Vec2::xy(x, y));
+        assert_eq!(at(0, 0), Some('+'));
+        // x three long going down to the right, y two long to the left, z two long up
+        assert_eq!(
+            (at(2, 1), at(4, 2), at(6, 3)),
+            (Some('\\'), Some('\\'), Some('x'))
+        );
+        assert_eq!((at(-2, 1), at(-4, 2)), (Some('/'), Some('y')));
+        assert_eq!((at(0, -1), at(0, -2)), (Some('|'), Some('z')));
+    }
+
+    #[test]
+    fn an_axis_pointing_at_the_camera_is_not_drawn() {
+        let mut canvas = StringCanvas::new(Vec2::xy(12, 4));
+        draw_axes(&mut canvas.pencil(), (4, 3, 5), &TopDown);
+        assert_eq!(canvas.to_string(), "+ - - - x\n|\n|\ny");
+    }
+
+    #[test]
+    fn screen_bounds_of_a_small_grid() {
+        let full: Grid = "WW\nWW\n\nWW\nWW".parse().unwrap();
+        assert_eq!(
+            full.screen_bounds(&Isometric),
+            (Vec2::xy(-2, -1), Vec2::xy(2, 2))
+        );
+        assert_eq!(
+            full.screen_bounds(&TopDown),
+            (Vec2::xy(0, 0), Vec2::xy(2, 1))
+        );
+        // le vide ne compte pas
+        let corner: Grid = "VV\nVW\n\nVV\nVV".parse().unwrap();
+        assert_eq!(
+            corner.screen_bounds(&Isometric),
+            (Vec2::xy(0, 2), Vec2::xy(0, 2))
+        );
+    }
+
+    #[test]
+    fn the_board_origin_keeps_every_cell_on_screen() {
+        for dimensions in [(2, 2, 2), (5, 3, 4), (10, 10, 6)] {
+            let grid = Grid::empty(dimensions);
+            for screen in [Vec2::xy(80, 40), Vec2::xy(8, 4)] {
+                for align in [Align::Center, Align::Top] {
+                    let origin = grid.board_origin(screen, align, &Isometric);
+                    for (coord, _) in grid.iter() {
+                        let drawn = origin + Isometric.project(coord);
+                        assert!(drawn.x >= 0 && drawn.y >= 0, "{coord:?} drawn at {drawn:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn peeking_draws_only_one_layer() {
+        let grid: Grid = "WW\nWW\n\nF.\nWV".parse().unwrap();
+        let draw = |z_filter| {
+            let mut canvas = StringCanvas::new(Vec2::xy(4, 2));
+            grid.draw_filtered(&mut canvas.pencil(), z_filter, &TopDown);
+            canvas.to_string()
+        };
+        assert_eq!(draw(Some(0)), "0 0\n0 0");
+        assert_eq!(draw(Some(1)), "F\n1");
+        assert_eq!(draw(Some(2)), "");
+        // sans filtre, le dessus est dessiné par-dessus le dessous
+        assert_eq!(draw(None), "F 0\n1 0");
+        assert!(in_layer((3, 4, 1), Some(1)) && in_layer((3, 4, 1), None));
+        assert!(!in_layer((3, 4, 1), Some(0)));
+    }
+
+    #[test]
+    fn what_is_closer_on_the_same_spot_hides_the_rest() {
+        // un pas vers la caméra en isométrique : +1 en x, +1 en y, +2 en z
+        assert!(occludes((1, 1, 2), (0, 0, 0), &Isometric));
+        assert!(occludes((3, 4, 5), (1, 2, 1), &Isometric));
+        assert!(!occludes((0, 0, 0), (1, 1, 2), &Isometric));
+        // pas au même endroit à l'écran
+        assert!(!occludes((1, 1, 1), (0, 0, 0), &Isometric));
+        assert!(!occludes((1, 0, 0), (0, 0, 0), &Isometric));
+        assert!(!occludes((2, 2, 2), (2, 2, 2), &Isometric));
+
+        assert!(occludes((2, 3, 5), (2, 3, 1), &TopDown));
+        assert!(!occludes((2, 3, 1), (2, 3, 5), &TopDown));
+        assert!(!occludes((3, 3, 5), (2, 3, 1), &TopDown));
+    }
+
+    #[test]
+    fn the_trail_darkens_from_head_to_tail() {
+        let ramp = &TRAIL_RAMPS[0];
+        assert_eq!(trail_color(ramp, 0, 9), Color::Xterm(226));
+        assert_eq!(trail_color(ramp, 4, 9), Color::Xterm(178));
+        assert_eq!(trail_color(ramp, 8, 9), Color::Xterm(94));
+        assert_eq!(trail_color(ramp, 1, 3), Color::Xterm(178));
+        // une tête toute seule, et ce qui dépasse prend la couleur de la queue
+        assert_eq!(trail_color(ramp, 0, 1), Color::Xterm(226));
+        assert_eq!(trail_color(ramp, 20, 9), Color::Xterm(94));
+    }
+
+    #[test]
+    fn a_board_is_centered_when_it_fits() {
+        let bounds = (Vec2::xy(-4, -2), Vec2::xy(5, 3)); // 10 de large, 6 de haut
+        assert_eq!(
+            board_origin_in(bounds, Vec2::xy(20, 10), Align::Center),
+            Vec2::xy(9, 4)
+        );
+        assert_eq!(
+            board_origin_in(bounds, Vec2::xy(20, 10), Align::Top),
+            Vec2::xy(9, 6)
+        );
+        // trop grand : il commence au bord, sur l'axe qui ne tient pas seulement
+        assert_eq!(
+            board_origin_in(bounds, Vec2::xy(6, 10), Align::Center),
+            Vec2::xy(4, 4)
+        );
+        assert_eq!(
+            board_origin_in(bounds, Vec2::xy(6, 3), Align::Center),
+            Vec2::xy(4, 2)
+        );
+    }
+
+    #[test]
+    fn conveyors_point_the_way_they_go_on_screen() {
+        let conveyor = |dir| Cell::Conveyor(dir).to_char();
+        assert_eq!(
+            [
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East
+            ]
+            .map(conveyor),
+            ['↗', '↙', '↖', '↘']
+        );
+        assert_eq!(Cell::Conveyor(Direction::West).color(), Color::Green);
+    }
+
+    #[test]
+    fn the_fog_shows_three_tiers_around_the_head() {
+        let grid: Grid = "WWWWWWW\n\n......F".parse().unwrap();
+        let config = GameConfig {
+            fog: Some(1),
+            ..GameConfig::default()
+        };
+        let mut game = GameState::new((0, 0, 1), grid, &config).unwrap();
+        game.step(Direction::East).unwrap();
+        game.step(Direction::East).unwrap();
+        let mut canvas = StringCanvas::new(Vec2::xy(14, 1));
+        let view = View {
+            projection: &TopDown,
+            ..View::default()
+        };
+        game.draw_view(&mut canvas.pencil(), view);
+        // derrière la queue c'est déjà vu donc gris, autour de la tête c'est en couleur,
+        // et plus loin rien, pas même la nourriture
+        assert_eq!(canvas.to_string(), "0 0 S 0");
+        let color = |x| canvas.color_at(Vec2::xy(x, 0));
+        assert_eq!(color(0), Some(Color::DarkGrey));
+        assert_eq!(color(2), Some(Color::Yellow));
+        assert_eq!(color(6), Some(Color::Yellow));
+    }
+}
@@ -0,0 +1,138 @@
+// Le bonus des boucles : les cases que le corps referme complètement sur une couche
+
+use std::collections::{HashSet, VecDeque};
+
+use super::{GameEvent, GameState, Vec3};
+
+/// The cells of layer `z` that `body` closes off, one region after another: those that can't get
+/// out going north, south, west or east without crossing a segment. Only the snake makes a fence,
+/// walls don't close anything.
+pub fn enclosures(body: &HashSet<Vec3>, z: isize) -> Vec<Vec<Vec3>> {
+    let fence: HashSet<(isize, isize)> = body
+        .iter()
+        .filter(|&&(_, _, sz)| sz == z)
+        .map(|&(x, y, _)| (x, y))
+        .collect();
+    let (Some(min_x), Some(max_x)) = (
+        fence.iter().map(|&(x, _)| x).min(),
+        fence.iter().map(|&(x, _)| x).max(),
+    ) else {
+        return Vec::new();
+    };
+    let min_y = fence.iter().map(|&(_, y)| y).min().unwrap_or_default();
+    let max_y = fence.iter().map(|&(_, y)| y).max().unwrap_or_default();
+    // une case de marge tout autour, c'est le dehors d'où on part
+    let (x_range, y_range) = (min_x - 1..=max_x + 1, min_y - 1..=max_y + 1);
+    let inside = |(x, y): (isize, isize)| x_range.contains(&x) && y_range.contains(&y);
+    let fill = |start: (isize, isize), seen: &mut HashSet<(isize, isize)>| {
+        let mut region = Vec::new();
+        let mut queue = VecDeque::from([start]);
+        seen.insert(start);
+        while let Some((x, y)) = queue.pop_front() {
+            region.push((x, y, z));
+            for next in [(x, y - 1), (x, y + 1), (x - 1, y), (x + 1, y)] {
+                if inside(next) && !fence.contains(&next) && seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        region
+    };
+
+    let mut seen = HashSet::new();
+    fill((min_x - 1, min_y - 1), &mut seen);
+    let mut regions = Vec::new();
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            if !fence.contains(&(x, y)) && !seen.contains(&(x, y)) {
+                regions.push(fill((x, y), &mut seen));
+            }
+        }
+    }
+    regions
+}
+
+impl GameState {
+    // Ce que le dernier mouvement de la tête vient de refermer : ce qui est entouré avec elle
+    // mais pas sans. Une boucle qui reste fermée ne rapporte donc qu'une fois.
+    pub(super) fn close_loops(&mut self, player: usize) {
+        let mut body: HashSet<Vec3> = self.snake_body_of(player).collect();
+        let head = self.head_of(player);
+        let count = |body: &HashSet<Vec3>| {
+            enclosures(body, head.2)
+                .iter()
+                .flatten()
+                .filter(|&&cell| cell != head)
+                .count()
+        };
+        let with_head = count(&body);
+        body.remove(&head);
+        let cells = with_head.saturating_sub(count(&body));
+        if cells > 0 {
+            self.players[player].score += cells as u32;
+            self.log(GameEvent::Enclosed { cells });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Direction, GameConfig};
+
+    #[test]
+    fn a_ring_encloses_the_cell_in_its_middle() {
+        let ring: HashSet<Vec3> = [
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (2, 1),
+            (2, 2),
+            (1, 2),
+            (0, 2),
+            (0, 1),
+        ]
+        .into_iter()
+        .map(|(x, y)| (x, y, 1))
+        .collect();
+        assert_eq!(enclosures(&ring, 1), [vec![(1, 1, 1)]]);
+        // une autre couche, ou la boucle ouverte : rien
+        assert!(enclosures(&ring, 0).is_empty());
+        let mut open = ring.clone();
+        open.remove(&(1, 2, 1));
+        assert!(enclosures(&open, 1).is_empty());
+    }
+
+    #[test]
+    fn closing_a_loop_scores_what_it_encloses() {
+        let floor = ["WWWWWWWWW"; 3].join("\n");
+        let grid = format!("{floor}\n\n.........\n.........\nF........")
+            .parse()
+            .unwrap();
+        let config = GameConfig {
+            loops: true,
+            starting_length: 8,
+            starting_facing: Direction::East,
+            ..GameConfig::default()
+        };
+        let mut game = GameState::new((7, 0, 1), grid, &config).unwrap();
+        game.enable_event_log();
+        // autour de (6, 1, 1), la queue en (5, 0, 1) fermant la boucle
+        let around = [
+            Direction::South,
+            Direction::South,
+            Direction::West,
+            Direction::West,
+        ];
+        game.run_script(around);
+        assert_eq!(game.score(), 0);
+        game.run_script([Direction::North]);
+        assert_eq!(game.score(), 1);
+        let events = game.take_events();
+        assert!(
+            events
+                .iter()
+                .any(|(_, event)| *event == GameEvent::Enclosed { cells: 1 })
+        );
+    }
+}
@@ -0,0 +1,107 @@
+// L'arène qui grandit : chaque bouffe mangée rajoute un bout de niveau à côté, relié par une passerelle
+
+use super::{Cell, GameState, GridError, Vec3};
+
+/// How long the walkway between the level and a new chunk is.
+const BRIDGE_LEN: isize = 2;
+const CHUNK_MIN_SIZE: isize = 3;
+const CHUNK_MAX_SIZE: isize = 6;
+
+impl GameState {
+    /// Grows the level by a square chunk east or south of it, bridged to somewhere walkable
+    /// on that side, with the next food in it. Returns false if there was nowhere to bridge from.
+    pub(super) fn expand(&mut self) -> Result<bool, GridError> {
+        let (mx, my, mz) = self.grid.dimensions;
+        // on raisonne le long de l'axe qui grandit, en travers de l'autre
+        let east = self.rng.below(2) == 0;
+        let to_coord = move |along: isize, across: isize, z: isize| -> Vec3 {
+            if east {
+                (along, across, z)
+            } else {
+                (across, along, z)
+            }
+        };
+        let (length, width) = if east { (mx, my) } else { (my, mx) };
+
+        let bridgeheads: Vec<(isize, isize)> = (0..width)
+            .flat_map(|across| (1..mz).map(move |z| (across, z)))
+            .filter(|&(across, z)| {
+                let coord = to_coord(length - 1, across, z);
+                self.grid.get(coord) == Some(Cell::Empty)
+                    && self.grid.cell_below(coord).is_some_and(Cell::is_solid)
+            })
+            .collect();
+        if bridgeheads.is_empty() {
+            return Ok(false);
+        }
+        let (across, z) = bridgeheads[self.rng.below(bridgeheads.len())];
+        let size = self.rng.between(CHUNK_MIN_SIZE, CHUNK_MAX_SIZE);
+        let chunk_along = length + BRIDGE_LEN;
+        let chunk_across = self.rng.between((across - size + 1).max(0), across);
+
+        let new_length = chunk_along + size;
+        let new_width = width.max(chunk_across + size);
+        let (dx, dy) = if east {
+            (new_length, new_width)
+        } else {
+            (new_width, new_length)
+        };
+        self.grid.resize((dx, dy, mz), Cell::Void)?;
+
+        let walkway = (length..chunk_along).map(|along| (along, across));
+        let chunk = (chunk_along..new_length).flat_map(|along| {
+            (chunk_across..chunk_across + size).map(move |across| (along, across))
+        });
+        for (along, across) in walkway.chain(chunk) {
+            self.grid.set(to_coord(along, across, z - 1), Cell::Block)?;
+            for above in z..mz {
+                self.grid.set(to_coord(along, across, above), Cell::Empty)?;
+            }
+        }
+        let food = to_coord(
+            chunk_along + self.rng.below(size as usize) as isize,
+            chunk_across + self.rng.below(size as usize) as isize,
+            z,
+        );
+        self.grid.set(food, Cell::Food)?;
+        self.grid_revision += 1;
+        self.log(super::GameEvent::Expanded {
+            dimensions: self.grid.dimensions,
+        });
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameConfig, Grid};
+
+    #[test]
+    fn every_new_chunk_can_be_walked_to() {
+        let grid: Grid = "WWW\nWWW\nWWW\n\n...\n...\n...".parse().unwrap();
+        let config = GameConfig {
+            expanding: true,
+            ..GameConfig::default()
+        };
+        let mut game = GameState::new((1, 1, 1), grid, &config).unwrap();
+        for _ in 0..10 {
+            let (before, fed) = (game.grid.dimensions(), game.grid.find_all(Cell::Food));
+            assert!(game.expand().unwrap());
+            let (mx, my, mz) = game.grid.dimensions();
+            assert!(mx > before.0 || my > before.1);
+            assert_eq!(mz, before.2);
+            // la nouvelle nourriture est dans le nouveau bout, et on peut aller la chercher
+            let foods = game.grid.find_all(Cell::Food);
+            let [food] = foods[..]
+                .iter()
+                .filter(|food| !fed.contains(food))
+                .collect::<Vec<_>>()[..]
+            else {
+                panic!("{foods:?} after {fed:?}");
+            };
+            assert!(food.0 >= before.0 + BRIDGE_LEN || food.1 >= before.1 + BRIDGE_LEN);
+            assert!(game.grid.reachable(game.head()).contains(food));
+        }
+    }
+}
@@ -0,0 +1,157 @@
+// Le brouillard : on ne voit que autour de la tête, et on se souvient de ce qu'on a déjà vu
+
+use std::collections::HashSet;
+
+use super::{Vec3, contains};
+
+/// How well a cell can be seen in fog of war mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sight {
+    Visible,
+    Explored, // seen before, drawn dimmed
+    Unseen,
+}
+
+/// What the snake sees around its head, up to `radius` cells away along every axis at once,
+/// and everything it has seen so far. Only there for the screen, the game plays the same without it.
+#[derive(Debug, Clone, Default)]
+pub struct Visibility {
+    radius: isize,
+    center: Option<Vec3>,
+    explored: HashSet<Vec3>,
+    dark: bool,    // nothing is remembered, out of sight is never drawn
+    revision: u64, // incremented every time what's seen changes
+}
+
+impl Visibility {
+    pub fn new(radius: u32) -> Self {
+        Self {
+            radius: radius as isize,
+            ..Self::default()
+        }
+    }
+
+    /// Like [`Visibility::new`] but forgetting everything as soon as it's out of sight.
+    pub fn dark(radius: u32) -> Self {
+        Self {
+            dark: true,
+            ..Self::new(radius)
+        }
+    }
+
+    pub fn radius(&self) -> u32 {
+        self.radius as u32
+    }
+
+    pub fn is_dark(&self) -> bool {
+        self.dark
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Looks around `head`, does nothing if it hasn't moved.
+    pub fn update(&mut self, head: Vec3, dimensions: Vec3) {
+        if self.center == Some(head) {
+            return;
+        }
+        self.center = Some(head);
+        self.revision += 1;
+        if self.dark {
+            return;
+        }
+        let (hx, hy, hz) = head;
+        let r = self.radius;
+        for z in hz - r..=hz + r {
+            for y in hy - r..=hy + r {
+                for x in hx - r..=hx + r {
+                    if contains((x, y, z), dimensions) {
+                        self.explored.insert((x, y, z));
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn sight(&self, (x, y, z): Vec3) -> Sight {
+        match self.center {
+            Some((hx, hy, hz))
+                if (x - hx).abs() <= self.radius
+                    && (y - hy).abs() <= self.radius
+                    && (z - hz).abs() <= self.radius =>
+            {
+                Sight::Visible
+            }
+            _ if self.explored.contains(&(x, y, z)) => Sight::Explored,
+            _ => Sight::Unseen,
+        }
+    }
+
+    /// Everything seen so far, in a stable order for saving.
+    pub fn explored(&self) -> Vec<Vec3> {
+        let mut explored: Vec<Vec3> = self.explored.iter().copied().collect();
+        explored.sort_unstable();
+        explored
+    }
+
+    pub fn set_explored(&mut self, explored: impl IntoIterator<Item = Vec3>) {
+        self.explored = explored.into_iter().collect();
+        self.center = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIMENSIONS: Vec3 = (10, 10, 5);
+
+    #[test]
+    fn sight_reaches_the_radius_along_every_axis_at_once() {
+        let mut visibility = Visibility::new(2);
+        assert_eq!(visibility.sight((5, 5, 2)), Sight::Unseen);
+        visibility.update((5, 5, 2), DIMENSIONS);
+        for visible in [
+            (7, 5, 2),
+            (3, 5, 2),
+            (5, 7, 2),
+            (5, 5, 4),
+            (5, 5, 0),
+            (7, 7, 4),
+            (3, 3, 0),
+        ] {
+            assert_eq!(visibility.sight(visible), Sight::Visible, "{visible:?}");
+        }
+        for unseen in [(8, 5, 2), (5, 2, 2), (8, 8, 2), (7, 8, 4)] {
+            assert_eq!(visibility.sight(unseen), Sight::Unseen, "{unseen:?}");
+        }
+
+        // un pas plus loin, ce qui sort du rayon est déjà vu
+        visibility.update((6, 5, 2), DIMENSIONS);
+        assert_eq!(visibility.sight((8, 5, 2)), Sight::Visible);
+        assert_eq!(visibility.sight((3, 5, 2)), Sight::Explored);
+        assert_eq!(visibility.sight((2, 5, 2)), Sight::Unseen);
+    }
+
+    #[test]
+    fn only_what_is_in_the_grid_gets_explored() {
+        let mut visibility = Visibility::new(1);
+        visibility.update((0, 0, 0), DIMENSIONS);
+        assert_eq!(visibility.explored().len(), 8);
+        assert_eq!(visibility.explored().first(), Some(&(0, 0, 0)));
+    }
+
+    #[test]
+    fn the_dark_forgets_what_is_out_of_sight() {
+        let mut visibility = Visibility::dark(1);
+        visibility.update((5, 5, 2), DIMENSIONS);
+        let revision = visibility.revision();
+        visibility.update((5, 5, 2), DIMENSIONS);
+        assert_eq!(visibility.revision(), revision);
+        visibility.update((7, 5, 2), DIMENSIONS);
+        assert_eq!(visibility.sight((6, 5, 2)), Sight::Visible);
+        assert_eq!(visibility.sight((5, 5, 2)), Sight::Unseen);
+        assert!(visibility.explored().is_empty());
+    }
+}
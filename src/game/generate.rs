@@ -0,0 +1,226 @@
+// Des niveaux tirés au hasard : un sol plein, des plateformes posées dessus et de quoi manger partout
+
+use std::collections::HashSet;
+
+use super::{Cell, Difficulty, Direction, Grid, Rng, Vec3};
+
+/// Goes up whenever the same seed starts giving a different level, so that replays of
+/// generated levels can tell they won't play back the same.
+pub const VERSION: u8 = 1;
+
+/// Where the snake starts in a generated level, the generator keeps it free.
+pub const SPAWN: Vec3 = (0, 0, 1);
+
+/// The smallest level the generator makes, smaller dimensions are grown to it.
+const MIN_DIMENSIONS: Vec3 = (2, 2, 2);
+// a platform is this many cells across at most
+const MAX_PLATFORM: isize = 4;
+const MAX_FOOD: usize = 10;
+
+// ce que la difficulté change aux niveaux générés
+impl Difficulty {
+    /// How many floor cells there are for each platform the generator tries to put down.
+    fn cells_per_platform(self) -> isize {
+        match self {
+            Difficulty::Easy => 32,
+            Difficulty::Normal => 16,
+            Difficulty::Hard => 8,
+        }
+    }
+
+    /// How many cells around a platform no other platform can take, the corridors between
+    /// them being at least one cell wider than that.
+    fn platform_gap(self) -> isize {
+        match self {
+            Difficulty::Easy => 3,
+            Difficulty::Normal => 2,
+            Difficulty::Hard => 1,
+        }
+    }
+
+    /// How many floor cells there are for each hole dug in the floor, none when easy.
+    fn cells_per_pit(self) -> Option<isize> {
+        match self {
+            Difficulty::Easy => None,
+            Difficulty::Normal => Some(48),
+            Difficulty::Hard => Some(16),
+        }
+    }
+
+    /// Whether platforms can get a second, smaller one on top, with its own stairs.
+    fn tiers(self) -> bool {
+        self == Difficulty::Hard
+    }
+
+    /// How far apart two foods are at least, counting steps along the floor.
+    fn food_spacing(self) -> isize {
+        match self {
+            Difficulty::Easy => 1,
+            Difficulty::Normal => 3,
+            Difficulty::Hard => 5,
+        }
+    }
+}
+
+/// A playable level for `seed`: a floor of blocks, platforms on it, some with stairs to climb
+/// them, and food that the snake can get to from [`SPAWN`].
+/// The same dimensions, seed and difficulty always give the same level.
+pub fn generate(dimensions: Vec3, seed: u64, difficulty: Difficulty) -> Grid {
+    let (mx, my, mz) = (
+        dimensions.0.max(MIN_DIMENSIONS.0),
+        dimensions.1.max(MIN_DIMENSIONS.1),
+        dimensions.2.max(MIN_DIMENSIONS.2),
+    );
+    let mut rng = Rng::new(seed);
+    let mut grid = Grid::empty((mx, my, mz));
+    for y in 0..my {
+        for x in 0..mx {
+            grid[(x, y, 0)] = Cell::Block;
+        }
+    }
+
+    // Les plateformes ne se touchent pas et restent loin des bords, et le coin de départ
+    // reste du sol pour qu'il y ait toujours une case où aller
+    let mut taken: HashSet<(isize, isize)> = (0..2)
+        .flat_map(|y| (0..2).map(move |x| (SPAWN.0 + x, SPAWN.1 + y)))
+        .collect();
+    if mz >= 3 {
+        for _ in 0..mx * my / difficulty.cells_per_platform() {
+            let (w, h) = (rng.between(2, MAX_PLATFORM), rng.between(2, MAX_PLATFORM));
+            if mx - 1 - w < 1 || my - 1 - h < 1 {
+                continue;
+            }
+            let (x0, y0) = (rng.between(1, mx - 1 - w), rng.between(1, my - 1 - h));
+            let area = |margin: isize| {
+                (y0 - margin..y0 + h + margin)
+                    .flat_map(move |y| (x0 - margin..x0 + w + margin).map(move |x| (x, y)))
+            };
+            if area(1).any(|cell| taken.contains(&cell)) {
+                continue;
+            }
+            taken.extend(area(difficulty.platform_gap()));
+            for (x, y) in area(0) {
+                grid[(x, y, 1)] = Cell::Block;
+            }
+            // une marche sur un des côtés, sauf sur une plateforme sur trois qui reste hors d'atteinte
+            if rng.below(3) > 0 {
+                let (sx, sy) = match rng.below(4) {
+                    0 => (x0 + rng.between(0, w - 1), y0 - 1),
+                    1 => (x0 + rng.between(0, w - 1), y0 + h),
+                    2 => (x0 - 1, y0 + rng.between(0, h - 1)),
+                    _ => (x0 + w, y0 + rng.between(0, h - 1)),
+                };
+                grid[(sx, sy, 1)] = Cell::Stairs(Direction::Up);
+            }
+            // un étage de plus, en retrait d'une case sur le bord ouest où est sa marche
+            if difficulty.tiers() && mz >= 4 && w >= 3 && h >= 3 && rng.below(2) == 0 {
+                for y in y0..y0 + h {
+                    for x in x0 + 1..x0 + w {
+                        grid[(x, y, 2)] = Cell::Block;
+                    }
+                }
+                grid[(x0, y0 + rng.between(0, h - 1), 2)] = Cell::Stairs(Direction::Up);
+            }
+        }
+    }
+
+    // les trous du sol et les plateformes sans marche peuvent couper des coins du niveau,
+    // la nourriture ne va que là où on arrive vraiment
+    if let Some(cells_per_pit) = difficulty.cells_per_pit() {
+        for _ in 0..mx * my / cells_per_pit {
+            let (x, y) = (rng.between(0, mx - 1), rng.between(0, my - 1));
+            if !taken.contains(&(x, y)) && grid[(x, y, 1)] == Cell::Empty {
+                grid[(x, y, 0)] = Cell::Empty;
+            }
+        }
+    }
+
+    let reachable = grid.reachable(SPAWN);
+    let mut spots: Vec<Vec3> = (0..mz)
+        .flat_map(|z| (0..my).flat_map(move |y| (0..mx).map(move |x| (x, y, z))))
+        .filter(|coord| *coord != SPAWN && grid[*coord] == Cell::Empty)
+        // the head goes through some cells while falling, food can't float there
+        .filter(|coord| grid.cell_below(*coord).is_some_and(Cell::is_solid))
+        .filter(|coord| reachable.contains(coord))
+        .collect();
+    let foods = (mx * my / 16).clamp(1, MAX_FOOD as isize) as usize;
+    for _ in 0..foods {
+        if spots.is_empty() {
+            break;
+        }
+        let spot = spots.swap_remove(rng.below(spots.len()));
+        grid[spot] = Cell::Food;
+        let spacing = difficulty.food_spacing();
+        spots.retain(|&(x, y, _)| (x - spot.0).abs() + (y - spot.1).abs() >= spacing);
+    }
+    debug_assert!(grid.validate_reachable(SPAWN).is_ok());
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFFICULTIES: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    #[test]
+    fn every_food_can_be_reached_from_the_spawn() {
+        for difficulty in DIFFICULTIES {
+            for seed in 0..10 {
+                for dimensions in [(2, 2, 2), (12, 8, 3), (30, 20, 5)] {
+                    let grid = generate(dimensions, seed, difficulty);
+                    assert_eq!(
+                        grid.validate_reachable(SPAWN),
+                        Ok(()),
+                        "seed {seed} {dimensions:?} {}",
+                        difficulty.name()
+                    );
+                    assert!(grid.count(Cell::Food) > 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn harder_levels_stay_within_their_density() {
+        let (mx, my, mz) = (24, 16, 4);
+        let mut platforms = [0; 3];
+        for (i, difficulty) in DIFFICULTIES.into_iter().enumerate() {
+            for seed in 0..10 {
+                let grid = generate((mx, my, mz), seed, difficulty);
+                let on_layer = |z, cell| {
+                    (0..my)
+                        .flat_map(|y| (0..mx).map(move |x| (x, y, z)))
+                        .filter(|&coord| grid[coord] == cell)
+                        .count() as isize
+                };
+                let pits = on_layer(0, Cell::Empty);
+                match difficulty.cells_per_pit() {
+                    Some(cells_per_pit) => assert!(pits <= mx * my / cells_per_pit),
+                    None => assert_eq!(pits, 0),
+                }
+                let raised = on_layer(1, Cell::Block);
+                let most = mx * my / difficulty.cells_per_platform() * MAX_PLATFORM * MAX_PLATFORM;
+                assert!(raised <= most, "{raised} raised cells");
+                platforms[i] += raised;
+                if !difficulty.tiers() {
+                    assert_eq!(on_layer(2, Cell::Block), 0);
+                }
+
+                let foods = grid.find_all(Cell::Food);
+                assert!(foods.len() <= MAX_FOOD);
+                let spacing = difficulty.food_spacing();
+                for (a, &(ax, ay, _)) in foods.iter().enumerate() {
+                    for &(bx, by, _) in &foods[a + 1..] {
+                        assert!((ax - bx).abs() + (ay - by).abs() >= spacing);
+                    }
+                }
+            }
+        }
+        // plus c'est dur, plus il y a de plateformes
+        assert!(
+            platforms[0] < platforms[1] && platforms[1] < platforms[2],
+            "{platforms:?}"
+        );
+    }
+}
@@ -0,0 +1,161 @@
+// Des bestioles qui se baladent dans le niveau et mangent le serpent qui leur fonce dedans
+
+use serde::{Deserialize, Serialize};
+
+use super::{Cell, Direction, GameError, GameState, SpawnError, Vec3, contains};
+
+/// How a mob gets around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Patrol {
+    Line(Direction), // back and forth, going this way first
+    Wander,          // to a random cell next to it every tick
+}
+
+/// Something that isn't a snake walking around the level, deadly to the head.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mob {
+    pub pos: Vec3,
+    pub patrol: Patrol,
+}
+
+impl Mob {
+    pub fn new(pos: Vec3, patrol: Patrol) -> Self {
+        Self { pos, patrol }
+    }
+}
+
+// where a mob can stand: in the open, on something that holds
+fn walkable(game: &GameState, coord: Vec3) -> bool {
+    matches!(game.grid.get(coord), Some(Cell::Empty | Cell::Food))
+        && game.grid.cell_below(coord).is_some_and(Cell::supports)
+}
+
+impl GameState {
+    /// Adds a mob, refusing one outside the level or inside something.
+    pub fn spawn_mob(&mut self, mob: Mob) -> Result<(), SpawnError> {
+        if !contains(mob.pos, self.grid.dimensions) {
+            return Err(SpawnError::OutOfBounds(mob.pos));
+        }
+        if !matches!(self.grid.get(mob.pos), Some(Cell::Empty | Cell::Food))
+            || self.is_occupied(mob.pos)
+        {
+            return Err(SpawnError::Blocked(mob.pos));
+        }
+        self.mobs.push(mob);
+        Ok(())
+    }
+
+    pub fn mobs(&self) -> &[Mob] {
+        &self.mobs
+    }
+
+    // adds whoever still alive has their head on a mob to `deaths`
+    pub(super) fn check_eaten(&self, deaths: &mut Vec<(usize, GameError)>) {
+        for (player, p) in self.players.iter().enumerate() {
+            let head = *p.snake.head();
+            if self.mobs.iter().any(|mob| mob.pos == head)
+                && deaths.iter().all(|&(dead, _)| dead != player)
+            {
+                deaths.push((player, GameError::SnakeEaten { head }));
+            }
+        }
+    }
+
+    // Après les serpents : un mob qui n'a plus rien sous lui tombe, sinon il avance.
+    // Un corps de serpent le fait juste faire demi-tour, une tête il la mange.
+    pub(super) fn move_mobs(&mut self) {
+        let heads: Vec<Vec3> = self.players.iter().map(|p| *p.snake.head()).collect();
+        let blocked = |game: &GameState, coord: Vec3| {
+            !walkable(game, coord) || (game.is_occupied(coord) && !heads.contains(&coord))
+        };
+        for idx in 0..self.mobs.len() {
+            let mob = self.mobs[idx];
+            if !self.grid.cell_below(mob.pos).is_some_and(Cell::supports) {
+                self.mobs[idx].pos = mob.pos + Direction::Down;
+                continue;
+            }
+            match mob.patrol {
+                Patrol::Line(dir) if blocked(self, self.grid.step(mob.pos, dir)) => {
+                    self.mobs[idx].patrol = Patrol::Line(dir.opposite());
+                }
+                Patrol::Line(dir) => self.mobs[idx].pos = self.grid.step(mob.pos, dir),
+                Patrol::Wander => {
+                    let free: Vec<Vec3> = Direction::horizontal()
+                        .into_iter()
+                        .map(|dir| self.grid.step(mob.pos, dir))
+                        .filter(|&coord| !blocked(self, coord))
+                        .collect();
+                    if !free.is_empty() {
+                        self.mobs[idx].pos = free[self.rng.below(free.len())];
+                    }
+                }
+            }
+        }
+        // ceux qui sont tombés hors du niveau n'en reviendront pas
+        let dimensions = self.grid.dimensions;
+        self.mobs.retain(|mob| contains(mob.pos, dimensions));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameConfig;
+
+    // deux rangées, un mur au bout de la première, le serpent au début de la seconde
+    fn patrolled(mob: Mob, snake: Vec3) -> GameState {
+        let grid = "WWWWW\nWWWWW\n\n...W.\n....F".parse().unwrap();
+        let mut game = GameState::new(snake, grid, &GameConfig::default()).unwrap();
+        game.spawn_mob(mob).unwrap();
+        game
+    }
+
+    #[test]
+    fn a_patrol_turns_around_at_a_wall() {
+        let mut game = patrolled(
+            Mob::new((1, 0, 1), Patrol::Line(Direction::East)),
+            (0, 1, 1),
+        );
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            game.update().unwrap();
+            seen.push(game.mobs()[0]);
+        }
+        let line = |x, dir| Mob::new((x, 0, 1), Patrol::Line(dir));
+        assert_eq!(
+            seen,
+            [
+                line(2, Direction::East),
+                // le demi-tour prend un tick
+                line(2, Direction::West),
+                line(1, Direction::West),
+                line(0, Direction::West),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_snake_moves_before_the_mobs() {
+        // il rentre dans le mob avant que celui-ci ne s'en aille
+        let mut game = patrolled(
+            Mob::new((2, 1, 1), Patrol::Line(Direction::East)),
+            (1, 1, 1),
+        );
+        game.queue_input(Direction::East);
+        assert_eq!(
+            game.update(),
+            Err(GameError::SnakeEaten { head: (2, 1, 1) })
+        );
+
+        // et le mob qui arrive ensuite sur la tête la mange aussi
+        let mut game = patrolled(
+            Mob::new((2, 1, 1), Patrol::Line(Direction::West)),
+            (0, 1, 1),
+        );
+        game.queue_input(Direction::East);
+        assert_eq!(
+            game.update(),
+            Err(GameError::SnakeEaten { head: (1, 1, 1) })
+        );
+    }
+}
@@ -0,0 +1,100 @@
+// Une vraie position 3D, pour migrer petit à petit depuis l'alias Vec3
+
+use std::ops::{Add, Sub};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Direction, Vec3};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Pos3 {
+    pub x: isize,
+    pub y: isize,
+    pub z: isize,
+}
+
+impl Pos3 {
+    pub const ZERO: Pos3 = Pos3::new(0, 0, 0);
+
+    pub const fn new(x: isize, y: isize, z: isize) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Whether the position is inside a box going from the origin to `dimensions` (excluded).
+    pub fn is_within(self, dimensions: Pos3) -> bool {
+        (0..dimensions.x).contains(&self.x)
+            && (0..dimensions.y).contains(&self.y)
+            && (0..dimensions.z).contains(&self.z)
+    }
+}
+
+impl From<Vec3> for Pos3 {
+    fn from((x, y, z): Vec3) -> Self {
+        Self { x, y, z }
+    }
+}
+
+impl From<Pos3> for Vec3 {
+    fn from(pos: Pos3) -> Self {
+        (pos.x, pos.y, pos.z)
+    }
+}
+
+impl Add for Pos3 {
+    type Output = Pos3;
+
+    fn add(self, other: Pos3) -> Pos3 {
+        Pos3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Pos3 {
+    type Output = Pos3;
+
+    fn sub(self, other: Pos3) -> Pos3 {
+        Pos3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Add<Direction> for Pos3 {
+    type Output = Pos3;
+
+    fn add(self, dir: Direction) -> Pos3 {
+        self + dir.delta()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        let (a, b) = (Pos3::new(1, -2, 3), Pos3::new(4, 5, -6));
+        assert_eq!(a + b, Pos3::new(5, 3, -3));
+        assert_eq!(a - b, Pos3::new(-3, -7, 9));
+        assert_eq!(a + b - b, a);
+        assert_eq!(a + Pos3::ZERO, a);
+        assert_eq!(a + Direction::Up, Pos3::new(1, -2, 4));
+        assert_eq!(a + Direction::North + Direction::South, a);
+        assert_eq!(a + Direction::None, a);
+    }
+
+    #[test]
+    fn tuple_conversions() {
+        let tuple: Vec3 = (7, -8, 9);
+        let pos = Pos3::from(tuple);
+        assert_eq!(pos, Pos3::new(7, -8, 9));
+        assert_eq!(Vec3::from(pos), tuple);
+        assert_eq!(tuple + Direction::West, (6, -8, 9));
+    }
+
+    #[test]
+    fn within_a_box() {
+        let dimensions = Pos3::new(3, 2, 1);
+        assert!(Pos3::ZERO.is_within(dimensions));
+        assert!(Pos3::new(2, 1, 0).is_within(dimensions));
+        assert!(!Pos3::new(3, 1, 0).is_within(dimensions));
+        assert!(!Pos3::new(0, 0, -1).is_within(dimensions));
+    }
+}
@@ -0,0 +1,196 @@
+// Des petits bouts de niveau tout faits, à tamponner dans une grille
+
+use std::str::FromStr;
+
+use super::{Cell, Direction, Grid, GridError, Pos3, Vec3, contains};
+
+/// How [`Grid::stamp`] puts a prefab down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StampMode {
+    pub skip_void: bool,   // the prefab's void leaves what's under it alone
+    pub quarter_turns: u8, // around the z axis, x going to y
+    pub clip: bool,        // what falls outside the grid is dropped instead of failing
+}
+
+/// A staircase turning around a 3x3 footprint, entered going east from its north west corner
+/// and left going west from its south west corner three levels up.
+pub const SPIRAL_STAIRCASE: &str = "\
+WWW
+WWW
+WWW
+
+.<W
+..W
+..W
+
+V..
+VV.
+WW<
+
+VVV
+VVV
+...";
+
+/// A walkway three cells long, on its own in the void.
+pub const BRIDGE: &str = "\
+WWW
+
+...";
+
+/// A 3x3 tower, walled on its four sides with its middle left empty.
+pub const HOLLOW_TOWER: &str = "\
+WWW
+WWW
+WWW
+
+WWW
+W.W
+WWW
+
+WWW
+W.W
+WWW";
+
+impl Grid {
+    /// Copies `prefab` into the grid with its origin at `origin`, see [`StampMode`].
+    /// Nothing is changed if part of it doesn't fit and the mode doesn't clip.
+    pub fn stamp(&mut self, origin: Vec3, prefab: &Grid, mode: StampMode) -> Result<(), GridError> {
+        let placed: Vec<(Vec3, Vec3, Cell)> = prefab
+            .iter()
+            .filter(|&(_, cell)| !(mode.skip_void && cell == Cell::Void))
+            .map(|(coord, cell)| {
+                let turned = rotate_z(coord, prefab.dimensions, mode.quarter_turns);
+                (
+                    coord,
+                    (Pos3::from(origin) + Pos3::from(turned)).into(),
+                    cell,
+                )
+            })
+            .collect();
+        if !mode.clip
+            && let Some(&(_, outside, _)) = placed
+                .iter()
+                .find(|&&(_, target, _)| !contains(target, self.dimensions))
+        {
+            return Err(GridError::OutOfBounds {
+                coord: outside,
+                dimensions: self.dimensions,
+            });
+        }
+        for (from, target, cell) in placed {
+            if !contains(target, self.dimensions) {
+                continue;
+            }
+            self.set(target, cell)?;
+            if let Some(meta) = prefab.meta(from) {
+                self.set_meta(target, meta.clone())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// where `coord` ends up once a grid of `dimensions` is turned a quarter `turns` times around z
+pub(super) fn rotate_z((x, y, z): Vec3, (mx, my, _): Vec3, turns: u8) -> Vec3 {
+    match turns % 4 {
+        0 => (x, y, z),
+        1 => (my - 1 - y, x, z),
+        2 => (mx - 1 - x, my - 1 - y, z),
+        _ => (y, mx - 1 - x, z),
+    }
+}
+
+/// Layers from the bottom up separated by an empty line, each one a row per y and a character per x:
+/// `V` void, `.` empty, `W` block, `F` food, `^` spike, `~` ice, `k` key, `#` door, `<` `>` stairs up and down.
+impl FromStr for Grid {
+    type Err = GridError;
+
+    fn from_str(text: &str) -> Result<Self, GridError> {
+        let layers: Vec<Vec<&str>> = text
+            .split("\n\n")
+            .map(|layer| layer.lines().collect())
+            .collect();
+        let my = layers[0].len();
+        let mx = layers[0].first().map_or(0, |row| row.chars().count());
+        if let Some(layer) = layers.iter().find(|layer| layer.len() != my) {
+            return Err(GridError::Parse(format!(
+                "a layer has {} rows instead of {my}",
+                layer.len()
+            )));
+        }
+        let mut cells = Vec::with_capacity(mx * my * layers.len());
+        for row in layers.iter().flatten() {
+            if row.chars().count() != mx {
+                return Err(GridError::Parse(format!("the row {row:?} isn't {mx} long")));
+            }
+            for c in row.chars() {
+                cells.push(
+                    char_cell(c).ok_or_else(|| GridError::Parse(format!("unknown cell {c:?}")))?,
+                );
+            }
+        }
+        Grid::new((mx as isize, my as isize, layers.len() as isize), cells)
+    }
+}
+
+fn char_cell(c: char) -> Option<Cell> {
+    Some(match c {
+        'V' => Cell::Void,
+        '.' => Cell::Empty,
+        'W' => Cell::Block,
+        'F' => Cell::Food,
+        '^' => Cell::Spike,
+        '~' => Cell::Ice,
+        'k' => Cell::Key(0),
+        '#' => Cell::Door(0),
+        '<' => Cell::Stairs(Direction::Up),
+        '>' => Cell::Stairs(Direction::Down),
+        '%' => Cell::Crumble,
+        'P' => Cell::Poison,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn void_in_a_prefab_can_leave_the_grid_alone() {
+        let prefab: Grid = "VF\n.V".parse().unwrap();
+        for (skip_void, kept) in [(true, Some(Cell::Block)), (false, None)] {
+            let mut grid: Grid = "WWW\nWWW".parse().unwrap();
+            let mode = StampMode {
+                skip_void,
+                ..StampMode::default()
+            };
+            grid.stamp((1, 0, 0), &prefab, mode).unwrap();
+            assert_eq!(grid.get((1, 0, 0)), kept);
+            assert_eq!(grid.get((2, 1, 0)), kept);
+            assert_eq!(grid.get((2, 0, 0)), Some(Cell::Food));
+            assert_eq!(grid.get((1, 1, 0)), Some(Cell::Empty));
+            // en dehors du tampon rien ne change
+            assert_eq!(grid.get((0, 0, 0)), Some(Cell::Block));
+        }
+    }
+
+    #[test]
+    fn a_prefab_turns_a_quarter_at_a_time() {
+        // trois de large, deux de haut : on voit de quel côté il est tourné
+        let prefab: Grid = "FW.\n...".parse().unwrap();
+        let stamped = |quarter_turns| {
+            let mut grid = Grid::empty((3, 3, 1));
+            let mode = StampMode {
+                quarter_turns,
+                ..StampMode::default()
+            };
+            grid.stamp((0, 0, 0), &prefab, mode).unwrap();
+            (grid.find_all(Cell::Food), grid.find_all(Cell::Block))
+        };
+        assert_eq!(stamped(0), (vec![(0, 0, 0)], vec![(1, 0, 0)]));
+        assert_eq!(stamped(1), (vec![(1, 0, 0)], vec![(1, 1, 0)]));
+        assert_eq!(stamped(2), (vec![(2, 1, 0)], vec![(1, 1, 0)]));
+        assert_eq!(stamped(3), (vec![(0, 2, 0)], vec![(0, 1, 0)]));
+        assert_eq!(stamped(4), stamped(0));
+    }
+}
@@ -0,0 +1,139 @@
+// Comment une case de la grille tombe à l'écran, pour pouvoir regarder le niveau autrement
+
+use std::fmt::Debug;
+
+use ruscii::{spatial::Vec2, terminal::Color};
+
+use super::{Cell, Pos3, Vec3};
+
+/// Turns grid coordinates into screen positions.
+///
+/// The grid is drawn in index order and what's drawn last stays on screen,
+/// so a projection has to put the cells with the bigger index in front.
+pub trait Projection: Debug + Sync {
+    /// Tells projections apart, for the screen and for caching.
+    fn name(&self) -> &'static str;
+
+    fn project(&self, coord: Vec3) -> Vec2;
+
+    /// The step from a cell to the next one drawn at the same screen position, closer to the camera.
+    fn toward_camera(&self) -> Pos3;
+
+    /// How `cell` at `coord` looks in this view, `None` to draw nothing there.
+    fn glyph(&self, _coord: Vec3, cell: Cell) -> Option<(char, Color)> {
+        Some((cell.to_char(), cell.color()))
+    }
+}
+
+/// The normal view, x going down to the right, y down to the left and z up.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Isometric;
+
+impl Projection for Isometric {
+    fn name(&self) -> &'static str {
+        "isometric"
+    }
+
+    fn project(&self, coord: Vec3) -> Vec2 {
+        let Pos3 { x, y, z } = coord.into();
+        let screen_x = (x - y) * 2;
+        let screen_y = (x + y) - z;
+        Vec2::xy(screen_x, screen_y)
+    }
+
+    fn toward_camera(&self) -> Pos3 {
+        Pos3::new(1, 1, 2)
+    }
+}
+
+/// Straight from above, each column showing its highest cell that isn't empty.
+/// The blocks show their height instead, mod 10, to tell the levels apart.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TopDown;
+
+impl Projection for TopDown {
+    fn name(&self) -> &'static str {
+        "top-down"
+    }
+
+    // les caractères sont deux fois plus hauts que larges
+    fn project(&self, coord: Vec3) -> Vec2 {
+        let Pos3 { x, y, .. } = coord.into();
+        Vec2::xy(x * 2, y)
+    }
+
+    fn toward_camera(&self) -> Pos3 {
+        Pos3::new(0, 0, 1)
+    }
+
+    // l'air ne cache rien vu d'en haut, sinon on ne verrait que la couche du dessus
+    fn glyph(&self, (_, _, z): Vec3, cell: Cell) -> Option<(char, Color)> {
+        match cell {
+            Cell::Empty => None,
+            Cell::Block => char::from_digit(z.rem_euclid(10) as u32, 10).map(|c| (c, cell.color())),
+            _ => Some((cell.to_char(), cell.color())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_cells_land_where_expected() {
+        let isometric = |coord| Isometric.project(coord);
+        assert_eq!(isometric((0, 0, 0)), Vec2::xy(0, 0));
+        assert_eq!(isometric((1, 0, 0)), Vec2::xy(2, 1));
+        assert_eq!(isometric((0, 1, 0)), Vec2::xy(-2, 1));
+        assert_eq!(isometric((0, 0, 1)), Vec2::xy(0, -1));
+        assert_eq!(isometric((3, 1, 2)), Vec2::xy(4, 2));
+
+        let top_down = |coord| TopDown.project(coord);
+        assert_eq!(top_down((0, 0, 0)), Vec2::xy(0, 0));
+        assert_eq!(top_down((3, 1, 2)), Vec2::xy(6, 1));
+        assert_eq!(
+            (Isometric.name(), TopDown.name()),
+            ("isometric", "top-down")
+        );
+    }
+
+    #[test]
+    fn the_step_toward_the_camera_stays_on_the_same_spot() {
+        let projections: [&dyn Projection; 2] = [&Isometric, &TopDown];
+        for projection in projections {
+            let coord = Pos3::new(2, 3, 1);
+            let closer = coord + projection.toward_camera();
+            assert_eq!(
+                projection.project(coord.into()),
+                projection.project(closer.into())
+            );
+        }
+    }
+
+    #[test]
+    fn top_down_keeps_x_and_y_and_shows_heights() {
+        // deux colonnes par case en x, une ligne par case en y, la hauteur ne compte pas
+        for z in 0..4 {
+            assert_eq!(TopDown.project((5, 7, z)), Vec2::xy(10, 7));
+        }
+        assert_eq!(
+            TopDown.glyph((0, 0, 3), Cell::Block),
+            Some(('3', Color::Yellow))
+        );
+        assert_eq!(
+            TopDown.glyph((0, 0, 12), Cell::Block).map(|(c, _)| c),
+            Some('2')
+        );
+        assert_eq!(TopDown.glyph((0, 0, 1), Cell::Empty), None);
+        assert_eq!(
+            TopDown.glyph((0, 0, 1), Cell::Food),
+            Some(('F', Color::Yellow))
+        );
+        // l'isométrique dessine tout, même l'air
+        assert_eq!(
+            Isometric.glyph((0, 0, 1), Cell::Empty),
+            Some((' ', Color::Yellow))
+        );
+    }
+}
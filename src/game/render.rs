@@ -0,0 +1,230 @@
+// Garde la dernière frame en mémoire pour savoir ce qui a changé d'une frame à l'autre
+
+use std::collections::HashMap;
+
+use ruscii::{drawing::Pencil, spatial::Vec2, terminal::Color};
+
+use super::{GameState, Grid, GridStorage, Vec3, View, projection::Projection};
+
+// what ends up at each screen position, the last thing drawn there hiding the others
+type Frame = HashMap<Vec2, (char, Color)>;
+
+// Où chaque case de la grille tombe à l'écran, rangé par index et refait seulement quand la taille
+// ou la projection changent
+#[derive(Debug, Default)]
+struct ScreenCache {
+    dimensions: Vec3,
+    projection: &'static str,
+    screen: Vec<Vec2>,
+}
+
+impl ScreenCache {
+    // a sparse grid is huge by definition, caching all of its positions would defeat the point
+    fn update(&mut self, grid: &Grid, projection: &dyn Projection) -> bool {
+        if !matches!(grid.storage, GridStorage::Dense(_)) {
+            self.screen = Vec::new();
+            return false;
+        }
+        if self.dimensions != grid.dimensions
+            || self.projection != projection.name()
+            || self.screen.is_empty()
+        {
+            self.dimensions = grid.dimensions;
+            self.projection = projection.name();
+            self.screen = (0..grid.volume())
+                .map(|idx| projection.project(grid.index_to_coord(idx)))
+                .collect();
+        }
+        true
+    }
+}
+
+/// Draws a game while remembering the previous frame: the grid is only walked again
+/// when it changed, and only the screen positions that differ from last frame count as drawn.
+/// The cells that blink are redrawn every frame over the board, they are always counted as drawn
+/// when their look changes.
+#[derive(Debug, Default)]
+pub struct Renderer {
+    board: Frame,                                               // the grid alone
+    board_for: Option<(u64, u64, Option<isize>, &'static str)>, // the grid and fog revisions, layer filter and projection it was made for
+    screen: ScreenCache,
+    animated: Vec<Vec3>, // where the cells of the board that blink are
+    last_frame: Frame,
+    drawn: usize,
+}
+
+impl Renderer {
+    /// Forgets everything so the next frame is redrawn from scratch (new game, window resized...).
+    pub fn invalidate(&mut self) {
+        *self = Self::default();
+    }
+
+    /// How many screen positions changed in the last frame.
+    pub fn drawn(&self) -> usize {
+        self.drawn
+    }
+
+    pub fn draw(&mut self, game: &GameState, pencil: &mut Pencil, view: View) {
+        let key = (
+            game.grid_revision,
+            game.fog_revision(),
+            view.layer,
+            view.projection.name(),
+        );
+        if self.board_for != Some(key) {
+            self.board.clear();
+            let board = &mut self.board;
+            let plot = &mut |screen, c, color| {
+                board.insert(screen, (c, color));
+            };
+            if self.screen.update(&game.grid, view.projection) {
+                let cache = &self.screen;
+                game.plot_grid(
+                    view,
+                    |coord| cache.screen[game.grid.coord_to_index(coord)],
+                    plot,
+                );
+            } else {
+                game.plot_grid(view, |coord| view.projection.project(coord), plot);
+            }
+            self.animated = game.animated_cells();
+            self.board_for = Some(key);
+        }
+        let mut frame = self.board.clone();
+        let plot = &mut |screen, c, color| {
+            frame.insert(screen, (c, color));
+        };
+        game.plot_animated(view, &self.animated, plot);
+        game.plot_overlays(view, plot);
+
+        self.drawn = diff(&self.last_frame, &frame);
+        // ruscii vide le canvas à chaque frame, donc il faut quand même tout recopier,
+        // mais c'est une case par position de l'écran et plus une par case de la grille
+        for (&screen, &(c, color)) in &frame {
+            pencil.set_foreground(color).draw_char(c, screen);
+        }
+        self.last_frame = frame;
+    }
+}
+
+// how many screen positions aren't the same in both frames, erased ones included
+fn diff(before: &Frame, after: &Frame) -> usize {
+    let changed = after
+        .iter()
+        .filter(|&(screen, drawn)| before.get(screen) != Some(drawn))
+        .count();
+    let erased = before
+        .keys()
+        .filter(|screen| !after.contains_key(screen))
+        .count();
+    changed + erased
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Cell, Direction, GameConfig, Isometric, StringCanvas, TopDown};
+
+    const SIZE: Vec2 = Vec2 { x: 8, y: 2 };
+
+    fn render(renderer: &mut Renderer, game: &GameState) -> StringCanvas {
+        let mut canvas = StringCanvas::new(SIZE);
+        let view = View {
+            projection: &TopDown,
+            ..View::default()
+        };
+        renderer.draw(game, &mut canvas.pencil(), view);
+        canvas
+    }
+
+    // ce que le Renderer devrait compter, en comparant les deux canvas case par case
+    fn changed(before: &StringCanvas, after: &StringCanvas) -> usize {
+        (0..SIZE.y)
+            .flat_map(|y| (0..SIZE.x).map(move |x| Vec2::xy(x, y)))
+            .filter(|&pos| {
+                (before.char_at(pos), before.color_at(pos))
+                    != (after.char_at(pos), after.color_at(pos))
+            })
+            .count()
+    }
+
+    #[test]
+    fn only_what_changed_counts_as_drawn() {
+        let grid: Grid = "WWWW\nWWWW\n\n...F\n....".parse().unwrap();
+        let mut game = GameState::new((0, 0, 1), grid, &GameConfig::default()).unwrap();
+        let mut renderer = Renderer::default();
+        let blank = StringCanvas::new(SIZE);
+        let first = render(&mut renderer, &game);
+        assert_eq!(renderer.drawn(), changed(&blank, &first));
+        assert_eq!(renderer.drawn(), 8);
+
+        let again = render(&mut renderer, &game);
+        assert_eq!(renderer.drawn(), 0);
+        assert_eq!(again.to_string(), first.to_string());
+
+        game.queue_input(Direction::South);
+        game.update().unwrap();
+        let moved = render(&mut renderer, &game);
+        assert_eq!(renderer.drawn(), changed(&again, &moved));
+        assert_eq!(renderer.drawn(), 2);
+
+        renderer.invalidate();
+        render(&mut renderer, &game);
+        assert_eq!(renderer.drawn(), 8);
+    }
+
+    #[test]
+    fn the_screen_cache_follows_the_grid_and_the_projection() {
+        let mut grid = Grid::empty((3, 2, 2));
+        let mut cache = ScreenCache::default();
+        let cached = |cache: &ScreenCache, grid: &Grid, projection: &dyn Projection| {
+            grid.iter().all(|(coord, _)| {
+                cache.screen[grid.coord_to_index(coord)] == projection.project(coord)
+            })
+        };
+        assert!(cache.update(&grid, &Isometric));
+        assert!(cached(&cache, &grid, &Isometric));
+
+        grid.resize((4, 3, 2), Cell::Empty).unwrap();
+        assert!(cache.update(&grid, &Isometric));
+        assert_eq!(cache.screen.len(), grid.volume());
+        assert!(cached(&cache, &grid, &Isometric));
+
+        assert!(cache.update(&grid, &TopDown));
+        assert!(cached(&cache, &grid, &TopDown));
+
+        // rien à garder pour une grille sparse
+        let sparse = grid.into_sparse();
+        assert!(!cache.update(&sparse, &TopDown));
+        assert!(cache.screen.is_empty());
+    }
+
+    #[test]
+    fn food_blinks_every_few_frames() {
+        let grid: Grid = "WWWW\nWWWW\n\n...F\n....".parse().unwrap();
+        let game = GameState::new((0, 0, 1), grid, &GameConfig::default()).unwrap();
+        let mut renderer = Renderer::default();
+        let mut draw = |frame| {
+            let mut canvas = StringCanvas::new(SIZE);
+            let view = View {
+                projection: &TopDown,
+                frame,
+                ..View::default()
+            };
+            renderer.draw(&game, &mut canvas.pencil(), view);
+            let food = Vec2::xy(6, 0);
+            (
+                canvas.char_at(food),
+                canvas.color_at(food),
+                renderer.drawn(),
+            )
+        };
+        let (c, color, _) = draw(0);
+        assert_eq!(c, Some('F'));
+        assert_ne!(color, Some(Color::Xterm(208)));
+        // rien n'a bougé mais la nourriture a changé d'aspect, elle seule est redessinée
+        assert_eq!(draw(5), (Some('f'), Some(Color::Xterm(208)), 1));
+        assert_eq!(draw(9), (Some('f'), Some(Color::Xterm(208)), 0));
+        assert_eq!(draw(10), (c, color, 1));
+    }
+}
@@ -0,0 +1,56 @@
+// Un petit générateur pseudo-aléatoire (xorshift64*), pour que la même graine redonne la même partie
+
+/// Deterministic random numbers, seeded once at the start of the game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift reste bloqué sur zéro
+        Self {
+            state: seed ^ 0x9e37_79b9_7f4a_7c15,
+        }
+        .warmed_up()
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number in `0..n`, `n` being more than zero.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// A number in `min..=max`.
+    pub fn between(&mut self, min: isize, max: isize) -> isize {
+        min + self.below((max - min + 1) as usize) as isize
+    }
+
+    /// The current state, a game saved with it picks the same numbers back up.
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn from_state(state: u64) -> Self {
+        Self {
+            state: state.max(1),
+        }
+    }
+
+    // the first few numbers after a small seed aren't very random
+    fn warmed_up(mut self) -> Self {
+        if self.state == 0 {
+            self.state = 1;
+        }
+        for _ in 0..4 {
+            self.next_u64();
+        }
+        self
+    }
+}
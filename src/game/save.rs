@@ -0,0 +1,411 @@
+// Sauvegarder une partie en cours pour la reprendre plus tard
+
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    Cell, CellMeta, Difficulty, Direction, GameConfig, GameState, Grid, GridError, Mob, Rng, Snake,
+    SpawnError, Vec3, Wrap, contains,
+};
+
+/// The version of the saves written now. It goes up when a save stops meaning the same thing
+/// to an older game, adding a field with a default doesn't need it.
+pub const SAVE_VERSION: u32 = 2;
+
+/// Everything needed to pick a game back up exactly where it was left.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveGame {
+    #[serde(default = "first_version")] // saves from before there was one
+    version: u32,
+    dimensions: Vec3,
+    cells: Vec<Cell>,
+    #[serde(default)]
+    meta: Vec<(Vec3, CellMeta)>,
+    body: Vec<Vec3>, // from the head to the tail
+    direction: Direction,
+    inputs: Vec<Direction>,
+    score: u32,
+    #[serde(default)]
+    eaten: u32,
+    tick: u32,
+    coyote_ticks: u32,
+    ticks_airborne: u32,
+    jumping: bool,
+    sliding: bool,
+    #[serde(default)]
+    conveyed: Option<Direction>,
+    #[serde(default)]
+    pending_growth: usize,
+    #[serde(default)] // saves made before endless mode existed
+    endless: bool,
+    #[serde(default)]
+    keys: BTreeSet<u8>,
+    #[serde(default)]
+    time_limit: Option<u32>,
+    #[serde(default)]
+    shrink_every: Option<u32>,
+    #[serde(default)]
+    expanding: bool,
+    #[serde(default)]
+    loops: bool,
+    #[serde(default)]
+    streak: Option<u32>,
+    #[serde(default)]
+    ticks_since_eat: Option<u32>,
+    #[serde(default = "no_multiplier")]
+    multiplier: u32,
+    #[serde(default)]
+    trail: bool,
+    #[serde(default)]
+    wrap: Wrap,
+    #[serde(default)]
+    fog: Option<u32>,
+    #[serde(default)]
+    dark: bool,
+    #[serde(default)]
+    explored: Vec<Vec3>,
+    #[serde(default)]
+    rng: u64,
+    #[serde(default)]
+    mobs: Vec<Mob>,
+    #[serde(default)]
+    difficulty: Difficulty,
+    // saves from before these were kept get the ones of their difficulty
+    #[serde(default)]
+    foods: Option<usize>,
+    #[serde(default)]
+    poison_percent: Option<u32>,
+    #[serde(default)]
+    reverse_guard: Option<bool>,
+    #[serde(default)]
+    speedup_percent: Option<u32>,
+    #[serde(default)]
+    max_speedup_percent: Option<u32>,
+    #[serde(default)]
+    starting_length: Option<usize>,
+    #[serde(default)]
+    practice: bool,
+    #[serde(default)]
+    ticks_per_second: Option<u32>,
+    #[serde(default)]
+    played: Option<Duration>,
+}
+
+impl SaveGame {
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        self.write_to(fs::File::create(path)?)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        Self::read_from(fs::File::open(path)?)
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> Result<(), SaveError> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| SaveError::Format(err.to_string()))?;
+        writer.write_all(text.as_bytes())?;
+        Ok(())
+    }
+
+    /// Refuses saves made by a newer version of the game, older ones fill in the defaults.
+    pub fn read_from(mut reader: impl Read) -> Result<Self, SaveError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let save: Self = ron::from_str(&text).map_err(|err| SaveError::Format(err.to_string()))?;
+        if save.version > SAVE_VERSION {
+            return Err(SaveError::TooNew(save.version));
+        }
+        Ok(save)
+    }
+}
+
+impl GameState {
+    /// Only the first player is saved, a two player game can't be picked back up.
+    pub fn to_save(&self) -> SaveGame {
+        let player = &self.players[0];
+        // sorted so the same game is always written out the same way
+        let mut meta: Vec<(Vec3, CellMeta)> = self.grid.meta.clone().into_iter().collect();
+        meta.sort_by_key(|&(coord, _)| coord);
+        SaveGame {
+            version: SAVE_VERSION,
+            dimensions: self.grid.dimensions,
+            cells: self.grid.iter().map(|(_, cell)| cell).collect(),
+            meta,
+            body: player.snake.body.iter().copied().collect(),
+            direction: player.snake.direction,
+            inputs: player.inputs.iter().copied().collect(),
+            score: player.score,
+            eaten: player.eaten,
+            tick: self.tick,
+            coyote_ticks: self.coyote_ticks,
+            ticks_airborne: player.ticks_airborne,
+            jumping: player.jumping,
+            sliding: player.sliding,
+            conveyed: player.conveyed,
+            pending_growth: player.snake.pending_growth,
+            endless: self.endless,
+            keys: self.keys.clone(),
+            time_limit: self.time_limit,
+            shrink_every: self.shrink_every,
+            expanding: self.expanding,
+            loops: self.loops,
+            streak: self.streak,
+            ticks_since_eat: player.ticks_since_eat,
+            multiplier: player.multiplier,
+            trail: self.trail,
+            wrap: self.grid.wrap(),
+            fog: self
+                .visibility
+                .as_ref()
+                .map(|visibility| visibility.radius()),
+            dark: self
+                .visibility
+                .as_ref()
+                .is_some_and(|visibility| visibility.is_dark()),
+            explored: self
+                .visibility
+                .as_ref()
+                .map(|visibility| visibility.explored())
+                .unwrap_or_default(),
+            rng: self.rng.state(),
+            mobs: self.mobs.clone(),
+            difficulty: self.difficulty,
+            foods: Some(self.foods),
+            poison_percent: Some(self.poison_percent),
+            reverse_guard: Some(self.reverse_guard),
+            speedup_percent: Some(self.speedup_percent),
+            max_speedup_percent: Some(self.max_speedup_percent),
+            starting_length: Some(self.starting_length),
+            practice: self.history.is_some(),
+            ticks_per_second: Some(self.ticks_per_second),
+            played: Some(self.played),
+        }
+    }
+
+    /// Writes the whole game to `writer`, see [`GameState::to_save`].
+    pub fn save(&self, writer: impl Write) -> Result<(), SaveError> {
+        self.to_save().write_to(writer)
+    }
+
+    /// Reads back a game written by [`GameState::save`].
+    pub fn load(reader: impl Read) -> Result<Self, SaveError> {
+        Self::from_save(SaveGame::read_from(reader)?)
+    }
+
+    /// Rebuilds a game from a save, refusing saves that don't make sense.
+    pub fn from_save(save: SaveGame) -> Result<Self, SaveError> {
+        let mut grid = Grid::new(save.dimensions, save.cells)?;
+        for (coord, meta) in save.meta {
+            grid.set_meta(coord, meta)?;
+        }
+        let Some(&head) = save.body.first() else {
+            return Err(SaveError::NoSnake);
+        };
+        for &segment in &save.body {
+            if !contains(segment, grid.dimensions) {
+                return Err(SaveError::SegmentOutOfBounds(segment));
+            }
+            if grid.get(segment).is_none_or(Cell::is_solid) {
+                return Err(SaveError::SegmentInWall(segment));
+            }
+        }
+
+        let preset = save.difficulty.to_config();
+        let config = GameConfig {
+            coyote_ticks: save.coyote_ticks,
+            endless: save.endless,
+            time_limit: save.time_limit,
+            shrink_every: save.shrink_every,
+            expanding: save.expanding,
+            loops: save.loops,
+            streak: save.streak,
+            trail: save.trail,
+            wrap: save.wrap,
+            fog: save.fog,
+            dark: save.dark,
+            foods: save.foods.unwrap_or(preset.foods),
+            poison_percent: save.poison_percent.unwrap_or(preset.poison_percent),
+            reverse_guard: save.reverse_guard.unwrap_or(preset.reverse_guard),
+            speedup_percent: save.speedup_percent.unwrap_or(preset.speedup_percent),
+            max_speedup_percent: save
+                .max_speedup_percent
+                .unwrap_or(preset.max_speedup_percent),
+            starting_length: save.starting_length.unwrap_or(preset.starting_length),
+            practice: save.practice,
+            ticks_per_second: save.ticks_per_second.unwrap_or(preset.ticks_per_second),
+            ..preset
+        };
+        let mut game = GameState::new(head, grid, &config)?;
+        let player = &mut game.players[0];
+        player.snake = Snake::new(save.direction, VecDeque::from(save.body));
+        player.snake.pending_growth = save.pending_growth;
+        player.inputs = VecDeque::from(save.inputs);
+        player.score = save.score;
+        player.eaten = save.eaten;
+        player.ticks_since_eat = save.ticks_since_eat;
+        player.multiplier = save.multiplier;
+        player.ticks_airborne = save.ticks_airborne;
+        player.jumping = save.jumping;
+        player.sliding = save.sliding;
+        player.conveyed = save.conveyed;
+        game.tick = save.tick;
+        // sans ce qui a été vraiment joué, au rythme de base
+        game.played = save
+            .played
+            .unwrap_or(Duration::from_secs(1) / game.ticks_per_second * save.tick);
+        game.rng = Rng::from_state(save.rng);
+        game.keys = save.keys;
+        game.mobs = save.mobs;
+        if let Some(visibility) = &mut game.visibility {
+            visibility.set_explored(save.explored);
+        }
+        game.look_around();
+        Ok(game)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("Could not access the save: {0}")]
+    Io(#[from] io::Error),
+    #[error("The save is not readable: {0}")]
+    Format(String),
+    #[error("The save is from a newer version of the game (save version {0})")]
+    TooNew(u32),
+    #[error("The grid in the save is broken: {0}")]
+    Grid(#[from] GridError),
+    #[error("The save has no snake in it")]
+    NoSnake,
+    #[error("The snake in the save is out of the grid at {0:?}")]
+    SegmentOutOfBounds(Vec3),
+    #[error("The snake in the save is inside a wall at {0:?}")]
+    SegmentInWall(Vec3),
+    #[error(transparent)]
+    Spawn(#[from] SpawnError),
+}
+
+// UTILS
+
+fn first_version() -> u32 {
+    1
+}
+
+fn no_multiplier() -> u32 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 6x6, a food in the middle and the snake in a corner, the food coming back once eaten
+    fn arena() -> GameState {
+        let floor = ["WWWWWW"; 6].join("\n");
+        let above = ["......", "......", "......", "...F..", "......", "......"].join("\n");
+        let grid: Grid = format!("{floor}\n\n{above}").parse().unwrap();
+        let config = GameConfig {
+            endless: true,
+            streak: Some(4),
+            ..GameConfig::default()
+        };
+        GameState::new((0, 0, 1), grid, &config).unwrap()
+    }
+
+    // what the game looks like after each input
+    fn play(game: &mut GameState, inputs: &[Direction]) -> Vec<(bool, SaveGame)> {
+        inputs
+            .iter()
+            .map(|&dir| {
+                game.queue_input(dir);
+                let alive = game.update().is_ok();
+                (alive, game.to_save())
+            })
+            .collect()
+    }
+
+    fn reloaded(game: &GameState) -> GameState {
+        let mut bytes = Vec::new();
+        game.save(&mut bytes).unwrap();
+        GameState::load(bytes.as_slice()).unwrap()
+    }
+
+    const NORTH: Direction = Direction::North;
+    const SOUTH: Direction = Direction::South;
+    const EAST: Direction = Direction::East;
+    const WEST: Direction = Direction::West;
+
+    #[test]
+    fn a_reloaded_game_goes_on_like_the_original() {
+        let mut game = arena();
+        play(&mut game, &[SOUTH, SOUTH, SOUTH, EAST, EAST, EAST, EAST]);
+        assert_eq!(game.foods_eaten(), 1);
+        let mut resumed = reloaded(&game);
+
+        let rest = [
+            EAST, SOUTH, SOUTH, WEST, WEST, WEST, NORTH, NORTH, NORTH, NORTH, EAST, EAST, EAST,
+        ];
+        assert_eq!(play(&mut resumed, &rest), play(&mut game, &rest));
+    }
+
+    #[test]
+    fn a_save_in_the_middle_of_a_run_reads_back_the_same() {
+        let mut game = arena();
+        for x in 0..6 {
+            let meta = CellMeta {
+                label: Some(format!("case {x}")),
+                value: Some(x as i32),
+            };
+            game.grid.set_meta((x, 5, 1), meta).unwrap();
+        }
+        play(
+            &mut game,
+            &[SOUTH, SOUTH, SOUTH, EAST, EAST, EAST, EAST, SOUTH],
+        );
+        let resumed = reloaded(&game);
+        assert_eq!(resumed.to_save(), game.to_save());
+        assert_eq!(resumed.score(), game.score());
+        assert_eq!(resumed.grid.meta((3, 5, 1)), game.grid.meta((3, 5, 1)));
+
+        // écrite deux fois, la même partie donne le même texte, les données des cases comprises
+        let written = |game: &GameState| {
+            let mut bytes = Vec::new();
+            game.save(&mut bytes).unwrap();
+            bytes
+        };
+        assert_eq!(written(&resumed), written(&game));
+    }
+
+    #[test]
+    fn the_settings_off_their_difficulty_come_back_too() {
+        let floor = ["WWWWWW"; 6].join("\n");
+        let above = ["......", "......", "......", "...F..", "......", "......"].join("\n");
+        let grid: Grid = format!("{floor}\n\n{above}").parse().unwrap();
+        let config = GameConfig {
+            endless: true,
+            foods: 3,
+            poison_percent: 30,
+            reverse_guard: false,
+            speedup_percent: 5,
+            max_speedup_percent: 100,
+            starting_length: 3,
+            practice: true,
+            ..GameConfig::default()
+        };
+        let mut game = GameState::new((0, 0, 1), grid, &config).unwrap();
+        play(&mut game, &[SOUTH, SOUTH, SOUTH, EAST, EAST, EAST, EAST]);
+        assert!(game.foods_eaten() > 0);
+
+        let resumed = reloaded(&game);
+        assert_eq!(resumed.to_save(), game.to_save());
+        assert_eq!(resumed.speedup(), game.speedup());
+        assert_eq!(resumed.speedup(), 5 * game.foods_eaten());
+    }
+}
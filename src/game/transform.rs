@@ -0,0 +1,219 @@
+// Tourner et retourner des grilles entières, pour servir un même niveau de plusieurs façons
+
+use serde::{Deserialize, Serialize};
+
+use super::{Cell, Direction, Grid, Vec3, Wrap, prefab::rotate_z};
+
+/// The axis a mirror flips: `Axis::X` swaps east and west, and so on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Direction {
+    /// Where this direction points once turned a quarter `turns` times around z, north going east.
+    pub fn rotated_z(self, turns: u8) -> Direction {
+        (0..turns % 4).fold(self, |dir, _| match dir {
+            Direction::North => Direction::East,
+            Direction::East => Direction::South,
+            Direction::South => Direction::West,
+            Direction::West => Direction::North,
+            dir => dir,
+        })
+    }
+
+    /// This direction seen in a mirror across `axis`.
+    pub fn mirrored(self, axis: Axis) -> Direction {
+        match (axis, self) {
+            (Axis::X, Direction::East | Direction::West)
+            | (Axis::Y, Direction::North | Direction::South)
+            | (Axis::Z, Direction::Up | Direction::Down) => self.opposite(),
+            _ => self,
+        }
+    }
+}
+
+impl Cell {
+    // the cells pointing somewhere turn with the grid, ids stay as they are
+    fn rotated_z(self, turns: u8) -> Cell {
+        match self {
+            Cell::Stairs(dir) => Cell::Stairs(dir.rotated_z(turns)),
+            Cell::Conveyor(dir) => Cell::Conveyor(dir.rotated_z(turns)),
+            Cell::Portal(id, dir) => Cell::Portal(id, dir.rotated_z(turns)),
+            cell => cell,
+        }
+    }
+
+    fn mirrored(self, axis: Axis) -> Cell {
+        match self {
+            Cell::Stairs(dir) => Cell::Stairs(dir.mirrored(axis)),
+            Cell::Conveyor(dir) => Cell::Conveyor(dir.mirrored(axis)),
+            Cell::Portal(id, dir) => Cell::Portal(id, dir.mirrored(axis)),
+            cell => cell,
+        }
+    }
+}
+
+impl Grid {
+    /// A copy turned a quarter `quarter_turns` times around z, x going to y:
+    /// the dimensions along x and y swap on odd turns.
+    pub fn rotated_z(&self, quarter_turns: u8) -> Grid {
+        let (mx, my, mz) = self.dimensions;
+        let dimensions = match quarter_turns % 2 {
+            0 => (mx, my, mz),
+            _ => (my, mx, mz),
+        };
+        let mut rotated = self.relocated(
+            dimensions,
+            |coord| rotate_z(coord, self.dimensions, quarter_turns),
+            |cell| cell.rotated_z(quarter_turns),
+        );
+        if quarter_turns % 2 == 1 {
+            let Wrap { x, y } = self.wrap;
+            rotated.wrap = Wrap { x: y, y: x };
+        }
+        rotated
+    }
+
+    /// A copy seen in a mirror across `axis`, the dimensions stay the same.
+    pub fn mirrored(&self, axis: Axis) -> Grid {
+        self.relocated(
+            self.dimensions,
+            |coord| mirror(coord, self.dimensions, axis),
+            |cell| cell.mirrored(axis),
+        )
+    }
+
+    // moves every cell and its meta to `to(coord)` in a grid of `dimensions`, changing it with `map`
+    fn relocated(
+        &self,
+        dimensions: Vec3,
+        to: impl Fn(Vec3) -> Vec3,
+        map: impl Fn(Cell) -> Cell,
+    ) -> Grid {
+        let mut moved = self
+            .blank(dimensions, Cell::Void)
+            .expect("the dimensions of a valid grid stay valid");
+        for (coord, cell) in self.stored() {
+            let target = to(coord);
+            moved[target] = map(cell);
+            if let Some(meta) = self.meta(coord) {
+                moved.meta.insert(target, meta.clone());
+            }
+        }
+        moved
+    }
+}
+
+/// Where `coord` ends up in a grid of `dimensions` seen in a mirror across `axis`.
+fn mirror((x, y, z): Vec3, (mx, my, mz): Vec3, axis: Axis) -> Vec3 {
+    match axis {
+        Axis::X => (mx - 1 - x, y, z),
+        Axis::Y => (x, my - 1 - y, z),
+        Axis::Z => (x, y, mz - 1 - z),
+    }
+}
+
+/// A mirror and then some quarter turns, what a level file asks for to be served another way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Transform {
+    #[serde(default)]
+    pub mirror: Option<Axis>,
+    #[serde(default)]
+    pub quarter_turns: u8,
+}
+
+impl Transform {
+    pub fn grid(self, grid: &Grid) -> Grid {
+        let mirrored = match self.mirror {
+            Some(axis) => grid.mirrored(axis),
+            None => grid.clone(),
+        };
+        mirrored.rotated_z(self.quarter_turns)
+    }
+
+    /// Where `dir` points once transformed.
+    pub fn direction(self, dir: Direction) -> Direction {
+        let mirrored = match self.mirror {
+            Some(axis) => dir.mirrored(axis),
+            None => dir,
+        };
+        mirrored.rotated_z(self.quarter_turns)
+    }
+
+    /// Where `coord` ends up in the transformed grid, `dimensions` being those before.
+    pub fn coord(self, coord: Vec3, dimensions: Vec3) -> Vec3 {
+        let mirrored = match self.mirror {
+            Some(axis) => mirror(coord, dimensions, axis),
+            None => coord,
+        };
+        rotate_z(mirrored, dimensions, self.quarter_turns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::CellMeta;
+
+    // trois sur deux, avec des cases qui pointent quelque part et de la donnée sur l'une d'elles
+    fn level() -> Grid {
+        let mut grid: Grid = "WWW\nWWV\n\n.<F\n..V".parse().unwrap();
+        grid[(0, 1, 1)] = Cell::Conveyor(Direction::East);
+        grid[(1, 1, 1)] = Cell::Portal(0, Direction::North);
+        grid.set_meta(
+            (2, 0, 1),
+            CellMeta {
+                value: Some(2),
+                ..CellMeta::default()
+            },
+        )
+        .unwrap();
+        grid
+    }
+
+    fn same(a: &Grid, b: &Grid) -> bool {
+        a.dimensions() == b.dimensions()
+            && a.diff(b).unwrap().is_empty()
+            && a.iter().all(|(coord, _)| a.meta(coord) == b.meta(coord))
+    }
+
+    #[test]
+    fn four_quarter_turns_go_all_the_way_round() {
+        let grid = level();
+        let turned = grid.rotated_z(1);
+        assert_eq!(turned.dimensions(), (2, 3, 2));
+        assert_eq!(
+            turned.get((0, 0, 1)),
+            Some(Cell::Conveyor(Direction::South))
+        );
+        assert_eq!(
+            turned.get((0, 1, 1)),
+            Some(Cell::Portal(0, Direction::East))
+        );
+        assert_eq!(turned.get((1, 1, 1)), Some(Cell::Stairs(Direction::Up)));
+        let all_round = (0..3).fold(turned, |grid, _| grid.rotated_z(1));
+        assert!(same(&all_round, &grid));
+        assert!(same(&grid.rotated_z(4), &grid));
+    }
+
+    #[test]
+    fn a_mirror_twice_is_no_mirror() {
+        let grid = level();
+        for axis in [Axis::X, Axis::Y, Axis::Z] {
+            let mirrored = grid.mirrored(axis);
+            assert!(!same(&mirrored, &grid));
+            assert!(same(&mirrored.mirrored(axis), &grid));
+        }
+        assert_eq!(
+            grid.mirrored(Axis::X).get((2, 1, 1)),
+            Some(Cell::Conveyor(Direction::West))
+        );
+        assert_eq!(
+            grid.mirrored(Axis::Z).get((1, 0, 0)),
+            Some(Cell::Stairs(Direction::Down))
+        );
+    }
+}
@@ -0,0 +1,352 @@
+use std::{cmp::Ordering, collections::BTreeMap, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::generate;
+use crate::game::{Cell, Difficulty, Grid, Mob, Patrol, Transform, Vec3, Wrap};
+
+/// Extension of the level files.
+pub const LEVEL_EXTENSION: &str = "ron";
+/// Levels can also be JSON, for the tools that prefer it.
+pub const JSON_EXTENSION: &str = "json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Level {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// How many moves the author thinks the level takes, to compare against when it's won.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub par_moves: Option<u32>,
+    pub grid: Grid,
+    pub start: Vec3,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mobs: Vec<Mob>,
+    // applied once when loading, what gets saved afterwards is the level as it was played
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<Transform>,
+}
+
+impl Level {
+    pub fn new(name: impl Into<String>, grid: Grid, start: Vec3) -> Self {
+        Self {
+            name: name.into(),
+            author: None,
+            par_moves: None,
+            grid,
+            start,
+            mobs: Vec::new(),
+            transform: None,
+        }
+    }
+
+    /// Le niveau de base : un sol plein, une bouffe au milieu et le serpent dans un coin
+    pub fn arena((max_x, max_y, max_z): Vec3) -> Self {
+        let mut grid = Grid::empty((max_x, max_y, max_z));
+        grid[(max_x / 2, max_y / 2, 1)] = Cell::Food;
+        for x in 0..max_x {
+            for y in 0..max_y {
+                grid[(x, y, 0)] = Cell::Block;
+            }
+        }
+        Self::new("Arena", grid, (0, 0, 1))
+    }
+
+    /// Where the expanding mode starts: a small floor with something to eat on it.
+    pub fn platform() -> Self {
+        let mut arena = Self::arena((5, 5, 3));
+        arena.name = "Platform".to_string();
+        arena
+    }
+
+    /// A level made up from `seed`, see [`generate::generate`].
+    pub fn generated(dimensions: Vec3, seed: u64, difficulty: Difficulty) -> Self {
+        Self::new(
+            format!("Generated {seed:016x} {}", difficulty.name()),
+            generate::generate(dimensions, seed, difficulty),
+            generate::SPAWN,
+        )
+    }
+
+    /// Reads a RON level, or a JSON one if the file ends in `.json`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LevelError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let level: Self = if is_json(path) {
+            Self::from_json(&text)?
+        } else {
+            ron::from_str(&text).map_err(|err| LevelError::Format(err.to_string()))?
+        };
+        Ok(match level.transform {
+            Some(transform) => level.transformed(transform),
+            None => level,
+        })
+    }
+
+    /// The same level mirrored and turned by `transform`, its start and mobs following.
+    pub fn transformed(&self, transform: Transform) -> Self {
+        let dimensions = self.grid.dimensions();
+        let mut level = Self::new(
+            self.name.clone(),
+            transform.grid(&self.grid),
+            transform.coord(self.start, dimensions),
+        );
+        level.author = self.author.clone();
+        level.par_moves = self.par_moves;
+        level.mobs = self
+            .mobs
+            .iter()
+            .map(|mob| {
+                let patrol = match mob.patrol {
+                    Patrol::Line(dir) => Patrol::Line(transform.direction(dir)),
+                    Patrol::Wander => Patrol::Wander,
+                };
+                Mob::new(transform.coord(mob.pos, dimensions), patrol)
+            })
+            .collect();
+        level
+    }
+
+    /// Writes the level as RON, or as JSON if the file ends in `.json`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), LevelError> {
+        let path = path.as_ref();
+        let text = if is_json(path) {
+            self.to_json()?
+        } else {
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+                .map_err(|err| LevelError::Format(err.to_string()))?
+        };
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Reads a level from JSON, the transform is left for [`Level::load`] to apply.
+    pub fn from_json(text: &str) -> Result<Self, LevelError> {
+        serde_json::from_str(text).map_err(|err| LevelError::Format(err.to_string()))
+    }
+
+    pub fn to_json(&self) -> Result<String, LevelError> {
+        serde_json::to_string_pretty(self).map_err(|err| LevelError::Format(err.to_string()))
+    }
+
+    /// The name as shown during a game, with the author when there is one.
+    pub fn title(&self) -> String {
+        match &self.author {
+            Some(author) => format!("{} by {author}", self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// How `moves` compares to the par of the level, nothing if it doesn't have one.
+    pub fn par_note(&self, moves: u32) -> Option<String> {
+        let par = self.par_moves?;
+        Some(match moves.cmp(&par) {
+            Ordering::Less => format!("{} under par", par - moves),
+            Ordering::Equal => "on par".to_string(),
+            Ordering::Greater => format!("{} over par", moves - par),
+        })
+    }
+
+    /// A stable hash of the whole level (FNV-1a), so a replay can tell it's being played on the right one.
+    pub fn fingerprint(&self) -> u64 {
+        const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+        let (mx, my, mz) = self.grid.dimensions();
+        let (sx, sy, sz) = self.start;
+        let header = [mx, my, mz, sx, sy, sz].map(|n| n as i64);
+        let bytes = header
+            .iter()
+            .flat_map(|n| n.to_le_bytes())
+            .chain(self.grid.iter().flat_map(|(_, cell)| cell_bytes(cell)))
+            .chain(self.mobs.iter().flat_map(|&mob| mob_bytes(mob)))
+            .chain(wrap_bytes(self.grid.wrap()));
+        bytes.fold(FNV_OFFSET, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        })
+    }
+
+    /// Checks that the level is actually playable, returns every problem found.
+    pub fn validate(&self) -> Vec<LevelIssue> {
+        let mut issues = Vec::new();
+
+        match self.grid.get(self.start) {
+            None => issues.push(LevelIssue::StartOutOfBounds(self.start)),
+            Some(Cell::Empty) => {
+                if !self.grid.cell_below(self.start).is_some_and(Cell::supports) {
+                    issues.push(LevelIssue::StartUnsupported(self.start));
+                }
+            }
+            Some(_) => issues.push(LevelIssue::StartObstructed(self.start)),
+        }
+
+        let reachable = self.grid.reachable(self.start);
+        for coord in self.grid.find_all(Cell::Food) {
+            if !self.grid.cell_below(coord).is_some_and(Cell::is_solid) {
+                issues.push(LevelIssue::FloatingFood(coord));
+            }
+            if !reachable.contains(&coord) {
+                issues.push(LevelIssue::UnreachableFood(coord));
+            }
+        }
+
+        // un portail mène à l'autre du même numéro, il en faut exactement deux
+        let mut portals: BTreeMap<u8, Vec<Vec3>> = BTreeMap::new();
+        for (coord, cell) in self.grid.iter_occupied() {
+            if let Cell::Portal(id, _) = cell {
+                portals.entry(id).or_default().push(coord);
+            }
+        }
+        for (id, coords) in portals {
+            if coords.len() != 2 {
+                issues.push(LevelIssue::UnpairedPortal(id, coords));
+            }
+        }
+
+        issues
+    }
+}
+
+// the cells that were there first keep their single byte, so older replays still recognize their level
+fn cell_bytes(cell: Cell) -> impl Iterator<Item = u8> {
+    let (tag, payload) = match cell {
+        Cell::Void => (0, [None, None]),
+        Cell::Empty => (1, [None, None]),
+        Cell::Block => (2, [None, None]),
+        Cell::Food => (3, [None, None]),
+        Cell::Spike => (4, [None, None]),
+        Cell::Ice => (5, [None, None]),
+        Cell::Key(id) => (6, [Some(id), None]),
+        Cell::Door(id) => (7, [Some(id), None]),
+        Cell::Stairs(dir) => (8, [Some(dir as u8), None]),
+        Cell::Conveyor(dir) => (9, [Some(dir as u8), None]),
+        Cell::Portal(id, dir) => (10, [Some(id), Some(dir as u8)]),
+        Cell::Crate => (11, [None, None]),
+        Cell::Crumble => (12, [None, None]),
+        Cell::Poison => (13, [None, None]),
+    };
+    std::iter::once(tag).chain(payload.into_iter().flatten())
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == JSON_EXTENSION)
+}
+
+// nothing for a level that doesn't wrap, it keeps the hash it had before wrapping existed
+fn wrap_bytes(wrap: Wrap) -> Option<u8> {
+    (!wrap.is_none()).then_some(wrap.x as u8 | (wrap.y as u8) << 1)
+}
+
+// after the cells, so a level without mobs keeps the hash it had before them
+fn mob_bytes(mob: Mob) -> impl Iterator<Item = u8> {
+    let (x, y, z) = mob.pos;
+    let patrol = match mob.patrol {
+        Patrol::Line(dir) => dir as u8,
+        Patrol::Wander => u8::MAX,
+    };
+    [x, y, z]
+        .into_iter()
+        .flat_map(|n| (n as i64).to_le_bytes())
+        .chain(std::iter::once(patrol))
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LevelIssue {
+    #[error("Starting position {0:?} is outside the level")]
+    StartOutOfBounds(Vec3),
+    #[error("Starting position {0:?} is not empty")]
+    StartObstructed(Vec3),
+    #[error("Starting position {0:?} has no block under it")]
+    StartUnsupported(Vec3),
+    #[error("Food at {0:?} is floating with nothing under it")]
+    FloatingFood(Vec3),
+    #[error("Food at {0:?} can't be reached from the start")]
+    UnreachableFood(Vec3),
+    #[error("Portal {0} needs exactly two ends but is at {1:?}")]
+    UnpairedPortal(u8, Vec<Vec3>),
+}
+
+#[derive(Debug, Error)]
+pub enum LevelError {
+    #[error("Could not access the level: {0}")]
+    Io(#[from] io::Error),
+    #[error("The level is not readable: {0}")]
+    Format(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Direction;
+
+    #[test]
+    fn stairs_hold_up_the_start() {
+        let on_stairs = |below| {
+            let mut grid: Grid = "WWW\n\nW.W\n\n..F".parse().unwrap();
+            grid[(1, 0, 1)] = below;
+            Level::new("stairs", grid, (1, 0, 2)).validate()
+        };
+        assert_eq!(on_stairs(Cell::Stairs(Direction::Up)), []);
+        assert_eq!(on_stairs(Cell::Block), []);
+        assert_eq!(
+            on_stairs(Cell::Empty),
+            [LevelIssue::StartUnsupported((1, 0, 2))]
+        );
+    }
+
+    #[test]
+    fn the_author_and_par_are_read_from_the_header() {
+        let level = Level {
+            author: Some("Absobel".to_string()),
+            par_moves: Some(12),
+            ..Level::new("Couloir", "W\n\nF".parse().unwrap(), (0, 0, 1))
+        };
+        let text = ron::ser::to_string(&level).unwrap();
+        let read: Level = ron::from_str(&text).unwrap();
+        assert_eq!(read.title(), "Couloir by Absobel");
+        assert_eq!(read.par_moves, Some(12));
+        assert_eq!(read.par_note(9).as_deref(), Some("3 under par"));
+        assert_eq!(read.par_note(12).as_deref(), Some("on par"));
+        assert_eq!(read.par_note(20).as_deref(), Some("8 over par"));
+
+        // les niveaux d'avant n'ont ni l'un ni l'autre
+        let old = text
+            .replace("author:Some(\"Absobel\"),", "")
+            .replace("par_moves:Some(12),", "");
+        assert_ne!(old, text);
+        let read: Level = ron::from_str(&old).unwrap();
+        assert_eq!(read.title(), "Couloir");
+        assert_eq!(read.par_note(9), None);
+    }
+
+    #[test]
+    fn food_behind_a_wall_is_unreachable() {
+        let level = |start| Level::new("wall", "WWWWW\n\n..W.F".parse().unwrap(), start);
+        assert_eq!(
+            level((0, 0, 1)).validate(),
+            [LevelIssue::UnreachableFood((4, 0, 1))]
+        );
+        assert_eq!(level((3, 0, 1)).validate(), []);
+    }
+
+    #[test]
+    fn a_portal_without_its_pair_is_reported() {
+        let mut grid: Grid = "WWWWW\n\n....F".parse().unwrap();
+        grid[(1, 0, 1)] = Cell::Portal(0, Direction::None);
+        assert_eq!(
+            Level::new("portal", grid.clone(), (0, 0, 1)).validate(),
+            [
+                LevelIssue::UnreachableFood((4, 0, 1)),
+                LevelIssue::UnpairedPortal(0, vec![(1, 0, 1)]),
+            ]
+        );
+        grid[(3, 0, 1)] = Cell::Portal(0, Direction::None);
+        assert_eq!(Level::new("portal", grid.clone(), (0, 0, 1)).validate(), []);
+        // un troisième ne sait plus où mener
+        grid[(2, 0, 1)] = Cell::Portal(0, Direction::East);
+        assert!(Level::new("portal", grid, (0, 0, 1)).validate().contains(
+            &LevelIssue::UnpairedPortal(0, vec![(1, 0, 1), (2, 0, 1), (3, 0, 1)])
+        ));
+    }
+}
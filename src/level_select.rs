@@ -0,0 +1,145 @@
+use std::{fs, path::Path};
+
+use ruscii::{drawing::Pencil, spatial::Vec2, terminal::Color};
+
+use crate::game::{Difficulty, Vec3};
+use crate::level::{JSON_EXTENSION, LEVEL_EXTENSION, Level};
+use crate::scores::{self, BestTimes};
+
+// Une ligne de la liste, un niveau qui n'a pas pu être lu reste affiché avec son erreur
+#[derive(Debug)]
+struct LevelEntry {
+    name: String,
+    level: Result<Level, String>,
+}
+
+/// The screen to pick a level from, among the built-in ones and those found in a directory.
+#[derive(Debug)]
+pub struct LevelSelect {
+    entries: Vec<LevelEntry>,
+    selected: usize,
+    scroll: usize, // index of the first visible entry
+}
+
+impl LevelSelect {
+    /// Lists `builtins` first, then every level file in `dir` sorted by file name.
+    /// A missing directory just means no extra levels.
+    pub fn scan(dir: impl AsRef<Path>, builtins: Vec<Level>) -> Self {
+        let mut entries: Vec<LevelEntry> = builtins
+            .into_iter()
+            .map(|level| LevelEntry {
+                name: level.name.clone(),
+                level: Ok(level),
+            })
+            .collect();
+
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == LEVEL_EXTENSION || ext == JSON_EXTENSION)
+            })
+            .collect();
+        paths.sort();
+        entries.extend(paths.into_iter().map(|path| {
+            let file_name = path.file_stem().unwrap_or_default().to_string_lossy();
+            match Level::load(&path) {
+                Ok(level) => LevelEntry {
+                    name: level.name.clone(),
+                    level: Ok(level),
+                },
+                Err(err) => LevelEntry {
+                    name: file_name.into_owned(),
+                    level: Err(err.to_string()),
+                },
+            }
+        }));
+
+        Self {
+            entries,
+            selected: 0,
+            scroll: 0,
+        }
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        self.selected = (self.selected + 1).min(self.entries.len().saturating_sub(1));
+    }
+
+    /// The highlighted level, `None` if it couldn't be loaded.
+    pub fn selected(&self) -> Option<&Level> {
+        self.entries.get(self.selected)?.level.as_ref().ok()
+    }
+
+    pub fn draw(
+        &mut self,
+        pencil: &mut Pencil,
+        size: Vec2,
+        best_times: &BestTimes,
+        difficulty: Difficulty,
+    ) {
+        let rows = (size.y as usize).saturating_sub(5).max(1);
+        // keep the selection on screen
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + rows {
+            self.scroll = self.selected + 1 - rows;
+        }
+
+        pencil.set_foreground(Color::White).draw_text(
+            &format!(
+                "Choose a level (Enter to play, D for difficulty: {}, A for achievements, Esc to quit)",
+                difficulty.name()
+            ),
+            Vec2::xy(2, 1),
+        );
+        for (row, (index, entry)) in self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(rows)
+            .enumerate()
+        {
+            let marker = if index == self.selected { '>' } else { ' ' };
+            let (color, details) = match &entry.level {
+                Ok(level) => {
+                    let (x, y, z): Vec3 = level.grid.dimensions();
+                    let key = BestTimes::level_key(&level.name, level.fingerprint());
+                    let best = best_times
+                        .get(&key)
+                        .map_or("--:--.-".to_string(), scores::format_time);
+                    let par = level
+                        .par_moves
+                        .map_or(String::new(), |par| format!("  par {par}"));
+                    (Color::White, format!("{x}x{y}x{z}  best {best}{par}"))
+                }
+                Err(_) => (Color::DarkGrey, "unreadable".to_string()),
+            };
+            let color = if index == self.selected && color == Color::White {
+                Color::Yellow
+            } else {
+                color
+            };
+            pencil.set_foreground(color).draw_text(
+                &format!("{marker} {:<24} {details}", entry.name),
+                Vec2::xy(2, 3 + row as i32),
+            );
+        }
+
+        if let Some(LevelEntry {
+            level: Err(err), ..
+        }) = self.entries.get(self.selected)
+        {
+            pencil
+                .set_foreground(Color::Red)
+                .draw_text(err, Vec2::xy(2, size.y - 1));
+        }
+    }
+}
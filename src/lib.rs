@@ -0,0 +1,2 @@
+// Le jeu lui-même, sans le terminal ni les menus, pour que les tests d'intégration y aient accès
+pub mod game;
@@ -1,8 +1,49 @@
-#![feature(let_chains)]
+mod achievements;
+mod bell;
+mod bindings;
+mod bot;
+mod cli;
+mod countdown;
+mod daily;
+mod demo;
+mod editor;
+mod effects;
+mod level;
+mod level_select;
+mod message_log;
+mod replay;
+mod scores;
+mod stats;
+mod timestep;
 
-mod game;
+use svnake::game;
 
-use game::Draw;
+use achievements::{Progress, Unlocked};
+use bell::Bell;
+use bindings::{Action, Bindings};
+use bot::Rival;
+use countdown::Countdown;
+use demo::Demo;
+use editor::Editor;
+use effects::Effects;
+use game::{
+    Cell, Direction, GameConfig, GameEvent, GameState, Isometric, PLAYER_COLORS, Projection,
+    Renderer, SaveGame, TopDown, View,
+};
+use level::Level;
+use level_select::LevelSelect;
+use message_log::MessageLog;
+use replay::{DailyTag, Playback, Replay};
+use scores::{BestTimes, ScoreEntry, ScoreTable};
+use stats::RunStats;
+use timestep::FixedTimestep;
+
+use std::{
+    io::{self, Write},
+    path::Path,
+    process::ExitCode,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use ruscii::app::{App, Config, State};
 use ruscii::drawing::Pencil;
@@ -13,51 +54,1020 @@ use ruscii::spatial::Vec2;
 use ruscii::terminal::Color;
 use ruscii::terminal::Window;
 
-fn main() {
-    let mut app = App::config(Config::new().fps(20));
+/// Under this much time left in time attack, the countdown turns red.
+const HURRY_UP: Duration = Duration::from_secs(10);
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut args = match cli::parse(&args) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n\n{}", cli::USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let mut config = args.config.clone();
+    // before the terminal is taken over, so the error stays readable
+    let level_file = match args.level.as_ref().map(|path| (path, Level::load(path))) {
+        Some((_, Ok(level))) => Some(level),
+        Some((path, Err(err))) => {
+            eprintln!("{}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+    let replay = match args.replay.map(Replay::load) {
+        Some(Ok(replay)) => Some(replay),
+        Some(Err(err)) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+    // un replay se rejoue avec les réglages de son enregistrement, pas ceux passés cette fois
+    if let Some(replay) = &replay {
+        replay.apply_to(&mut config);
+    }
+    let mut app = App::config(Config::new().fps(config.fps));
     let size = app.window().size() - Vec2::xy(1, 1);
+    // counts rendered frames, not game ticks, those are handled by the timestep
     let mut fps_counter = FPSCounter::default();
+    let mut timestep = FixedTimestep::new(config.ticks_per_second);
+
+    let mut message = None;
+    let best_times_path = BestTimes::default_path();
+    let mut best_times = match best_times_path.as_ref().map(BestTimes::load) {
+        Some(Ok(best_times)) => best_times,
+        Some(Err(err)) => {
+            message = Some(format!("{err}, forgetting the best times"));
+            BestTimes::default()
+        }
+        None => BestTimes::default(),
+    };
+    let unlocked_path = Unlocked::default_path();
+    let mut unlocked = match unlocked_path.as_ref().map(Unlocked::load) {
+        Some(Ok(unlocked)) => unlocked,
+        Some(Err(err)) => {
+            message = Some(format!("{err}, starting over"));
+            Unlocked::default()
+        }
+        None => Unlocked::default(),
+    };
+    let bindings_path = Bindings::default_path();
+    let bindings = match bindings_path.as_ref().map(Bindings::load) {
+        Some(Ok(bindings)) => bindings,
+        Some(Err(err)) => {
+            message = Some(format!("{err}, using the default keys"));
+            Bindings::default()
+        }
+        None => Bindings::default(),
+    };
+    // the defaults are written out the first time, so there is a file to edit
+    if let Some(path) = bindings_path.filter(|path| !path.exists())
+        && let Err(err) = bindings.save(path)
+    {
+        message = Some(err.to_string());
+    }
 
     // dx  dy dz = 1
     let max_x = size.x as isize / 2;
     let max_y = max_x;
     let max_z = size.y as isize;
-    let mut grid = game::Grid::empty((max_x, max_y, max_z));
-    grid.set((max_x / 2, max_y / 2, 1), game::Cell::Food)
-        .unwrap();
-    for x in 0..max_x {
-        for y in 0..max_y {
-            grid.set((x, y, 0), game::Cell::Block).unwrap();
-        }
+    let dimensions = config.dimensions.unwrap_or((max_x, max_y, max_z));
+    let arena = Level::arena(dimensions);
+    if let Some(path) = &args.edit {
+        let mut editor = match Editor::open(path, arena) {
+            Ok(editor) => editor,
+            Err(err) => {
+                eprintln!("{}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        };
+        app.run(|app_state: &mut State, window: &mut Window| {
+            for key_event in app_state.keyboard().last_key_events() {
+                match key_event {
+                    KeyEvent::Pressed(Key::Esc) => app_state.stop(),
+                    KeyEvent::Pressed(key) => editor.press(*key),
+                    _ => (),
+                }
+            }
+            editor.draw(&mut Pencil::new(window.canvas_mut()), size);
+        });
+        return ExitCode::SUCCESS;
     }
-    let mut game = game::GameState::new((0, 0, 1), grid);
 
-    app.run(|app_state: &mut State, window: &mut Window| {
-        for key_event in app_state.keyboard().last_key_events() {
-            if let KeyEvent::Pressed(Key::Esc) = key_event {
-                app_state.stop()
+    // une partie du jour rejouée se fait sur le niveau de son jour à elle, pas d'aujourd'hui
+    let daily_date = match &replay {
+        Some(replay) => replay.daily.as_ref().map(|daily| daily.date.clone()),
+        None => args.daily.then(daily::today),
+    };
+    if let Some(daily) = replay.as_ref().and_then(|replay| replay.daily.as_ref())
+        && daily.generator != game::generate::VERSION
+    {
+        eprintln!(
+            "This replay was played on a level from version {} of the generator, this is version {}",
+            daily.generator,
+            game::generate::VERSION
+        );
+        return ExitCode::FAILURE;
+    }
+
+    let mut generated = false;
+    let level = if let Some(date) = &daily_date {
+        args.seed = Some(daily::seed(date));
+        daily::level(date)
+    } else if let Some(level) = level_file {
+        level
+    } else if args.select {
+        let mut select = LevelSelect::scan(&args.levels_dir, vec![arena]);
+        let mut chosen = None;
+        let mut show_achievements = false;
+        let mut idle_frames = 0;
+        let mut demo: Option<Demo> = None;
+        let mut difficulty = config.difficulty;
+        app.run(|app_state: &mut State, window: &mut Window| {
+            let key_events = app_state.keyboard().last_key_events();
+            if key_events.is_empty() {
+                idle_frames += 1;
+            } else {
+                idle_frames = 0;
+            }
+            // pendant la démo n'importe quelle touche ramène au menu, et ne fait rien d'autre
+            if demo.is_some() {
+                if key_events
+                    .iter()
+                    .any(|key_event| matches!(key_event, KeyEvent::Pressed(_)))
+                {
+                    demo = None;
+                }
+            } else {
+                for key_event in key_events {
+                    match key_event {
+                        KeyEvent::Pressed(Key::Esc) => app_state.stop(),
+                        KeyEvent::Pressed(Key::A) => show_achievements = !show_achievements,
+                        KeyEvent::Pressed(Key::D) => difficulty = difficulty.next(),
+                        KeyEvent::Pressed(Key::Up) => select.up(),
+                        KeyEvent::Pressed(Key::Down) => select.down(),
+                        KeyEvent::Pressed(Key::Enter) if select.selected().is_some() => {
+                            chosen = select.selected().cloned();
+                            app_state.stop();
+                        }
+                        _ => (),
+                    }
+                }
+                if idle_frames >= demo::IDLE_SECONDS * config.fps {
+                    demo = Some(Demo::new(config.ticks_per_second));
+                }
             }
+            let mut pencil = Pencil::new(window.canvas_mut());
+            if let Some(playing) = &mut demo {
+                playing.advance();
+                playing.draw(&mut pencil, size);
+                if playing.is_over() {
+                    demo = None;
+                    idle_frames = 0;
+                }
+            } else if show_achievements {
+                unlocked.draw(&mut pencil);
+            } else {
+                select.draw(&mut pencil, size, &best_times, difficulty);
+            }
+        });
+        // ce qui a été passé en ligne de commande reste, sauf si on a choisi autre chose ici
+        if difficulty != config.difficulty {
+            difficulty.apply_to(&mut config);
+            timestep = FixedTimestep::new(config.ticks_per_second);
+        }
+        match chosen {
+            Some(level) => level,
+            None => return ExitCode::SUCCESS,
+        }
+    } else if args.generate || replay.as_ref().is_some_and(|replay| replay.generated) {
+        // the level comes from the seed, which then has to be known before everything else
+        let seed = match &replay {
+            Some(replay) => replay.seed,
+            None => *args.seed.get_or_insert_with(clock_seed),
+        };
+        generated = true;
+        Level::generated(dimensions, seed, config.difficulty)
+    } else if config.expanding {
+        Level::platform()
+    } else {
+        arena
+    };
+
+    let mut autopilot = args.bot;
+    let record_path = args.record;
+    let save_path = args.save;
+    let mut playback = match replay {
+        Some(replay) if replay.level_hash != level.fingerprint() => {
+            eprintln!("This replay was recorded on a different level");
+            return ExitCode::FAILURE;
+        }
+        replay => replay.map(Playback::new),
+    };
+    let issues = level.validate();
+    for issue in &issues {
+        eprintln!("{issue}");
+    }
+    if args.strict && !issues.is_empty() {
+        eprintln!("Refusing to start a broken level (--strict)");
+        return ExitCode::FAILURE;
+    }
+
+    // a replay has to be played with the seed it was recorded with
+    config.seed = match (&playback, args.seed) {
+        (Some(playback), _) => playback.seed(),
+        (None, Some(seed)) => seed,
+        (None, None) => clock_seed(),
+    };
+    // a replay being played back isn't recorded again
+    let mut recording = (record_path.is_some() && playback.is_none()).then(|| Replay {
+        daily: daily_date.clone().map(|date| DailyTag {
+            date,
+            generator: game::generate::VERSION,
+        }),
+        generated,
+        // the dimensions the level was made for, whatever the size of the terminal playing it back
+        ..Replay::new(
+            level.fingerprint(),
+            &GameConfig {
+                dimensions: Some(dimensions),
+                ..config.clone()
+            },
+        )
+    });
+    // undoing moves would make recorded runs meaningless, and the level growing can't be undone
+    config.practice &= recording.is_none() && !config.expanding;
+    // replays only know about one snake
+    config.two_players &= recording.is_none() && playback.is_none();
+    config.rival =
+        args.rival.is_some() && !config.two_players && recording.is_none() && playback.is_none();
+    config.practice &= !config.rival;
+    config.practice &= !config.two_players;
+    let versus = config.two_players;
+    let practice = config.practice;
+    let level_key = BestTimes::level_key(&level.name, level.fingerprint());
+    let title = format!("{} - {}", level.title(), config.difficulty.name());
+    let spawned = GameState::new(level.start, level.grid.clone(), &config).and_then(|mut game| {
+        for &mob in &level.mobs {
+            game.spawn_mob(mob)?;
+        }
+        Ok(game)
+    });
+    let mut game = match spawned {
+        Ok(game) => game,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    // ce qu'il s'est passé pendant les ticks d'une frame, pour les effets
+    game.enable_event_log();
+    let mut death = None;
+    let mut rival = args
+        .rival
+        .filter(|_| config.rival)
+        .map(|blunders| Rival::new(blunders, config.seed));
+
+    let scores_path = ScoreTable::default_path(config.difficulty);
+    let mut scores = match scores_path.as_ref().map(ScoreTable::load) {
+        Some(Ok(scores)) => scores,
+        Some(Err(err)) => {
+            message = Some(format!("{err}, starting a fresh table"));
+            ScoreTable::default()
         }
+        None => {
+            message = Some("No data directory, high scores won't be saved".to_string());
+            ScoreTable::default()
+        }
+    };
+    let mut game_over: Option<GameOver> = None;
 
-        let dir = app_state
-            .keyboard()
-            .last_key_events()
-            .iter()
-            .rev()
-            .find_map(|event| event.pressed())
-            .unwrap_or(Key::Unknown)
-            .into();
+    let mut completed = false;
+    let mut show_axes = false;
+    let mut paused = false;
+    let mut view = View::default();
+    let mut renderer = Renderer::default();
+    let mut effects = Effects::default();
+    let mut countdown = Some(Countdown::new(config.fps));
+    let mut log = MessageLog::new(config.fps);
+    let mut banners = MessageLog::new(config.fps);
+    // les événements ne disent pas de quel serpent ils parlent, et on ne triche pas
+    let mut progress = (!versus && !config.rival && !practice && playback.is_none())
+        .then(|| Progress::new(config.starting_length));
+    let mut stats =
+        (!versus && !config.rival && !practice).then(|| RunStats::new(config.starting_length));
+    let mut bell = args
+        .sound
+        .then(|| Bell::new((config.fps as usize / 6).max(1)));
+    let mut window_size = size;
+    let mut show_debug = false;
+    let mut show_coords = false;
 
+    app.run(|app_state: &mut State, window: &mut Window| {
+        if let Some(game_over) = game_over.as_mut().filter(|_| !effects.is_flashing()) {
+            for key_event in app_state.keyboard().last_key_events() {
+                match key_event {
+                    KeyEvent::Pressed(Key::Esc) => app_state.stop(),
+                    KeyEvent::Pressed(Key::Enter) => {
+                        if game_over.confirm(&mut scores).is_some()
+                            && let Some(path) = &scores_path
+                            && let Err(err) = scores.save(path)
+                        {
+                            message = Some(err.to_string());
+                        }
+                    }
+                    KeyEvent::Pressed(Key::Backspace) => {
+                        game_over.initials.pop();
+                    }
+                    KeyEvent::Pressed(key) => game_over.type_letter(*key),
+                    _ => (),
+                }
+            }
+        }
+        let actions = match game_over {
+            Some(_) => Vec::new(), // les initiales se tapent avec les touches brutes
+            None => bindings.actions(app_state.keyboard().last_key_events()),
+        };
+        if playback.is_none() && game_over.is_none() {
+            let held = app_state.keyboard().get_keys_down();
+            for player in 0..if versus { 2 } else { 1 } {
+                if (player == 0 && autopilot) || !game.inputs_empty(player) {
+                    continue;
+                }
+                if let Some(dir) = held_steering(&bindings, &actions, &held, player, versus) {
+                    game.queue_input_for(player, dir);
+                }
+            }
+        }
+        for action in actions {
+            match (action, &mut playback) {
+                (Action::Quit, _) => app_state.stop(),
+                (Action::Hint, _) => game.toggle_hint(),
+                (Action::Axes, _) => show_axes = !show_axes,
+                (Action::Debug, _) => show_debug = !show_debug,
+                (Action::Coords, _) => show_coords = !show_coords,
+                (Action::Peek, _) => {
+                    view.layer = match view.layer {
+                        Some(_) => None,
+                        None => Some(game.head().2),
+                    }
+                }
+                (Action::LayerUp, _) if view.layer.is_some() => {
+                    view.layer = view
+                        .layer
+                        .map(|z| (z + 1).min(game.grid().dimensions().2 - 1))
+                }
+                (Action::LayerDown, _) if view.layer.is_some() => {
+                    view.layer = view.layer.map(|z| (z - 1).max(0))
+                }
+                (Action::SeeThrough, _) => view.see_through = !view.see_through,
+                (Action::TrailFade, _) => view.trail_fade = !view.trail_fade,
+                (Action::Preview, _) => view.preview = !view.preview,
+                (Action::TopDown, _) => {
+                    view.projection = if view.projection.name() == TopDown.name() {
+                        &Isometric
+                    } else {
+                        &TopDown
+                    }
+                }
+                (Action::Faster, _) => timestep.faster(),
+                (Action::Slower, _) => timestep.slower(),
+                // en replay les touches du serpent pilotent la lecture
+                (Action::Jump, Some(playback)) => playback.paused = !playback.paused,
+                (Action::Move(Direction::West), Some(playback)) => playback.slower(),
+                (Action::Move(Direction::East), Some(playback)) => playback.faster(),
+                (_, Some(_)) => (), // pas de pilotage pendant un replay
+                (Action::Autopilot, None) => autopilot = !autopilot,
+                (Action::Pause, None) => {
+                    paused = !paused;
+                    // on recompte en repartant, le temps de retrouver où on en était
+                    countdown = (!paused).then(|| Countdown::new(config.fps));
+                }
+                (Action::Undo, None) if practice && game.undo() => {
+                    death = None;
+                    message = None;
+                    effects.clear();
+                }
+                (Action::Save, None) if versus || rival.is_some() => {
+                    message = Some("Can't save a two player game".to_string())
+                }
+                (Action::Save, None) => {
+                    message = Some(match game.to_save().write(&save_path) {
+                        Ok(()) => format!("Saved to {save_path}"),
+                        Err(err) => err.to_string(),
+                    })
+                }
+                (Action::Load, None) if recording.is_some() => {
+                    message = Some("Can't load a save while recording".to_string())
+                }
+                (Action::Load, None) => {
+                    match SaveGame::read(&save_path).and_then(GameState::from_save) {
+                        Ok(loaded) => {
+                            game = loaded;
+                            game.enable_event_log();
+                            rival = None;
+                            renderer.invalidate();
+                            effects.clear();
+                            log.clear();
+                            progress = None;
+                            stats = None;
+                            countdown = Some(Countdown::new(config.fps));
+                            if practice {
+                                game.enable_undo();
+                            }
+                            message = Some(format!("Loaded {save_path}"));
+                        }
+                        Err(err) => message = Some(err.to_string()),
+                    }
+                }
+                (Action::MoveAlt(dir), None) if versus => game.queue_input_for(1, dir),
+                (Action::Move(dir) | Action::MoveAlt(dir), None) if !autopilot => {
+                    game.queue_input(dir)
+                }
+                (Action::Jump, None) if !autopilot => game.queue_input(Direction::Up),
+                _ => (),
+            }
+        }
         fps_counter.update();
-        game.update(dir).expect("PERDU");
+        for _ in 0..timestep.tick() {
+            // en entraînement on attend que le joueur revienne en arrière
+            // et en pause ou pendant le compte à rebours le temps de jeu ne bouge pas,
+            // le chrono compte en ticks
+            if death.is_some()
+                || completed
+                || paused
+                || countdown.as_ref().is_some_and(Countdown::is_holding)
+            {
+                break;
+            }
+            if autopilot {
+                game.queue_input(bot::choose_direction(&game));
+            }
+            if let Some(rival) = &mut rival
+                && game.player_count() > Rival::PLAYER
+            {
+                game.queue_input_for(Rival::PLAYER, rival.choose_direction(&game));
+            }
+            if let Some(playback) = &mut playback {
+                for _ in 0..playback.ticks_this_step() {
+                    let Some(input) = playback.next_input() else {
+                        playback.end(false, game.score());
+                        break;
+                    };
+                    if game.step(input).is_err() {
+                        playback.end(true, game.score());
+                        break;
+                    }
+                }
+            } else {
+                let input = game.take_input();
+                if let Some(recording) = &mut recording {
+                    recording.record(input);
+                }
+                if let Err(err) = game.step(input) {
+                    if versus {
+                        message = Some(versus_result(&game));
+                    } else if practice {
+                        message = Some(format!("{err}, press U to undo"));
+                    } else {
+                        let entry =
+                            ScoreEntry::new(game.score(), game.snake_body().count(), &level.name);
+                        if let Some(date) = &daily_date {
+                            log.extend(record_daily(
+                                &mut scores,
+                                scores_path.as_deref(),
+                                date,
+                                entry.clone(),
+                            ));
+                        }
+                        game_over = Some(GameOver::new(&scores, entry));
+                    }
+                    death = Some(err);
+                    break;
+                }
+                if rival.is_some() && game.player_count() <= Rival::PLAYER {
+                    rival = None;
+                    message = Some("The rival is out, the food is all yours".to_string());
+                }
+                if game.is_complete() && versus {
+                    completed = true;
+                    message = Some(versus_result(&game));
+                } else if game.is_complete() {
+                    completed = true;
+                    if let Some(date) = &daily_date {
+                        let entry =
+                            ScoreEntry::new(game.score(), game.snake_body().count(), &level.name);
+                        log.extend(record_daily(
+                            &mut scores,
+                            scores_path.as_deref(),
+                            date,
+                            entry,
+                        ));
+                    }
+                    let time = game.time_played();
+                    let new_best = best_times.record(&level_key, time);
+                    // le serpent avance d'une case par tick
+                    let par = level.par_note(game.tick()).map_or(String::new(), |note| {
+                        format!(", {} moves {note}", game.tick())
+                    });
+                    message = Some(format!(
+                        "Level complete in {}{par}{}",
+                        scores::format_time(time),
+                        if new_best { ", NEW BEST" } else { "" }
+                    ));
+                    if new_best
+                        && let Some(path) = &best_times_path
+                        && let Err(err) = best_times.save(path)
+                    {
+                        message = Some(err.to_string());
+                    }
+                }
+            }
+        }
 
+        // repris de la partie à chaque frame, comme ça un undo ou un chargement ralentit aussi
+        timestep.set_ramp(game.speedup());
+        for (tick, event) in game.take_events() {
+            if let Some(bell) = &mut bell {
+                bell.hear(&event);
+            }
+            if let Some(stats) = &mut stats {
+                stats.observe(tick, &event);
+            }
+            for achievement in progress
+                .as_mut()
+                .map_or(Vec::new(), |progress| progress.observe(tick, &event))
+            {
+                if unlocked.unlock(achievement) {
+                    log.push(format!("Achievement unlocked: {}", achievement.name()));
+                    banners.push(format!("* {} *", achievement.name()));
+                    if let Some(path) = &unlocked_path
+                        && let Err(err) = unlocked.save(path)
+                    {
+                        message = Some(err.to_string());
+                    }
+                }
+            }
+            if let Some(text) = message_log::describe(&event) {
+                log.push(text);
+            }
+            match event {
+                GameEvent::Ate { coord } => effects.burst(view.projection.project(coord)),
+                GameEvent::Died(_) => effects.flash(
+                    (0..game.player_count())
+                        .flat_map(|player| game.snake_body_of(player))
+                        .map(|coord| view.projection.project(coord)),
+                    config.fps as usize / 2,
+                ),
+                _ => (),
+            }
+        }
+
+        if window.size() != window_size {
+            window_size = window.size();
+            renderer.invalidate();
+        }
         let mut pencil = Pencil::new(window.canvas_mut());
         let pencil = pencil
-            .set_origin(size / 4)
+            .set_origin(Vec2::zero())
             .set_foreground(Color::Grey)
             .draw_rect(&RectCharset::double_lines(), Vec2::zero(), size)
+            .draw_center_text(&format!(" {title} "), Vec2::xy(size.x / 2, 0))
             .set_foreground(Color::Yellow);
 
-        game.draw(pencil);
+        // le plateau a sa propre origine, le reste garde celle du cadre
+        pencil.set_origin(game.grid().board_origin_following(
+            size,
+            game.head(),
+            args.align,
+            view.projection,
+        ));
+        view.frame = app_state.step();
+        renderer.draw(&game, pencil, view);
+        effects.draw(pencil);
+        effects.advance();
+        if show_axes {
+            game::draw_axes(pencil, game.grid().dimensions(), view.projection);
+        }
+        pencil.set_origin(Vec2::zero());
+
+        if let Some(playback) = &playback {
+            let text = match playback.status() {
+                Some(status) => status.to_string(),
+                None if playback.paused => "REPLAY (paused)".to_string(),
+                None => format!("REPLAY x{}", playback.speed()),
+            };
+            pencil
+                .set_foreground(Color::White)
+                .draw_text(&text, Vec2::xy(1, 1));
+        } else if versus {
+            // chaque score dans la couleur de son serpent
+            let mut x = 1;
+            for (player, color) in PLAYER_COLORS
+                .into_iter()
+                .enumerate()
+                .take(game.player_count())
+            {
+                let text = format!("P{} {}", player + 1, game.score_of(player));
+                pencil
+                    .set_foreground(color)
+                    .draw_text(&text, Vec2::xy(x, 1));
+                x += text.len() as i32 + 2;
+            }
+        } else if let Some(best) = daily_date
+            .as_deref()
+            .and_then(|date| scores.daily_best(date))
+        {
+            pencil
+                .set_foreground(Color::White)
+                .draw_text(&format!("Today's best {}", best.score), Vec2::xy(1, 1));
+        }
+        // en replay c'est le temps de l'enregistrement, pas celui de l'horloge
+        let (time, color) = match game.remaining_ticks() {
+            Some(left) if game.tick_length() * left < HURRY_UP => {
+                (game.tick_length() * left, Color::Red)
+            }
+            Some(left) => (game.tick_length() * left, Color::White),
+            None => (game.time_played(), Color::White),
+        };
+        pencil
+            .set_foreground(color)
+            .draw_right_aligned_text(&scores::format_time(time), Vec2::xy(size.x - 1, 1));
+        if let Some(left) = game.ticks_until_shrink() {
+            pencil.set_foreground(Color::Red).draw_right_aligned_text(
+                &format!(
+                    "shrinks in {}",
+                    scores::format_time(game.tick_length() * left)
+                ),
+                Vec2::xy(size.x - 1, 3),
+            );
+        }
+        if let Some(left) = game.streak_left() {
+            pencil
+                .set_foreground(Color::Yellow)
+                .draw_right_aligned_text(
+                    &format!("streak x{} ({left})", game.multiplier()),
+                    Vec2::xy(size.x - 1, 4),
+                );
+        }
+        if paused {
+            pencil
+                .set_foreground(Color::White)
+                .draw_center_text("PAUSED", Vec2::xy(size.x / 2, 1));
+        }
+        if let Some(z) = view.layer {
+            pencil.set_foreground(Color::White).draw_center_text(
+                &format!("PEEK layer {z} (PgUp/PgDn)"),
+                Vec2::xy(size.x / 2, 2),
+            );
+        }
+        if view.projection.name() != Isometric.name() {
+            pencil.set_foreground(Color::White).draw_center_text(
+                &format!("{} view", view.projection.name()),
+                Vec2::xy(size.x / 2, 3),
+            );
+        }
+        if timestep.pace() != 1.0 {
+            pencil.set_foreground(Color::White).draw_right_aligned_text(
+                &format!("x{}", (timestep.pace() * 100.0).round() / 100.0),
+                Vec2::xy(size.x - 1, 2),
+            );
+        }
+        if let Some(message) = &message {
+            pencil
+                .set_foreground(Color::White)
+                .draw_text(message, Vec2::xy(1, 2));
+        }
+        log.draw(pencil, Vec2::xy(1, 3), (size.x - 2).max(0) as usize);
+        log.advance();
+        if let Some(banner) = banners.current() {
+            pencil.set_foreground(Color::Yellow).draw_center_text(
+                &message_log::truncated(banner, (size.x - 2).max(0) as usize),
+                Vec2::xy(size.x / 2, size.y - 2),
+            );
+        }
+        banners.advance();
+        if let Some(bell) = &mut bell
+            && bell.ring(app_state.step())
+        {
+            ring_terminal();
+        }
+        if show_debug {
+            pencil.set_foreground(Color::DarkGrey).draw_text(
+                &format!(
+                    "{} fps  {} cells changed",
+                    fps_counter.count(),
+                    renderer.drawn()
+                ),
+                Vec2::xy(1, size.y - 1),
+            );
+        }
+        if show_coords {
+            draw_coords(pencil, &game, size);
+        }
+        if let Some(counting) = &mut countdown {
+            counting.draw(pencil, size / 2);
+            counting.advance();
+            if counting.is_over() {
+                countdown = None;
+            }
+        }
+        if let Some(game_over) = game_over.as_ref().filter(|_| !effects.is_flashing()) {
+            game_over.draw(
+                pencil,
+                &scores,
+                stats
+                    .as_ref()
+                    .map(|stats| (stats, timestep.tick_duration())),
+                size,
+            );
+        } else if let Some(stats) = stats.as_ref().filter(|_| completed) {
+            draw_summary(
+                pencil,
+                "LEVEL COMPLETE",
+                stats,
+                size,
+                timestep.tick_duration(),
+            );
+        } else if let Some(playback) = playback.as_ref().filter(|p| p.status().is_some()) {
+            draw_summary(
+                pencil,
+                "RECORDED RUN",
+                playback.stats(),
+                size,
+                timestep.tick_duration(),
+            );
+        }
     });
+
+    if let (Some(recording), Some(path)) = (&mut recording, &record_path) {
+        recording.finish(game.score(), death.is_some(), stats.unwrap_or_default());
+        if let Err(err) = recording.save(path) {
+            eprintln!("Could not save the replay to {path}: {err}");
+        }
+    }
+    if let Some(err) = death {
+        eprintln!("PERDU: {err}");
+    }
+    ExitCode::SUCCESS
+}
+
+// La graine quand on n'en donne pas : l'horloge, pour que chaque partie soit différente
+fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos() as u64)
+}
+
+// the bell character goes straight to the terminal, it doesn't take up a cell of the canvas
+fn ring_terminal() {
+    let mut stdout = io::stdout();
+    let _ = stdout.write_all(b"\x07").and_then(|()| stdout.flush());
+}
+
+// Garde le score s'il est le meilleur du défi de `date`, et de quoi le dire au joueur
+fn record_daily(
+    scores: &mut ScoreTable,
+    path: Option<&Path>,
+    date: &str,
+    entry: ScoreEntry,
+) -> Option<String> {
+    if !scores.record_daily(date, entry) {
+        return None;
+    }
+    Some(match path.map(|path| scores.save(path)) {
+        Some(Err(err)) => err.to_string(),
+        _ => format!("Best score of the {date} challenge"),
+    })
+}
+
+// Le résumé de la partie seul au milieu de l'écran, quand il n'y a pas de scores à montrer
+fn draw_summary(pencil: &mut Pencil, heading: &str, stats: &RunStats, size: Vec2, tick: Duration) {
+    let top = size.y / 2 - 5;
+    pencil
+        .set_foreground(Color::Green)
+        .draw_center_text(heading, Vec2::xy(size.x / 2, top));
+    stats.draw(pencil, size.x / 2, top + 2, tick);
+}
+
+// Où est la tête et sur quoi elle est, pour comprendre les chutes qu'on nous signale
+fn draw_coords(pencil: &mut Pencil, game: &GameState, size: Vec2) {
+    let head = game.head();
+    let cell = |cell: Option<Cell>| cell.map_or("nothing".to_string(), |cell| format!("{cell:?}"));
+    pencil
+        .set_foreground(Color::DarkGrey)
+        .draw_right_aligned_text(
+            &format!(
+                "head {head:?} in {} on {}",
+                cell(game.grid().get(head)),
+                cell(game.cell_below(head))
+            ),
+            Vec2::xy(size.x - 1, size.y - 1),
+        );
+}
+
+fn versus_result(game: &GameState) -> String {
+    let scores = format!("{} - {}", game.score_of(0), game.score_of(1));
+    match game.winner() {
+        Some(player) => format!("Player {} wins! {scores}", player + 1),
+        None => format!("Draw! {scores}"),
+    }
+}
+
+// L'écran de fin : le tableau des scores, et les initiales à taper si on y rentre
+struct GameOver {
+    entry: Option<ScoreEntry>, // None once it's in the table, or if it didn't make it
+    initials: String,
+    highlight: Option<usize>,
+}
+
+impl GameOver {
+    const INITIALS_LEN: usize = 3;
+
+    fn new(scores: &ScoreTable, entry: ScoreEntry) -> Self {
+        Self {
+            entry: scores.qualifies(entry.score).then_some(entry),
+            initials: String::new(),
+            highlight: None,
+        }
+    }
+
+    fn type_letter(&mut self, key: Key) {
+        if self.entry.is_some()
+            && self.initials.len() < Self::INITIALS_LEN
+            && let Some(letter) = key_letter(key)
+        {
+            self.initials.push(letter);
+        }
+    }
+
+    // puts the score in the table, returns where it went
+    fn confirm(&mut self, scores: &mut ScoreTable) -> Option<usize> {
+        let mut entry = self.entry.take()?;
+        entry.initials = (!self.initials.is_empty()).then(|| self.initials.clone());
+        self.highlight = scores.insert(entry);
+        self.highlight
+    }
+
+    // the run's summary goes under the table, if there's one
+    fn draw(
+        &self,
+        pencil: &mut Pencil,
+        scores: &ScoreTable,
+        stats: Option<(&RunStats, Duration)>,
+        size: Vec2,
+    ) {
+        let below = stats.map_or(0, |(stats, tick)| stats.rows(tick).len() as i32 + 1);
+        let top = size.y / 2 - (scores::MAX_ENTRIES as i32 + below) / 2 - 2;
+        let center = size.x / 2;
+        pencil
+            .set_foreground(Color::Red)
+            .draw_center_text("GAME OVER", Vec2::xy(center, top));
+        if self.entry.is_some() {
+            let blanks = "_".repeat(Self::INITIALS_LEN - self.initials.len());
+            pencil.set_foreground(Color::White).draw_center_text(
+                &format!("New high score! Initials: {}{blanks}", self.initials),
+                Vec2::xy(center, top + 1),
+            );
+        }
+        for (rank, entry) in scores.entries().iter().enumerate() {
+            let color = if self.highlight == Some(rank) {
+                Color::Yellow
+            } else {
+                Color::White
+            };
+            let line = format!(
+                "{:>2}. {:<3} {:>5}  len {:<4} {:<10} {}",
+                rank + 1,
+                entry.initials.as_deref().unwrap_or("---"),
+                entry.score,
+                entry.length,
+                entry.level,
+                scores::format_date(entry.date),
+            );
+            pencil
+                .set_foreground(color)
+                .draw_center_text(&line, Vec2::xy(center, top + 3 + rank as i32));
+        }
+        if let Some((stats, tick)) = stats {
+            stats.draw(pencil, center, top + 4 + scores::MAX_ENTRIES as i32, tick);
+        }
+    }
+}
+
+fn key_letter(key: Key) -> Option<char> {
+    const LETTERS: [(Key, char); 26] = [
+        (Key::A, 'A'),
+        (Key::B, 'B'),
+        (Key::C, 'C'),
+        (Key::D, 'D'),
+        (Key::E, 'E'),
+        (Key::F, 'F'),
+        (Key::G, 'G'),
+        (Key::H, 'H'),
+        (Key::I, 'I'),
+        (Key::J, 'J'),
+        (Key::K, 'K'),
+        (Key::L, 'L'),
+        (Key::M, 'M'),
+        (Key::N, 'N'),
+        (Key::O, 'O'),
+        (Key::P, 'P'),
+        (Key::Q, 'Q'),
+        (Key::R, 'R'),
+        (Key::S, 'S'),
+        (Key::T, 'T'),
+        (Key::U, 'U'),
+        (Key::V, 'V'),
+        (Key::W, 'W'),
+        (Key::X, 'X'),
+        (Key::Y, 'Y'),
+        (Key::Z, 'Z'),
+    ];
+    LETTERS
+        .iter()
+        .find(|&&(letter_key, _)| letter_key == key)
+        .map(|&(_, letter)| letter)
+}
+
+/// Where the keys held down steer `player`, unless a key pressed during the frame already does.
+fn held_steering(
+    bindings: &Bindings,
+    actions: &[Action],
+    held: &[Key],
+    player: usize,
+    versus: bool,
+) -> Option<Direction> {
+    let steers = |action| steered_by(action, player, versus);
+    // une touche appuyée pendant la frame passe avant celles qu'on tient
+    if actions.iter().any(|&action| steers(action).is_some()) {
+        return None;
+    }
+    bindings.held_direction(held, steers)
+}
+
+/// The direction `action` steers `player` in, WASD going to the second snake in versus mode.
+fn steered_by(action: Action, player: usize, versus: bool) -> Option<Direction> {
+    match (action, player) {
+        (Action::Move(dir), 0) => Some(dir),
+        (Action::MoveAlt(dir), 0) if !versus => Some(dir),
+        (Action::MoveAlt(dir), 1) if versus => Some(dir),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // les touches tenues vont de la plus ancienne à la plus récente, comme ruscii les donne
+    fn steering(
+        actions: &[Action],
+        held: &[Key],
+        player: usize,
+        versus: bool,
+    ) -> Option<Direction> {
+        held_steering(&Bindings::default(), actions, held, player, versus)
+    }
+
+    #[test]
+    fn the_last_key_held_steers() {
+        assert_eq!(
+            steering(&[], &[Key::Up, Key::Left], 0, false),
+            Some(Direction::West)
+        );
+        assert_eq!(
+            steering(&[], &[Key::Left, Key::Space], 0, false),
+            Some(Direction::West)
+        );
+        assert_eq!(steering(&[], &[Key::Space], 0, false), None);
+        // seul, WASD dirige aussi
+        assert_eq!(
+            steering(&[], &[Key::Right, Key::W], 0, false),
+            Some(Direction::North)
+        );
+    }
+
+    #[test]
+    fn a_key_pressed_this_frame_goes_before_the_ones_held() {
+        let pressed = [Action::Move(Direction::East)];
+        assert_eq!(steering(&pressed, &[Key::Up, Key::Left], 0, false), None);
+        // ce qui ne dirige pas ce joueur-là ne compte pas
+        let pressed = [Action::Pause, Action::MoveAlt(Direction::South)];
+        assert_eq!(
+            steering(&pressed, &[Key::Left], 0, true),
+            Some(Direction::West)
+        );
+        assert_eq!(steering(&pressed, &[Key::W], 1, true), None);
+    }
+
+    #[test]
+    fn in_versus_each_snake_has_its_keys() {
+        let held = [Key::W, Key::Right];
+        assert_eq!(steering(&[], &held, 0, true), Some(Direction::East));
+        assert_eq!(steering(&[], &held, 1, true), Some(Direction::North));
+        assert_eq!(steering(&[], &[Key::Right], 1, true), None);
+    }
 }
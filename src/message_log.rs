@@ -0,0 +1,149 @@
+// Une ligne sous le HUD qui raconte ce qui vient de se passer, un message après l'autre
+
+use std::collections::VecDeque;
+
+use ruscii::{drawing::Pencil, spatial::Vec2, terminal::Color};
+
+use crate::game::GameEvent;
+
+/// How long each message stays, in seconds.
+const MESSAGE_SECONDS: usize = 2;
+
+/// What's worth telling the player about `event`, nothing for what happens all the time
+/// or already has its own feedback (moves, food, deaths...).
+pub fn describe(event: &GameEvent) -> Option<String> {
+    Some(match event {
+        GameEvent::Unlocked { id } => format!("Key {id} collected, its doors are open"),
+        GameEvent::Shrunk { .. } => "The edge of the arena collapsed".to_string(),
+        GameEvent::Expanded {
+            dimensions: (x, y, z),
+        } => format!("The level grew to {x}x{y}x{z}"),
+        GameEvent::Enclosed { cells: 1 } => "Closed a loop around a cell, +1".to_string(),
+        GameEvent::Enclosed { cells } => format!("Closed a loop around {cells} cells, +{cells}"),
+        GameEvent::Moved { .. }
+        | GameEvent::Ate { .. }
+        | GameEvent::Jumped
+        | GameEvent::Fell
+        | GameEvent::Completed
+        | GameEvent::Died(_) => return None,
+    })
+}
+
+/// `text` cut to fit in `width` columns, with an ellipsis when something was cut.
+pub fn truncated(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    if width > 0 {
+        cut.push('…');
+    }
+    cut
+}
+
+/// The messages waiting to be shown, the oldest first and on screen for a couple of seconds.
+#[derive(Debug)]
+pub struct MessageLog {
+    queue: VecDeque<String>,
+    shown_for: usize, // frames the first one has been on screen
+    frames_each: usize,
+}
+
+impl MessageLog {
+    pub fn new(fps: u32) -> Self {
+        Self {
+            queue: VecDeque::new(),
+            shown_for: 0,
+            frames_each: fps.max(1) as usize * MESSAGE_SECONDS,
+        }
+    }
+
+    pub fn push(&mut self, message: String) {
+        self.queue.push_back(message);
+    }
+
+    pub fn extend(&mut self, messages: impl IntoIterator<Item = String>) {
+        self.queue.extend(messages);
+    }
+
+    /// What's on screen right now.
+    pub fn current(&self) -> Option<&str> {
+        self.queue.front().map(String::as_str)
+    }
+
+    /// Goes on to the next frame, and to the next message once the first one has been there long enough.
+    pub fn advance(&mut self) {
+        if self.queue.is_empty() {
+            return;
+        }
+        self.shown_for += 1;
+        if self.shown_for >= self.frames_each {
+            self.queue.pop_front();
+            self.shown_for = 0;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.shown_for = 0;
+    }
+
+    pub fn draw(&self, pencil: &mut Pencil, at: Vec2, width: usize) {
+        if let Some(message) = self.current() {
+            pencil
+                .set_foreground(Color::Grey)
+                .draw_text(&truncated(message, width), at);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameError;
+
+    #[test]
+    fn only_the_rare_events_are_described() {
+        let described = |event| describe(&event);
+        assert_eq!(
+            described(GameEvent::Unlocked { id: 2 }).as_deref(),
+            Some("Key 2 collected, its doors are open")
+        );
+        assert_eq!(
+            described(GameEvent::Shrunk { ring: 0 }).as_deref(),
+            Some("The edge of the arena collapsed")
+        );
+        assert_eq!(
+            described(GameEvent::Expanded {
+                dimensions: (7, 5, 3)
+            })
+            .as_deref(),
+            Some("The level grew to 7x5x3")
+        );
+        assert_eq!(
+            described(GameEvent::Enclosed { cells: 1 }).as_deref(),
+            Some("Closed a loop around a cell, +1")
+        );
+        assert_eq!(
+            described(GameEvent::Enclosed { cells: 4 }).as_deref(),
+            Some("Closed a loop around 4 cells, +4")
+        );
+        for event in [
+            GameEvent::Moved { head: (0, 0, 1) },
+            GameEvent::Ate { coord: (1, 0, 1) },
+            GameEvent::Jumped,
+            GameEvent::Fell,
+            GameEvent::Completed,
+            GameEvent::Died(GameError::TimeUp),
+        ] {
+            assert_eq!(describe(&event), None, "{event:?}");
+        }
+    }
+
+    #[test]
+    fn long_messages_are_cut_with_an_ellipsis() {
+        assert_eq!(truncated("Key 2 collected", 20), "Key 2 collected");
+        assert_eq!(truncated("Key 2 collected", 6), "Key 2…");
+        assert_eq!(truncated("Key", 0), "");
+    }
+}
@@ -0,0 +1,567 @@
+use std::{fs, io, path::Path};
+
+use thiserror::Error;
+
+use crate::game::{Difficulty, Direction, GameConfig, Wrap};
+use crate::stats::RunStats;
+
+const MAGIC: &[u8; 4] = b"JJRP";
+const FORMAT_VERSION: u8 = 5;
+const STATS_START: usize = MAGIC.len() + 1 + 8 + 8 + 4 + 1;
+const DAILY_START: usize = STATS_START + RunStats::ENCODED_LEN;
+const DATE_LEN: usize = "YYYY-MM-DD".len();
+const CONFIG_START: usize = DAILY_START + 1 + DATE_LEN;
+// the dimensions as three i64, eleven u32 settings, then a byte of flags, the facing, the wrap and the difficulty
+const CONFIG_LEN: usize = 3 * 8 + 11 * 4 + 4;
+const HEADER_LEN: usize = CONFIG_START + CONFIG_LEN + 1;
+
+/// Every input fed to the game, one per tick, so a run can be played back exactly.
+///
+/// On disk : `JJRP`, the format version, the level fingerprint and the seed
+/// (both little endian u64), the final score (little endian u32), whether the
+/// snake died on the last tick, the run's [`RunStats`], the generator version
+/// and the date of a daily challenge (0 and zeros otherwise), the settings the game
+/// was played with (see [`Replay::config`]), whether the level was generated,
+/// then one byte per tick.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    pub level_hash: u64,
+    pub seed: u64,
+    pub score: u32,
+    pub died: bool,
+    pub stats: RunStats,
+    pub daily: Option<DailyTag>,
+    /// Only the settings that change how the game plays and the level's dimensions,
+    /// the others (frame rate, players...) are left at their defaults.
+    pub config: GameConfig,
+    pub generated: bool, // the level came from the generator, with the seed and these dimensions
+    pub inputs: Vec<Direction>,
+}
+
+/// What a daily challenge replay needs to get its level back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DailyTag {
+    pub date: String,  // YYYY-MM-DD
+    pub generator: u8, // the version of the generator that made the level
+}
+
+impl Replay {
+    /// A recording of a game played with `config` on the level hashing to `level_hash`.
+    pub fn new(level_hash: u64, config: &GameConfig) -> Self {
+        let mut recorded = GameConfig::default();
+        copy_rules(config, &mut recorded);
+        Self {
+            level_hash,
+            seed: config.seed,
+            score: 0,
+            died: false,
+            stats: RunStats::default(),
+            daily: None,
+            config: recorded,
+            generated: false,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Puts the recorded settings and seed in `config`, leaving it the frontend's own.
+    pub fn apply_to(&self, config: &mut GameConfig) {
+        copy_rules(&self.config, config);
+        config.seed = self.seed;
+    }
+
+    /// Remembers how the run ended, so playing it back can tell if it went differently.
+    pub fn finish(&mut self, score: u32, died: bool, stats: RunStats) {
+        self.score = score;
+        self.died = died;
+        self.stats = stats;
+    }
+
+    pub fn record(&mut self, input: Direction) {
+        self.inputs.push(input);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.inputs.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&self.level_hash.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.score.to_le_bytes());
+        bytes.push(self.died as u8);
+        bytes.extend(self.stats.to_bytes());
+        match &self.daily {
+            Some(daily) => {
+                bytes.push(daily.generator);
+                bytes.extend(daily.date.bytes().chain([0; DATE_LEN]).take(DATE_LEN));
+            }
+            None => bytes.extend([0; 1 + DATE_LEN]),
+        }
+        bytes.extend(config_to_bytes(&self.config));
+        bytes.push(self.generated as u8);
+        bytes.extend(self.inputs.iter().map(|&dir| direction_to_byte(dir)));
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        if bytes.len() < HEADER_LEN || !bytes.starts_with(MAGIC) {
+            return Err(ReplayError::NotAReplay);
+        }
+        let version = bytes[MAGIC.len()];
+        if version != FORMAT_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let level_hash = u64::from_le_bytes(bytes[5..13].try_into().unwrap());
+        let seed = u64::from_le_bytes(bytes[13..21].try_into().unwrap());
+        let score = u32::from_le_bytes(bytes[21..25].try_into().unwrap());
+        let died = bytes[25] != 0;
+        let stats = RunStats::from_bytes(&bytes[STATS_START..DAILY_START]);
+        let daily = match bytes[DAILY_START] {
+            0 => None,
+            generator => Some(DailyTag {
+                date: String::from_utf8(bytes[DAILY_START + 1..CONFIG_START].to_vec())
+                    .map_err(|_| ReplayError::NotAReplay)?,
+                generator,
+            }),
+        };
+        let config = config_from_bytes(&bytes[CONFIG_START..CONFIG_START + CONFIG_LEN])
+            .ok_or(ReplayError::NotAReplay)?;
+        let generated = bytes[CONFIG_START + CONFIG_LEN] != 0;
+        let inputs = bytes[HEADER_LEN..]
+            .iter()
+            .enumerate()
+            .map(|(tick, &byte)| {
+                byte_to_direction(byte).ok_or(ReplayError::InvalidInput { tick, byte })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            level_hash,
+            seed,
+            score,
+            died,
+            stats,
+            daily,
+            config,
+            generated,
+            inputs,
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 8.0;
+
+/// Plays a [`Replay`] back tick by tick, at an adjustable speed.
+#[derive(Debug)]
+pub struct Playback {
+    replay: Replay,
+    tick: usize,
+    speed: f32,    // recorded ticks per game tick
+    progress: f32, // fraction of a recorded tick carried over to the next one
+    pub paused: bool,
+    status: Option<String>, // set once the replay is over, or went out of sync
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            tick: 0,
+            speed: 1.0,
+            progress: 0.0,
+            paused: false,
+            status: None,
+        }
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed * 2.0).min(MAX_SPEED);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = (self.speed / 2.0).max(MIN_SPEED);
+    }
+
+    /// How many recorded ticks should be played during one game tick.
+    pub fn ticks_this_step(&mut self) -> usize {
+        if self.paused || self.status.is_some() {
+            return 0;
+        }
+        self.progress += self.speed;
+        let ticks = self.progress.floor();
+        self.progress -= ticks;
+        ticks as usize
+    }
+
+    /// The input for the next tick, `None` once the recording is over.
+    pub fn next_input(&mut self) -> Option<Direction> {
+        let input = self.replay.inputs.get(self.tick).copied();
+        if input.is_some() {
+            self.tick += 1;
+        }
+        input
+    }
+
+    /// To be called when the game ends during playback, either by dying or running out of inputs.
+    pub fn end(&mut self, died: bool, score: u32) {
+        if self.status.is_some() {
+            return;
+        }
+        let expected_end = self.tick == self.replay.inputs.len();
+        self.status = Some(if died && !(expected_end && self.replay.died) {
+            format!(
+                "DESYNC: died at tick {} but the recording didn't",
+                self.tick
+            )
+        } else if !died && self.replay.died {
+            "DESYNC: the recording died but the replay survived".to_string()
+        } else if score != self.replay.score {
+            format!("DESYNC: score {score} instead of {}", self.replay.score)
+        } else {
+            "Replay over".to_string()
+        });
+    }
+
+    /// What the recorded game was seeded with, the replay has to use the same.
+    pub fn seed(&self) -> u64 {
+        self.replay.seed
+    }
+
+    /// How the recorded run went, as it was recorded.
+    pub fn stats(&self) -> &RunStats {
+        &self.replay.stats
+    }
+
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+}
+
+// UTILS
+
+// ce qui change la partie elle-même, le reste (images par seconde, joueurs...) est au frontend
+fn copy_rules(from: &GameConfig, to: &mut GameConfig) {
+    to.ticks_per_second = from.ticks_per_second;
+    to.dimensions = from.dimensions;
+    to.coyote_ticks = from.coyote_ticks;
+    to.endless = from.endless;
+    to.starting_length = from.starting_length;
+    to.starting_facing = from.starting_facing;
+    to.time_limit = from.time_limit;
+    to.shrink_every = from.shrink_every;
+    to.expanding = from.expanding;
+    to.loops = from.loops;
+    to.streak = from.streak;
+    to.trail = from.trail;
+    to.wrap = from.wrap;
+    to.fog = from.fog;
+    to.dark = from.dark;
+    to.difficulty = from.difficulty;
+    to.foods = from.foods;
+    to.poison_percent = from.poison_percent;
+    to.reverse_guard = from.reverse_guard;
+    to.speedup_percent = from.speedup_percent;
+    to.max_speedup_percent = from.max_speedup_percent;
+}
+
+// little endian like the rest of the header, u32::MAX for what isn't set
+fn config_to_bytes(config: &GameConfig) -> Vec<u8> {
+    let (x, y, z) = config.dimensions.unwrap_or((0, 0, 0));
+    let optional = |setting: Option<u32>| setting.unwrap_or(u32::MAX);
+    let flags = [
+        config.endless,
+        config.expanding,
+        config.loops,
+        config.trail,
+        config.dark,
+        config.reverse_guard,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |flags, (bit, &set)| flags | (set as u8) << bit);
+    let difficulty = match config.difficulty {
+        Difficulty::Easy => 0,
+        Difficulty::Normal => 1,
+        Difficulty::Hard => 2,
+    };
+    [x, y, z]
+        .iter()
+        .flat_map(|&n| (n as i64).to_le_bytes())
+        .chain(
+            [
+                config.ticks_per_second,
+                config.coyote_ticks,
+                config.starting_length as u32,
+                optional(config.time_limit),
+                optional(config.shrink_every),
+                optional(config.streak),
+                optional(config.fog),
+                config.foods as u32,
+                config.poison_percent,
+                config.speedup_percent,
+                config.max_speedup_percent,
+            ]
+            .iter()
+            .flat_map(|n| n.to_le_bytes()),
+        )
+        .chain([
+            flags,
+            direction_to_byte(config.starting_facing),
+            config.wrap.x as u8 | (config.wrap.y as u8) << 1,
+            difficulty,
+        ])
+        .collect()
+}
+
+// `bytes` being CONFIG_LEN long, None if something in it makes no sense
+fn config_from_bytes(bytes: &[u8]) -> Option<GameConfig> {
+    let int = |idx: usize| i64::from_le_bytes(bytes[idx * 8..idx * 8 + 8].try_into().unwrap());
+    let counter = |idx: usize| {
+        let start = 3 * 8 + idx * 4;
+        u32::from_le_bytes(bytes[start..start + 4].try_into().unwrap())
+    };
+    let optional = |idx: usize| Some(counter(idx)).filter(|&n| n != u32::MAX);
+    let [flags, facing, wrap, difficulty] = bytes[CONFIG_LEN - 4..] else {
+        return None;
+    };
+    let flag = |bit: u8| flags & 1 << bit != 0;
+    let dimensions = (int(0) as isize, int(1) as isize, int(2) as isize);
+    Some(GameConfig {
+        dimensions: (dimensions != (0, 0, 0)).then_some(dimensions),
+        ticks_per_second: counter(0),
+        coyote_ticks: counter(1),
+        starting_length: counter(2) as usize,
+        time_limit: optional(3),
+        shrink_every: optional(4),
+        streak: optional(5),
+        fog: optional(6),
+        foods: counter(7) as usize,
+        poison_percent: counter(8),
+        speedup_percent: counter(9),
+        max_speedup_percent: counter(10),
+        endless: flag(0),
+        expanding: flag(1),
+        loops: flag(2),
+        trail: flag(3),
+        dark: flag(4),
+        reverse_guard: flag(5),
+        starting_facing: byte_to_direction(facing)?,
+        wrap: Wrap {
+            x: wrap & 1 != 0,
+            y: wrap & 2 != 0,
+        },
+        difficulty: match difficulty {
+            0 => Difficulty::Easy,
+            1 => Difficulty::Normal,
+            2 => Difficulty::Hard,
+            _ => return None,
+        },
+        ..GameConfig::default()
+    })
+}
+
+fn direction_to_byte(dir: Direction) -> u8 {
+    match dir {
+        Direction::None => 0,
+        Direction::North => 1,
+        Direction::South => 2,
+        Direction::West => 3,
+        Direction::East => 4,
+        Direction::Up => 5,
+        Direction::Down => 6,
+    }
+}
+
+fn byte_to_direction(byte: u8) -> Option<Direction> {
+    Some(match byte {
+        0 => Direction::None,
+        1 => Direction::North,
+        2 => Direction::South,
+        3 => Direction::West,
+        4 => Direction::East,
+        5 => Direction::Up,
+        6 => Direction::Down,
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("Could not read the replay file: {0}")]
+    Io(#[from] io::Error),
+    #[error("This is not a replay file")]
+    NotAReplay,
+    #[error("Unsupported replay format version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Invalid input byte {byte} at tick {tick}")]
+    InvalidInput { tick: usize, byte: u8 },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameState;
+    use crate::level::Level;
+
+    // plays `script` the way the game loop does, recording what each tick acted on
+    fn record(level: &Level, script: &[Direction]) -> (Replay, Vec<(isize, isize, isize)>) {
+        record_with(level, script, &GameConfig::default())
+    }
+
+    fn record_with(
+        level: &Level,
+        script: &[Direction],
+        config: &GameConfig,
+    ) -> (Replay, Vec<(isize, isize, isize)>) {
+        let mut game = GameState::new(level.start, level.grid.clone(), config).unwrap();
+        let mut replay = Replay::new(level.fingerprint(), config);
+        let mut heads = Vec::new();
+        let mut died = false;
+        for &dir in script {
+            game.queue_input(dir);
+            let input = game.take_input();
+            replay.record(input);
+            died = game.step(input).is_err();
+            heads.push(game.head());
+            if died {
+                break;
+            }
+        }
+        replay.finish(game.score(), died, RunStats::default());
+        (replay, heads)
+    }
+
+    #[test]
+    fn a_recorded_game_reads_back_the_same() {
+        let level = Level::arena((6, 6, 2));
+        let [north, south, east] = [Direction::North, Direction::South, Direction::East];
+        let script = [
+            south, south, south, east, east, east, north, north, north, north,
+        ];
+        let (replay, heads) = record(&level, &script);
+        assert!(replay.died);
+        assert_eq!(replay.score, 1);
+
+        let path = std::env::temp_dir().join(format!("svnake-replay-{}", std::process::id()));
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded, replay);
+
+        // les entrées relues refont exactement la même partie
+        let mut game = GameState::new(level.start, level.grid, &GameConfig::default()).unwrap();
+        let mut replayed = Vec::new();
+        for &input in &loaded.inputs {
+            let died = game.step(input).is_err();
+            replayed.push(game.head());
+            if died {
+                break;
+            }
+        }
+        assert_eq!(replayed, heads);
+        assert_eq!(game.score(), loaded.score);
+    }
+
+    // plays it back like the game loop does, one recorded tick per game tick
+    fn play_back(level: &Level, replay: Replay) -> Playback {
+        let mut config = GameConfig::default();
+        replay.apply_to(&mut config);
+        play_back_with(level, replay, &config)
+    }
+
+    fn play_back_with(level: &Level, replay: Replay, config: &GameConfig) -> Playback {
+        let mut playback = Playback::new(replay);
+        let mut game = GameState::new(level.start, level.grid.clone(), config).unwrap();
+        while playback.status().is_none() {
+            for _ in 0..playback.ticks_this_step() {
+                let Some(input) = playback.next_input() else {
+                    playback.end(false, game.score());
+                    break;
+                };
+                if game.step(input).is_err() {
+                    playback.end(true, game.score());
+                    break;
+                }
+            }
+        }
+        playback
+    }
+
+    #[test]
+    fn playing_back_ends_on_the_recorded_score() {
+        let level = Level::arena((6, 6, 2));
+        let [south, east] = [Direction::South, Direction::East];
+        let (replay, _) = record(&level, &[south, south, south, east, east, east, east]);
+        assert_eq!(replay.score, 1);
+        let playback = play_back(&level, replay.clone());
+        assert_eq!(playback.status(), Some("Replay over"));
+
+        let tampered = Replay { score: 2, ..replay };
+        let playback = play_back(&level, tampered);
+        assert_eq!(playback.status(), Some("DESYNC: score 1 instead of 2"));
+    }
+
+    #[test]
+    fn the_settings_it_was_played_with_come_back_with_it() {
+        let level = Level::arena((8, 8, 2));
+        let config = GameConfig {
+            endless: true,
+            streak: Some(6),
+            trail: true,
+            starting_length: 3,
+            starting_facing: Direction::West,
+            time_limit: Some(400),
+            wrap: Wrap { x: true, y: false },
+            fog: Some(2),
+            foods: 3,
+            seed: 42,
+            dimensions: Some((8, 8, 2)),
+            ..Difficulty::Hard.to_config()
+        };
+        // par le bord de gauche qui ramène à droite, puis vers la nourriture
+        let [south, west] = [Direction::South, Direction::West];
+        let script = [west, south, south, south, south, west, west, west];
+        let (replay, _) = record_with(&level, &script, &config);
+        assert!(!replay.died);
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(loaded, replay);
+
+        // ce que le frontend a à lui est gardé, le reste vient de l'enregistrement
+        let mut played = GameConfig {
+            fps: 60,
+            ..GameConfig::default()
+        };
+        loaded.apply_to(&mut played);
+        assert_eq!(
+            played,
+            GameConfig {
+                fps: 60,
+                ..config.clone()
+            }
+        );
+        assert_eq!(
+            play_back(&level, loaded.clone()).status(),
+            Some("Replay over")
+        );
+
+        // rejoué avec les réglages par défaut, ce n'est plus la même partie
+        let plain = GameConfig {
+            seed: 42,
+            ..GameConfig::default()
+        };
+        let playback = play_back_with(&level, loaded, &plain);
+        assert_ne!(playback.status(), Some("Replay over"));
+    }
+}
@@ -0,0 +1,336 @@
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::Difficulty;
+
+/// How many scores the table keeps.
+pub const MAX_ENTRIES: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub score: u32,
+    pub length: usize,
+    pub level: String,
+    pub date: u64, // seconds since the unix epoch
+    pub initials: Option<String>,
+}
+
+impl ScoreEntry {
+    /// A score made right now, initials still to be typed in.
+    pub fn new(score: u32, length: usize, level: impl Into<String>) -> Self {
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Self {
+            score,
+            length,
+            level: level.into(),
+            date,
+            initials: None,
+        }
+    }
+}
+
+/// The best scores, highest first, and the best one of each daily challenge.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreTable {
+    entries: Vec<ScoreEntry>,
+    #[serde(default)] // tables saved before the daily challenge
+    daily: BTreeMap<String, ScoreEntry>, // by date, YYYY-MM-DD
+}
+
+impl ScoreTable {
+    /// Where the table of `difficulty` lives, under the user's data directory. Each difficulty
+    /// has its own, normal keeping the file there was before difficulties.
+    pub fn default_path(difficulty: Difficulty) -> Option<PathBuf> {
+        let name = match difficulty {
+            Difficulty::Normal => "scores.ron".to_string(),
+            other => format!("scores-{}.ron", other.name()),
+        };
+        Some(data_dir()?.join(name))
+    }
+
+    /// A missing file is just an empty table.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScoreError> {
+        let mut table: Self = load_or_default(path)?;
+        // don't trust the file to be sorted
+        table.entries.sort_by_key(|entry| Reverse(entry.score));
+        table.entries.truncate(MAX_ENTRIES);
+        Ok(table)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ScoreError> {
+        save(self, path)
+    }
+
+    pub fn entries(&self) -> &[ScoreEntry] {
+        &self.entries
+    }
+
+    /// Whether `score` would make it into the table.
+    pub fn qualifies(&self, score: u32) -> bool {
+        self.entries.len() < MAX_ENTRIES || self.entries.iter().any(|entry| entry.score < score)
+    }
+
+    /// Puts the entry at its place, after the scores that are as good as it.
+    /// Returns its index, `None` if it didn't make it.
+    pub fn insert(&mut self, entry: ScoreEntry) -> Option<usize> {
+        let index = self
+            .entries
+            .iter()
+            .position(|other| other.score < entry.score)
+            .unwrap_or(self.entries.len());
+        if index >= MAX_ENTRIES {
+            return None;
+        }
+        self.entries.insert(index, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        Some(index)
+    }
+
+    /// The best score made on the challenge of `date`.
+    pub fn daily_best(&self, date: &str) -> Option<&ScoreEntry> {
+        self.daily.get(date)
+    }
+
+    /// Keeps `entry` if it beats the best of `date`, returns whether it did.
+    pub fn record_daily(&mut self, date: &str, entry: ScoreEntry) -> bool {
+        if self
+            .daily_best(date)
+            .is_some_and(|best| best.score >= entry.score)
+        {
+            return false;
+        }
+        self.daily.insert(date.to_string(), entry);
+        true
+    }
+}
+
+/// The best completion time of every level played, keyed by [`BestTimes::level_key`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BestTimes {
+    times: BTreeMap<String, Duration>,
+}
+
+impl BestTimes {
+    pub fn default_path() -> Option<PathBuf> {
+        Some(data_dir()?.join("best_times.ron"))
+    }
+
+    /// A missing file means no level was ever finished.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScoreError> {
+        load_or_default(path)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ScoreError> {
+        save(self, path)
+    }
+
+    /// The name alone isn't enough, two levels can have the same one.
+    pub fn level_key(name: &str, fingerprint: u64) -> String {
+        format!("{name}#{fingerprint:016x}")
+    }
+
+    pub fn get(&self, level_key: &str) -> Option<Duration> {
+        self.times.get(level_key).copied()
+    }
+
+    /// Keeps `time` if it beats the current best, returns whether it did.
+    pub fn record(&mut self, level_key: &str, time: Duration) -> bool {
+        if self.get(level_key).is_some_and(|best| best <= time) {
+            return false;
+        }
+        self.times.insert(level_key.to_string(), time);
+        true
+    }
+}
+
+/// `mm:ss.t`, like a speedrun timer.
+pub fn format_time(time: Duration) -> String {
+    let tenths = time.as_millis() / 100;
+    format!(
+        "{:02}:{:02}.{}",
+        tenths / 600,
+        (tenths / 10) % 60,
+        tenths % 10
+    )
+}
+
+/// `YYYY-MM-DD` for a unix timestamp, in UTC.
+pub fn format_date(timestamp: u64) -> String {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+// UTILS
+
+// where everything that outlives a game is kept
+pub(crate) fn data_dir() -> Option<PathBuf> {
+    let data_dir = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_dir.join("svnake"))
+}
+
+fn load_or_default<T: Default + for<'de> Deserialize<'de>>(
+    path: impl AsRef<Path>,
+) -> Result<T, ScoreError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(T::default()),
+        Err(err) => return Err(err.into()),
+    };
+    ron::from_str(&text).map_err(|err| ScoreError::Format(err.to_string()))
+}
+
+fn save(value: &impl Serialize, path: impl AsRef<Path>) -> Result<(), ScoreError> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let text = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|err| ScoreError::Format(err.to_string()))?;
+    fs::write(path, text)?;
+    Ok(())
+}
+
+#[derive(Debug, Error)]
+pub enum ScoreError {
+    #[error("Could not access the high scores: {0}")]
+    Io(#[from] io::Error),
+    #[error("The high scores file is corrupted: {0}")]
+    Format(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameConfig, GameState};
+    use crate::level::Level;
+    use crate::timestep::FixedTimestep;
+
+    fn entry(score: u32, level: &str) -> ScoreEntry {
+        ScoreEntry {
+            score,
+            length: score as usize + 1,
+            level: level.to_string(),
+            date: 0,
+            initials: None,
+        }
+    }
+
+    // un fichier à part par test et par lancement, les tests tournant en même temps
+    fn temp_file(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("svnake-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn scores_go_in_order_ties_after() {
+        let mut table = ScoreTable::default();
+        assert_eq!(table.insert(entry(5, "a")), Some(0));
+        assert_eq!(table.insert(entry(9, "b")), Some(0));
+        assert_eq!(table.insert(entry(5, "c")), Some(2));
+        assert_eq!(table.insert(entry(1, "d")), Some(3));
+        let levels: Vec<&str> = table.entries().iter().map(|e| e.level.as_str()).collect();
+        assert_eq!(levels, ["b", "a", "c", "d"]);
+    }
+
+    #[test]
+    fn the_table_keeps_the_best_ten() {
+        let mut table = ScoreTable::default();
+        for score in 1..=MAX_ENTRIES as u32 {
+            table.insert(entry(score * 10, "level"));
+        }
+        assert!(!table.qualifies(10));
+        assert_eq!(table.insert(entry(10, "level")), None);
+        assert!(table.qualifies(11));
+        assert_eq!(table.insert(entry(55, "level")), Some(5));
+        assert_eq!(table.entries().len(), MAX_ENTRIES);
+        assert_eq!(table.entries().last().unwrap().score, 20);
+    }
+
+    #[test]
+    fn the_table_reads_back_what_was_written() {
+        let mut table = ScoreTable::default();
+        table.insert(entry(3, "Arena"));
+        table.insert(ScoreEntry {
+            initials: Some("ABS".to_string()),
+            ..entry(7, "Platform")
+        });
+        table.record_daily("2026-10-16", entry(4, "Daily"));
+        let path = temp_file("scores.ron");
+        table.save(&path).unwrap();
+        let loaded = ScoreTable::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), table);
+        assert_eq!(
+            ScoreTable::load(temp_file("missing.ron")).unwrap(),
+            ScoreTable::default()
+        );
+    }
+
+    #[test]
+    fn the_timer_only_counts_played_ticks() {
+        let level = Level::arena((6, 6, 2));
+        let config = GameConfig::default();
+        let mut game = GameState::new(level.start, level.grid, &config).unwrap();
+        let mut timestep = FixedTimestep::new(config.ticks_per_second);
+        // comme la boucle du jeu : le temps passe en pause mais aucun tick n'est joué
+        for frame in 0..25 {
+            let paused = (10..20).contains(&frame);
+            for _ in 0..timestep.steps(Duration::from_millis(100)) {
+                if !paused {
+                    game.update().unwrap();
+                }
+            }
+        }
+        assert_eq!(game.tick(), 30);
+        assert_eq!(format_time(game.time_played()), "00:01.5");
+        assert_eq!(format_time(Duration::from_millis(83_456)), "01:23.4");
+    }
+
+    #[test]
+    fn only_a_better_time_is_kept() {
+        let mut times = BestTimes::default();
+        let key = BestTimes::level_key("Arena", 0xabc);
+        let secs = Duration::from_secs;
+        assert!(times.record(&key, secs(30)));
+        assert!(!times.record(&key, secs(40)));
+        assert!(!times.record(&key, secs(30)));
+        assert_eq!(times.get(&key), Some(secs(30)));
+        assert!(times.record(&key, secs(20)));
+        assert_eq!(times.get(&key), Some(secs(20)));
+        // same name, another level
+        let other = BestTimes::level_key("Arena", 0xdef);
+        assert_eq!(times.get(&other), None);
+
+        let path = temp_file("best_times.ron");
+        times.save(&path).unwrap();
+        let loaded = BestTimes::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), times);
+    }
+}
@@ -0,0 +1,224 @@
+// Le résumé d'une partie, tenu à jour avec les événements et montré à la fin
+
+use std::time::Duration;
+
+use ruscii::{drawing::Pencil, spatial::Vec2, terminal::Color};
+
+use crate::game::{GameEvent, Vec3};
+use crate::scores::format_time;
+
+/// How many times a snake changed heading, from where its head went tick after tick.
+/// Only a step to a neighbor on the same layer has a heading, the rest (stairs, portals,
+/// the edges of a wrapping level, ticks it didn't move...) doesn't count as turning.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Turns {
+    count: u32,
+    head: Option<Vec3>,    // where the last move went
+    heading: Option<Vec3>, // the last step along the floor
+}
+
+impl Turns {
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn moved(&mut self, head: Vec3) {
+        let previous = self.head.replace(head);
+        let Some((px, py, pz)) = previous else {
+            return;
+        };
+        let step = (head.0 - px, head.1 - py, head.2 - pz);
+        if step.2 != 0 || step.0.abs() + step.1.abs() != 1 {
+            return;
+        }
+        if self.heading.is_some_and(|heading| heading != step) {
+            self.count += 1;
+        }
+        self.heading = Some(step);
+    }
+}
+
+/// What happened during a run, fed its events in order.
+/// Like the achievements it only makes sense with a single snake.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunStats {
+    pub ticks: u32, // ticks survived
+    pub foods: u32,
+    pub keys: u32,
+    pub fall: u32, // cells fallen, all falls put together
+    pub max_length: u32,
+    pub last_meal: Option<u32>, // the tick of the last food eaten
+    turns: Turns,
+    length: u32,
+}
+
+impl RunStats {
+    /// The size of [`RunStats::to_bytes`].
+    pub const ENCODED_LEN: usize = 7 * 4;
+
+    pub fn new(starting_length: usize) -> Self {
+        Self {
+            length: starting_length as u32,
+            max_length: starting_length as u32,
+            ..Self::default()
+        }
+    }
+
+    /// Counts `event`, which happened on `tick`.
+    pub fn observe(&mut self, tick: u32, event: &GameEvent) {
+        // le tick où le serpent meurt n'est pas un tick de survécu
+        self.ticks = match event {
+            GameEvent::Died(_) => self.ticks.max(tick),
+            _ => self.ticks.max(tick + 1),
+        };
+        match *event {
+            GameEvent::Moved { head } => self.turns.moved(head),
+            GameEvent::Ate { .. } => {
+                self.foods += 1;
+                self.length += 1;
+                self.max_length = self.max_length.max(self.length);
+                self.last_meal = Some(tick);
+            }
+            GameEvent::Unlocked { .. } => self.keys += 1,
+            GameEvent::Fell => self.fall += 1,
+            _ => (),
+        }
+    }
+
+    pub fn turns(&self) -> u32 {
+        self.turns.count
+    }
+
+    /// The ticks a food took on average, the first one counted from the start.
+    pub fn ticks_per_food(&self) -> Option<f32> {
+        let last = self.last_meal?;
+        Some((last + 1) as f32 / self.foods as f32)
+    }
+
+    /// The table's lines, a name and a value each, ticks lasting `tick`.
+    pub fn rows(&self, tick: Duration) -> Vec<(&'static str, String)> {
+        vec![
+            (
+                "Ticks survived",
+                format!("{} ({})", self.ticks, format_time(tick * self.ticks)),
+            ),
+            ("Food eaten", self.foods.to_string()),
+            ("Keys collected", self.keys.to_string()),
+            ("Cells fallen", self.fall.to_string()),
+            ("Turns", self.turns().to_string()),
+            ("Longest", self.max_length.to_string()),
+            (
+                "Between foods",
+                self.ticks_per_food().map_or("-".to_string(), |ticks| {
+                    format!("{ticks:.1} ticks ({})", format_time(tick.mul_f32(ticks)))
+                }),
+            ),
+        ]
+    }
+
+    /// The counters, little endian u32 each, no meal being `u32::MAX`.
+    /// Where the snake was heading isn't kept, only what the table shows.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [
+            self.ticks,
+            self.foods,
+            self.keys,
+            self.fall,
+            self.turns.count,
+            self.max_length,
+            self.last_meal.unwrap_or(u32::MAX),
+        ]
+        .iter()
+        .flat_map(|counter| counter.to_le_bytes())
+        .collect()
+    }
+
+    /// Reads back [`RunStats::to_bytes`], `bytes` being [`RunStats::ENCODED_LEN`] long.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let counter =
+            |idx: usize| u32::from_le_bytes(bytes[idx * 4..idx * 4 + 4].try_into().unwrap());
+        Self {
+            ticks: counter(0),
+            foods: counter(1),
+            keys: counter(2),
+            fall: counter(3),
+            turns: Turns {
+                count: counter(4),
+                ..Turns::default()
+            },
+            max_length: counter(5),
+            last_meal: Some(counter(6)).filter(|&tick| tick != u32::MAX),
+            length: counter(5),
+        }
+    }
+
+    /// The table, centered on `center` from row `top` down.
+    pub fn draw(&self, pencil: &mut Pencil, center: i32, top: i32, tick: Duration) {
+        for (row, (name, value)) in self.rows(tick).into_iter().enumerate() {
+            pencil.set_foreground(Color::Grey).draw_center_text(
+                &format!("{name:<16}{value:>20}"),
+                Vec2::xy(center, top + row as i32),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Direction, GameConfig, GameState};
+
+    // une partie jouée avec `inputs`, ses événements comptés au fur et à mesure
+    fn played(level: &str, inputs: &[Direction]) -> RunStats {
+        let grid = level.parse().unwrap();
+        let mut game = GameState::new((0, 0, 1), grid, &GameConfig::default()).unwrap();
+        game.enable_event_log();
+        let mut stats = RunStats::new(1);
+        for &input in inputs {
+            let died = game.step(input).is_err();
+            for (tick, event) in game.take_events() {
+                stats.observe(tick, &event);
+            }
+            if died {
+                break;
+            }
+        }
+        stats
+    }
+
+    #[test]
+    fn counters_follow_the_events() {
+        let level = "WWWW\nWWWW\nWWWW\n\n.F..\n..F.\n...F";
+        let (east, south) = (Direction::East, Direction::South);
+        let stats = played(level, &[east, south, east, south, east]);
+        assert_eq!(
+            (stats.foods, stats.max_length, stats.last_meal),
+            (3, 4, Some(4))
+        );
+        assert_eq!((stats.ticks, stats.turns()), (5, 3));
+        assert_eq!(stats.ticks_per_food(), Some(5.0 / 3.0));
+        // le cap n'est pas gardé, seulement ce que le tableau montre
+        let tick = Duration::from_millis(50);
+        assert_eq!(
+            RunStats::from_bytes(&stats.to_bytes()).rows(tick),
+            stats.rows(tick)
+        );
+    }
+
+    #[test]
+    fn frames_without_input_are_not_turns() {
+        let level = "WWWW\nWWWW\n\n...F\n....";
+        let none = Direction::None;
+        // il continue tout droit sans qu'on touche à rien, puis se cogne au bord
+        let stats = played(level, &[Direction::East, none, none, none]);
+        assert_eq!((stats.turns(), stats.foods), (0, 1));
+        assert_eq!(stats.ticks, 3);
+
+        // et un tick où la tête reste sur place ne change pas le cap
+        let mut turns = Turns::default();
+        for head in [(0, 0, 1), (1, 0, 1), (1, 0, 1), (2, 0, 1), (2, 1, 1)] {
+            turns.moved(head);
+        }
+        assert_eq!(turns.count(), 1);
+    }
+}
@@ -0,0 +1,191 @@
+use std::time::{Duration, Instant};
+
+/// Past this many ticks in a single frame we give up catching up, otherwise a
+/// long freeze (window dragged, terminal suspended...) would fast-forward the game.
+const MAX_STEPS_PER_FRAME: u32 = 5;
+const MIN_SPEED: f32 = 0.125;
+const MAX_SPEED: f32 = 4.0;
+
+/// Turns the real time elapsed between frames into a whole number of game ticks,
+/// so the game runs at the same speed whatever the frame rate is.
+#[derive(Debug)]
+pub struct FixedTimestep {
+    step: Duration,
+    accumulator: Duration, // time not yet spent on a tick
+    last_frame: Option<Instant>,
+    speed: f32, // slow motion / fast forward, for debugging
+    ramp: u32,  // percent faster the game has sped up to as the snake ate
+}
+
+impl FixedTimestep {
+    pub fn new(ticks_per_second: u32) -> Self {
+        Self {
+            step: Duration::from_secs(1) / ticks_per_second,
+            accumulator: Duration::ZERO,
+            last_frame: None,
+            speed: 1.0,
+            ramp: 0,
+        }
+    }
+
+    /// How long a tick lasts in game time.
+    pub fn tick_duration(&self) -> Duration {
+        self.step
+    }
+
+    /// Sets how fast game time goes compared to real time, clamped to something playable.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    pub fn faster(&mut self) {
+        self.set_speed(self.speed * 2.0);
+    }
+
+    pub fn slower(&mut self) {
+        self.set_speed(self.speed / 2.0);
+    }
+
+    /// Makes the ticks come `percent` faster than the tick rate, see `GameConfig::speedup_after`.
+    /// Like the speed it doesn't change how long a tick lasts in game time, the clock still counts ticks.
+    pub fn set_ramp(&mut self, percent: u32) {
+        self.ramp = percent;
+    }
+
+    /// How many times faster than the tick rate the ticks come, speed and ramp together.
+    pub fn pace(&self) -> f32 {
+        self.speed * (100 + self.ramp) as f32 / 100.0
+    }
+
+    /// How many ticks to run this frame, measured from the previous call.
+    pub fn tick(&mut self) -> u32 {
+        let now = Instant::now();
+        // the very first frame runs one tick so the game starts right away
+        let elapsed = self
+            .last_frame
+            .map_or(self.step, |last| now.duration_since(last));
+        self.last_frame = Some(now);
+        self.steps(elapsed.mul_f32(self.pace()))
+    }
+
+    /// How many ticks fit in `elapsed` (already scaled by the pace) plus what was left over last time.
+    pub fn steps(&mut self, elapsed: Duration) -> u32 {
+        self.accumulator += elapsed;
+        let steps = self.accumulator.as_nanos() / self.step.as_nanos();
+        if steps > MAX_STEPS_PER_FRAME as u128 {
+            self.accumulator = Duration::ZERO;
+            return MAX_STEPS_PER_FRAME;
+        }
+        let steps = steps as u32;
+        self.accumulator -= self.step * steps;
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Difficulty, Direction, GameConfig, GameState};
+    use crate::level::Level;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn frames_add_up_to_whole_ticks() {
+        // 20 ticks per second, a tick every 50ms
+        let mut timestep = FixedTimestep::new(20);
+        assert_eq!(timestep.steps(MS * 49), 0);
+        assert_eq!(timestep.steps(MS * 2), 1); // 1ms left over
+        assert_eq!(timestep.steps(MS * 100), 2); // still 1ms left over
+        assert_eq!(timestep.steps(MS * 49), 1);
+        assert_eq!(timestep.steps(Duration::ZERO), 0);
+        assert_eq!(timestep.steps(MS * 50 * 5), 5);
+    }
+
+    #[test]
+    fn a_long_freeze_is_not_caught_up() {
+        let mut timestep = FixedTimestep::new(20);
+        assert_eq!(timestep.steps(MS * 40), 0);
+        assert_eq!(timestep.steps(Duration::from_secs(10)), MAX_STEPS_PER_FRAME);
+        // what was left before the freeze goes with it
+        assert_eq!(timestep.steps(MS * 40), 0);
+        assert_eq!(timestep.steps(MS * 10), 1);
+    }
+
+    #[test]
+    fn the_speed_stays_within_bounds() {
+        let mut timestep = FixedTimestep::new(20);
+        timestep.set_speed(100.0);
+        assert_eq!(timestep.pace(), MAX_SPEED);
+        timestep.faster();
+        assert_eq!(timestep.pace(), MAX_SPEED);
+        timestep.set_speed(0.0);
+        assert_eq!(timestep.pace(), MIN_SPEED);
+        timestep.slower();
+        assert_eq!(timestep.pace(), MIN_SPEED);
+        // from the bounds back to normal
+        for _ in 0..3 {
+            timestep.faster();
+        }
+        assert_eq!(timestep.pace(), 1.0);
+        timestep.set_speed(MAX_SPEED);
+        timestep.slower();
+        timestep.slower();
+        assert_eq!(timestep.pace(), 1.0);
+    }
+
+    #[test]
+    fn a_paused_game_keeps_its_time() {
+        let config = GameConfig {
+            time_limit: Some(100),
+            ..GameConfig::default()
+        };
+        let level = Level::arena((6, 6, 2));
+        let mut game = GameState::new(level.start, level.grid, &config).unwrap();
+        let mut timestep = FixedTimestep::new(config.ticks_per_second);
+        // comme la boucle du jeu, qui ne joue pas les ticks de la frame en pause
+        let mut play = |game: &mut GameState, frames: u32, paused: bool| {
+            for _ in 0..frames {
+                for _ in 0..timestep.steps(MS * 100) {
+                    if !paused {
+                        game.update().unwrap();
+                    }
+                }
+            }
+        };
+        play(&mut game, 5, false);
+        assert_eq!(game.remaining_ticks(), Some(90));
+        play(&mut game, 50, true);
+        assert_eq!(game.remaining_ticks(), Some(90));
+        play(&mut game, 5, false);
+        assert_eq!(game.remaining_ticks(), Some(80));
+    }
+
+    #[test]
+    fn eating_speeds_the_ticks_up_to_the_cap() {
+        let config = Difficulty::Hard.to_config();
+        // une rangée de nourriture à manger d'affilée, et une au bout pour que ça continue
+        let strip = format!("{}\n\n.{}....F", "W".repeat(30), "F".repeat(24));
+        // combien de ticks en une seconde de frames de 10ms, après avoir mangé `foods` fois
+        let ticks_in_a_second = |foods: u32| {
+            let mut game = GameState::new((0, 0, 1), strip.parse().unwrap(), &config).unwrap();
+            for _ in 0..foods {
+                game.step(Direction::East).unwrap();
+            }
+            assert_eq!(game.foods_eaten(), foods);
+            let mut timestep = FixedTimestep::new(config.ticks_per_second);
+            // comme la boucle du jeu
+            timestep.set_ramp(game.speedup());
+            (0..100)
+                .map(|_| timestep.steps((MS * 10).mul_f32(timestep.pace())))
+                .sum::<u32>()
+        };
+        assert_eq!(ticks_in_a_second(0), 28);
+        assert_eq!(ticks_in_a_second(4), 33); // 20% plus vite
+        assert_eq!(ticks_in_a_second(20), 56); // deux fois plus vite, c'est le plafond
+        assert_eq!(ticks_in_a_second(24), 56);
+        // sans accélération, manger ne change rien
+        let normal = Difficulty::Normal.to_config();
+        assert_eq!(normal.speedup_after(50), 0);
+    }
+}
@@ -0,0 +1,80 @@
+// Des parties entières jouées sans terminal, sur des niveaux écrits ici
+
+use svnake::game::{Direction, GameConfig, GameError, GameState, Grid};
+
+// a 5x3 floor, the snake starts on the left of the middle row with three foods in front of it
+const CORRIDOR: &str = "WWWWW\nWWWWW\nWWWWW\n\n.....\n.FFF.\n.....";
+
+fn start(level: &str, start: (isize, isize, isize)) -> GameState {
+    let grid: Grid = level.parse().unwrap();
+    GameState::new(start, grid, &GameConfig::default()).unwrap()
+}
+
+#[test]
+fn eating_everything_completes_the_level() {
+    let mut game = start(CORRIDOR, (0, 1, 1));
+    let report = game.run_script([Direction::East; 10]);
+    assert!(game.is_complete());
+    assert_eq!(report.death, None);
+    assert_eq!(report.ticks, 3);
+    assert_eq!(report.score, 3);
+    assert_eq!(report.length, 4);
+    assert_eq!(game.head(), (3, 1, 1));
+}
+
+#[test]
+fn running_into_the_edge_ends_the_game() {
+    let mut game = start(CORRIDOR, (0, 1, 1));
+    let script = [Direction::North, Direction::East, Direction::North];
+    let report = game.run_script(script);
+    assert_eq!(report.ticks, 2);
+    assert_eq!(report.score, 0);
+    assert_eq!(
+        report.death,
+        Some(GameError::SnakeCollision {
+            head: (1, 0, 1),
+            attempted_move: (1, -1, 1),
+        })
+    );
+}
+
+// the second snake starts across from the first, facing it
+fn versus(level: &str, start: (isize, isize, isize), length: usize) -> GameState {
+    let config = GameConfig {
+        two_players: true,
+        starting_length: length,
+        starting_facing: Direction::East,
+        ..GameConfig::default()
+    };
+    GameState::new(start, level.parse().unwrap(), &config).unwrap()
+}
+
+#[test]
+fn two_heads_on_the_same_cell_is_a_draw() {
+    let mut game = versus("WWWWW\n\nF...F", (1, 0, 1), 1);
+    assert_eq!(game.head_of(1), (3, 0, 1));
+    game.queue_input_for(0, Direction::East);
+    game.queue_input_for(1, Direction::West);
+    assert_eq!(
+        game.update(),
+        Err(GameError::HitOtherSnake { head: (2, 0, 1) })
+    );
+    assert_eq!(game.winner(), None);
+}
+
+#[test]
+fn running_head_first_into_each_other_is_a_draw() {
+    for length in [1, 2] {
+        let mut game = versus("WWWWWW\n\nF....F", (2, 0, 1), length);
+        assert_eq!(game.head_of(1), (3, 0, 1));
+        // each one goes where the other's head was
+        game.queue_input_for(0, Direction::East);
+        game.queue_input_for(1, Direction::West);
+        assert_eq!(
+            game.update(),
+            Err(GameError::HitOtherSnake { head: (3, 0, 1) }),
+            "{length} long"
+        );
+        assert_eq!(game.winner(), None);
+    }
+}